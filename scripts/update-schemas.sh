@@ -0,0 +1,13 @@
+#!/usr/bin/env sh
+# Refreshes the copies of the New Horizons schemas bundled into the server,
+# they're used whenever the latest ones can't be fetched or cached.
+set -eu
+
+BASE_URL="https://raw.githubusercontent.com/Outer-Wilds-New-Horizons/new-horizons/main/NewHorizons/Schemas"
+OUT_DIR="$(dirname "$0")/../server/src/schemas"
+
+for schema in body_schema star_system_schema addon_manifest_schema; do
+    echo "Downloading $schema.json"
+    curl -fsSL "$BASE_URL/$schema.json" -o "$OUT_DIR/$schema.json.tmp"
+    mv "$OUT_DIR/$schema.json.tmp" "$OUT_DIR/$schema.json"
+done
@@ -0,0 +1,9 @@
+/// Conditions the base game sets itself, dialogue can require these without ever setting them.
+/// Add to this as more are found, the array length has to match.
+const VANILLA_CONDITIONS: [&str; 5] = [
+  "LAUNCH_CODES_GIVEN",
+  "KNOWS_MEDITATION",
+  "MET_SOLANUM",
+  "MET_PRISONER",
+  "PLAYER_ENTERED_TIMELOOPCORE",
+];
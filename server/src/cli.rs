@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, VersionedTextDocumentIdentifier};
+
+use crate::{
+    file_paths::SchemaFetch,
+    project::{exclude_patterns, Project},
+    validation::{ErrorSet, MainValidator, ValidationOptions},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct CheckOptions {
+    pub path: PathBuf,
+    pub json: bool,
+    /// Fail on warnings too
+    pub strict: bool,
+}
+
+impl CheckOptions {
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut path = None;
+        let mut json = false;
+        let mut strict = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--strict" => strict = true,
+                "--format" => match args.next().map(|s| s.as_str()) {
+                    Some("json") => json = true,
+                    Some("text") => json = false,
+                    other => return Err(anyhow!("Unknown output format: {other:?}")),
+                },
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => return Err(anyhow!("Unexpected argument: {arg}")),
+            }
+        }
+        Ok(Self {
+            path: path.unwrap_or_else(|| PathBuf::from(".")),
+            json,
+            strict,
+        })
+    }
+}
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    }
+}
+
+pub fn format_diagnostic(
+    root: &Path,
+    file: &VersionedTextDocumentIdentifier,
+    diagnostic: &Diagnostic,
+) -> String {
+    let path = file
+        .uri
+        .to_file_path()
+        .map(|p| {
+            p.strip_prefix(root)
+                .map(|p| p.to_owned())
+                .unwrap_or(p)
+                .to_string_lossy()
+                .to_string()
+        })
+        .unwrap_or_else(|_| file.uri.to_string());
+    let code = match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code.clone(),
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        None => "-".to_string(),
+    };
+    format!(
+        "{path}:{}:{}: {} {code} {}",
+        diagnostic.range.start.line + 1,
+        diagnostic.range.start.character + 1,
+        severity_name(diagnostic.severity),
+        diagnostic.message
+    )
+}
+
+pub fn exit_code(errors: &ErrorSet, strict: bool) -> i32 {
+    let failed = errors.iter().any(|(_, d)| {
+        let severity = d.severity.unwrap_or(DiagnosticSeverity::ERROR);
+        severity == DiagnosticSeverity::ERROR || (strict && severity == DiagnosticSeverity::WARNING)
+    });
+    i32::from(failed)
+}
+
+/// Validates the mod at `path`, along with the absolute path it was loaded from
+pub fn check_project(path: &Path, options: &ValidationOptions) -> Result<(PathBuf, ErrorSet)> {
+    // File URLs can only be made from absolute paths
+    let root = path
+        .canonicalize()
+        .map_err(|why| anyhow!("Can't check {}: {why}", path.display()))?;
+    if !root.is_dir() {
+        return Err(anyhow!("{} isn't a folder", path.display()));
+    }
+    let mut project = Project {
+        excludes: exclude_patterns(&root, &options.exclude_globs, options.use_gitignore),
+        ..Default::default()
+    };
+    project.load_from(&root);
+    // There's no later run to pick up the latest schemas, so wait for them
+    let validator = MainValidator::new(options, SchemaFetch::Blocking);
+    let mut errors = validator.validate_all(&project);
+    errors.sort_by_key(|(file, d)| (file.uri.clone(), d.range.start));
+    Ok((root, errors))
+}
+
+pub fn check(args: &[String]) -> Result<i32> {
+    let options = CheckOptions::parse(args)?;
+    let (root, errors) = check_project(&options.path, &ValidationOptions::default())?;
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&errors)?);
+    } else {
+        for (file, diagnostic) in errors.iter() {
+            println!("{}", format_diagnostic(&root, file, diagnostic));
+        }
+    }
+    Ok(exit_code(&errors, options.strict))
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{Position, Range, Url};
+
+    use crate::utils::error_codes::{self, get_error_code};
+
+    use super::*;
+
+    fn error(severity: DiagnosticSeverity) -> (VersionedTextDocumentIdentifier, Diagnostic) {
+        (
+            VersionedTextDocumentIdentifier::new(
+                Url::parse("file:///mod/planets/test.json").unwrap(),
+                0,
+            ),
+            Diagnostic {
+                range: Range::new(Position::new(2, 4), Position::new(2, 8)),
+                severity: Some(severity),
+                code: get_error_code(error_codes::JSON_SYNTAX_ERROR),
+                message: "Invalid JSON".to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_check_options() {
+        let args = ["mod", "--format", "json", "--strict"].map(String::from);
+        assert_eq!(
+            CheckOptions::parse(&args).unwrap(),
+            CheckOptions {
+                path: PathBuf::from("mod"),
+                json: true,
+                strict: true,
+            }
+        );
+        assert!(CheckOptions::parse(&["--format".to_string()]).is_err());
+        assert!(CheckOptions::parse(&["a", "b"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_format_diagnostic() {
+        let (file, diagnostic) = error(DiagnosticSeverity::ERROR);
+        assert_eq!(
+            format_diagnostic(Path::new("/mod"), &file, &diagnostic),
+            "planets/test.json:3:5: error nh.json.syntax_error Invalid JSON"
+        );
+    }
+
+    #[test]
+    fn test_check_relative_path() {
+        // Tests run from the crate root
+        let path = Path::new("src/test_files/broken_mod");
+        let (root, errors) = check_project(path, &ValidationOptions::default()).unwrap();
+        assert!(root.is_absolute());
+        assert!(!errors.is_empty());
+        assert_eq!(
+            format_diagnostic(&root, &errors[0].0, &errors[0].1),
+            "planets/Broken.json:4:1: error nh.json.syntax_error Invalid JSON: expected value at line 4 column 1"
+        );
+
+        assert!(
+            check_project(Path::new("src/test_files/missing_mod"), &Default::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let warnings = vec![error(DiagnosticSeverity::WARNING)];
+        assert_eq!(exit_code(&warnings, false), 0);
+        assert_eq!(exit_code(&warnings, true), 1);
+        assert_eq!(exit_code(&vec![error(DiagnosticSeverity::ERROR)], false), 1);
+        assert_eq!(exit_code(&vec![], true), 0);
+    }
+}
@@ -0,0 +1,268 @@
+use json_position_parser::tree::PathType;
+use lsp_types::{Color, ColorInformation, ColorPresentation, Range, TextEdit};
+use serde_json::{Map, Value};
+
+use crate::utils::{json_idx_to_offset, json_path_to_json_pos_path, offset_to_lsp_position};
+
+const COMPONENTS: [&str; 4] = ["r", "g", "b", "a"];
+
+/// An `{ "r": .., "g": .., "b": .., "a": .. }` object in a JSON file, ranges are byte offsets
+#[derive(Debug)]
+pub struct JsonColor {
+    pub start: usize,
+    pub end: usize,
+    /// 0-1 floats instead of 0-255 ints
+    pub is_float: bool,
+    /// Value and value range of each component that's present, in `COMPONENTS` order
+    pub components: Vec<(&'static str, f64, std::ops::Range<usize>)>,
+}
+
+impl JsonColor {
+    fn parse(text: &str, start: usize, end: usize) -> Option<Self> {
+        let object_text = &text[start..end];
+        let tree = json_position_parser::parse_json(object_text).ok()?;
+        let mut components = vec![];
+        for component in COMPONENTS {
+            let Some(found) = tree
+                .value_at(&[PathType::Object(component)])
+                .first()
+                .cloned()
+            else {
+                continue;
+            };
+            let range = (start + json_idx_to_offset(object_text, found.range.start.idx))
+                ..(start + json_idx_to_offset(object_text, found.range.end.idx));
+            let value = text[range.clone()].trim().parse::<f64>().ok()?;
+            components.push((component, value, range));
+        }
+        let is_float = components
+            .iter()
+            .any(|(_, _, range)| text[range.clone()].contains(['.', 'e', 'E']));
+        Some(Self {
+            start,
+            end,
+            is_float,
+            components,
+        })
+    }
+
+    fn component(&self, name: &str) -> Option<f64> {
+        self.components
+            .iter()
+            .find(|(component, _, _)| *component == name)
+            .map(|(_, value, _)| *value)
+    }
+
+    fn normalize(&self, value: f64) -> f32 {
+        let value = if self.is_float { value } else { value / 255.0 };
+        value.clamp(0.0, 1.0) as f32
+    }
+
+    pub fn color(&self) -> Color {
+        Color {
+            red: self.normalize(self.component("r").unwrap_or_default()),
+            green: self.normalize(self.component("g").unwrap_or_default()),
+            blue: self.normalize(self.component("b").unwrap_or_default()),
+            alpha: self
+                .component("a")
+                .map(|a| self.normalize(a))
+                .unwrap_or(1.0),
+        }
+    }
+
+    pub fn range(&self, text: &str) -> Range {
+        Range::new(
+            offset_to_lsp_position(text, self.start),
+            offset_to_lsp_position(text, self.end),
+        )
+    }
+
+    fn format_component(&self, value: f32) -> String {
+        if self.is_float {
+            let formatted = format!("{:.3}", value);
+            let trimmed = formatted.trim_end_matches('0');
+            if trimmed.ends_with('.') {
+                format!("{trimmed}0")
+            } else {
+                trimmed.to_string()
+            }
+        } else {
+            ((value * 255.0).round() as u8).to_string()
+        }
+    }
+
+    /// The object's text with each component swapped for `color`, keeping its layout
+    pub fn with_color(&self, text: &str, color: &Color) -> String {
+        let mut edited = text[self.start..self.end].to_string();
+        let new_value = |name: &str| match name {
+            "r" => color.red,
+            "g" => color.green,
+            "b" => color.blue,
+            _ => color.alpha,
+        };
+        if self.component("a").is_none() && color.alpha < 1.0 {
+            if let Some((_, _, range)) = self.components.iter().find(|(c, _, _)| *c == "b") {
+                let alpha = format!(", \"a\": {}", self.format_component(color.alpha));
+                edited.insert_str(range.end - self.start, &alpha);
+            }
+        }
+        for (name, _, range) in self.components.iter().rev() {
+            let range = (range.start - self.start)..(range.end - self.start);
+            edited.replace_range(range, &self.format_component(new_value(name)));
+        }
+        edited
+    }
+}
+
+fn is_color(map: &Map<String, Value>) -> bool {
+    COMPONENTS.iter().all(|component| {
+        map.get(*component)
+            .map(Value::is_number)
+            .unwrap_or(*component == "a")
+    })
+}
+
+/// Every path to a color object, array items are wildcards
+fn color_paths(value: &Value, path: String, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if is_color(map) && !paths.contains(&path) {
+                paths.push(path.clone());
+            }
+            for (key, value) in map.iter() {
+                color_paths(value, format!("{path}/{key}"), paths);
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter() {
+                color_paths(value, format!("{path}/*"), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn find_colors(text: &str) -> Vec<JsonColor> {
+    let (Ok(value), Ok(tree)) = (
+        serde_json::from_str::<Value>(text),
+        json_position_parser::parse_json(text),
+    ) else {
+        return vec![];
+    };
+    let mut paths = vec![];
+    color_paths(&value, String::new(), &mut paths);
+    let mut colors = paths
+        .iter()
+        .flat_map(|path| tree.value_at(&json_path_to_json_pos_path(path)))
+        .filter_map(|found| {
+            JsonColor::parse(
+                text,
+                json_idx_to_offset(text, found.range.start.idx),
+                json_idx_to_offset(text, found.range.end.idx),
+            )
+        })
+        // Wildcards can also match objects in the same array that aren't colors
+        .filter(|color| color.components.len() >= 3)
+        .collect::<Vec<_>>();
+    colors.sort_by_key(|color| color.start);
+    colors
+}
+
+pub fn document_colors(text: &str) -> Vec<ColorInformation> {
+    find_colors(text)
+        .into_iter()
+        .map(|color| ColorInformation {
+            range: color.range(text),
+            color: color.color(),
+        })
+        .collect()
+}
+
+pub fn color_presentations(text: &str, range: Range, color: &Color) -> Vec<ColorPresentation> {
+    find_colors(text)
+        .into_iter()
+        .filter(|found| found.range(text) == range)
+        .map(|found| {
+            let label = ["r", "g", "b", "a"]
+                .iter()
+                .zip([color.red, color.green, color.blue, color.alpha])
+                .map(|(name, value)| format!("{name}: {}", found.format_component(value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ColorPresentation {
+                label,
+                text_edit: Some(TextEdit::new(range, found.with_color(text, color))),
+                additional_text_edits: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_int_and_float_colors() {
+        let text = r#"{
+  "Atmosphere": { "atmosphereTint": { "r": 255, "g": 0, "b": 51, "a": 255 } },
+  "Base": { "lightTint": {"r": 0.5, "g": 1.0, "b": 0.25} },
+  "name": "Test"
+}"#;
+        let colors = document_colors(text);
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].range.start, lsp_types::Position::new(1, 36));
+        assert_eq!(colors[0].color.red, 1.0);
+        assert_eq!(colors[0].color.blue, 0.2);
+        assert_eq!(colors[0].color.alpha, 1.0);
+        assert_eq!(colors[1].range.start.line, 2);
+        assert_eq!(colors[1].color.red, 0.5);
+        assert_eq!(colors[1].color.alpha, 1.0);
+    }
+
+    #[test]
+    fn test_find_colors_in_nested_arrays() {
+        let text = r#"{"curiosities": [
+    {"id": "A", "color": {"r": 10, "g": 20, "b": 30}},
+    {"id": "B", "color": "not a color"},
+    {"id": "C", "color": {"r": 40, "g": 50, "b": 60}, "highlightColor": {"r": 1, "g": 2, "b": 3}}
+], "rings": [[{"r": 0.0, "g": 0.0, "b": 0.0}]]}"#;
+        let colors = find_colors(text);
+        assert_eq!(colors.len(), 4);
+        assert_eq!(colors[0].component("r"), Some(10.0));
+        assert_eq!(colors[1].component("r"), Some(40.0));
+        assert_eq!(colors[2].component("r"), Some(1.0));
+        assert!(colors[3].is_float);
+    }
+
+    #[test]
+    fn test_color_presentations() {
+        let text = r#"{"color": {"r": 255, "g": 0, "b": 0}, "tint": {
+    "r": 1.0,
+    "g": 1.0,
+    "b": 1.0,
+    "a": 1.0
+}}"#;
+        let colors = document_colors(text);
+        let color = Color {
+            red: 0.0,
+            green: 0.5,
+            blue: 1.0,
+            alpha: 0.5,
+        };
+
+        let presentations = color_presentations(text, colors[0].range, &color);
+        assert_eq!(presentations.len(), 1);
+        assert_eq!(
+            presentations[0].text_edit.as_ref().unwrap().new_text,
+            r#"{"r": 0, "g": 128, "b": 255, "a": 128}"#
+        );
+
+        let presentations = color_presentations(text, colors[1].range, &color);
+        assert_eq!(
+            presentations[0].text_edit.as_ref().unwrap().new_text,
+            "{\n    \"r\": 0.0,\n    \"g\": 0.5,\n    \"b\": 1.0,\n    \"a\": 0.5\n}"
+        );
+        assert_eq!(presentations[0].label, "r: 0.0, g: 0.5, b: 1.0, a: 0.5");
+    }
+}
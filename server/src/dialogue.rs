@@ -0,0 +1,501 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DiagnosticTag, Url, VersionedTextDocumentIdentifier,
+};
+use roxmltree::Document;
+use serde_json::Value;
+
+use crate::{
+    project::Project,
+    ship_log::{find_duplicate_ids, related_duplicates, IdSet, ShipLogContext, ID},
+    utils::error_codes::{self, get_error_code},
+    validation::{ErrorSet, Validator},
+};
+
+const FACT_REFERENCE_TAGS: [&str; 2] = ["DialogueTargetShipLogCondition", "FactID"];
+
+const CONDITION_SET_TAGS: [&str; 3] = ["SetCondition", "ConditionToSet", "SetPersistentCondition"];
+
+const CONDITION_REFERENCE_TAGS: [&str; 5] = [
+    "DialogueCondition",
+    "RequiredCondition",
+    "CancelledCondition",
+    "RequiredPersistentCondition",
+    "CancelledPersistentCondition",
+];
+
+/// Planet config properties that set a condition
+const PLANET_CONDITION_SET_KEYS: [&str; 3] =
+    ["condition", "setCondition", "setPersistentCondition"];
+
+/// Planet config properties that check a condition
+const PLANET_CONDITION_REFERENCE_KEYS: [&str; 3] = [
+    "activationCondition",
+    "deactivationCondition",
+    "blockAfterPersistentCondition",
+];
+
+include!("base_game_conditions.rs");
+
+/// Every string value of the given keys, anywhere in `value`
+fn collect_strings(value: &Value, keys: &[&str], out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter() {
+                match value {
+                    Value::String(s) if keys.contains(&key.as_str()) => out.push(s.clone()),
+                    _ => collect_strings(value, keys, out),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter() {
+                collect_strings(value, keys, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct DialogueContext {
+    pub fact_references: IdSet,
+    pub revealed_facts: IdSet,
+    pub node_names: IdSet,
+    pub target_references: IdSet,
+    pub set_conditions: IdSet,
+    pub condition_references: IdSet,
+    pub entry_conditions: IdSet,
+    /// Conditions set and checked by planet configs, they aren't validated themselves
+    pub planet_set_conditions: Vec<String>,
+    pub planet_condition_references: Vec<String>,
+}
+
+impl DialogueContext {
+    pub fn parse(
+        &mut self,
+        dialogue_file: &VersionedTextDocumentIdentifier,
+        raw_str: &str,
+    ) -> Result<()> {
+        let tree = Document::parse(raw_str)?;
+        for node in tree
+            .descendants()
+            .filter(|n| FACT_REFERENCE_TAGS.contains(&n.tag_name().name()))
+        {
+            self.fact_references
+                .push(ID::new(&tree, &node, dialogue_file));
+        }
+        for node in tree.descendants() {
+            match node.tag_name().name() {
+                "Name"
+                    if node
+                        .parent_element()
+                        .map(|p| p.has_tag_name("DialogueNode"))
+                        == Some(true) =>
+                {
+                    self.node_names.push(ID::new(&tree, &node, dialogue_file));
+                }
+                "FactID"
+                    if node.parent_element().map(|p| p.has_tag_name("RevealFacts"))
+                        == Some(true) =>
+                {
+                    self.revealed_facts
+                        .push(ID::new(&tree, &node, dialogue_file));
+                }
+                "DialogueTarget" => {
+                    self.target_references
+                        .push(ID::new(&tree, &node, dialogue_file));
+                }
+                tag if CONDITION_SET_TAGS.contains(&tag) => {
+                    self.set_conditions
+                        .push(ID::new(&tree, &node, dialogue_file));
+                }
+                "EntryCondition" => {
+                    self.entry_conditions
+                        .push(ID::new(&tree, &node, dialogue_file));
+                }
+                tag if CONDITION_REFERENCE_TAGS.contains(&tag) => {
+                    self.condition_references
+                        .push(ID::new(&tree, &node, dialogue_file));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_project(project: &Project) -> Self {
+        let mut ctx = Self::default();
+        for file in project.dialogue_files.iter() {
+            if let Err(why) = ctx.parse(&file.id, &file.contents) {
+                eprintln!("Error parsing dialogue file: {why:?}");
+            }
+        }
+        for file in project.planet_files.iter() {
+            ctx.parse_planet(&file.json());
+        }
+        ctx
+    }
+
+    pub fn parse_planet(&mut self, contents: &str) {
+        if let Ok(planet) = serde_json::from_str::<Value>(contents) {
+            collect_strings(
+                &planet,
+                &PLANET_CONDITION_SET_KEYS,
+                &mut self.planet_set_conditions,
+            );
+            collect_strings(
+                &planet,
+                &PLANET_CONDITION_REFERENCE_KEYS,
+                &mut self.planet_condition_references,
+            );
+        }
+    }
+
+    fn validate_targets(&self, errors: &mut ErrorSet) {
+        for reference in self.target_references.iter() {
+            let exists = self.node_names.iter().any(|name| {
+                name.source_file.uri == reference.source_file.uri && name.value == reference.value
+            });
+            if !exists {
+                errors.push((
+                    reference.source_file.clone(),
+                    Diagnostic {
+                        range: reference.range,
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: get_error_code(error_codes::DIALOGUE_MISSING_TARGET),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message: format!("Unknown Dialogue Node: `{}`", reference.value),
+                        related_information: None,
+                        tags: None,
+                        data: None,
+                    },
+                ))
+            }
+        }
+    }
+
+    fn validate_duplicate_nodes(&self, errors: &mut ErrorSet) {
+        let groups = find_duplicate_ids(&self.node_names, |id| {
+            (id.source_file.uri.clone(), id.value.clone())
+        });
+        for (group, id) in groups
+            .iter()
+            .flat_map(|group| group.iter().map(move |id| (group, id)))
+        {
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: get_error_code(error_codes::DIALOGUE_DUPLICATE_NODE),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message: format!("Duplicate Dialogue Node: `{}`", id.value),
+                    related_information: Some(related_duplicates(group, id)),
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    fn push_condition_error(
+        errors: &mut ErrorSet,
+        id: &ID,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+        tags: Option<Vec<DiagnosticTag>>,
+    ) {
+        errors.push((
+            id.source_file.clone(),
+            Diagnostic {
+                range: id.value_range,
+                severity: Some(severity),
+                code: get_error_code(code),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message,
+                related_information: None,
+                tags,
+                data: None,
+            },
+        ))
+    }
+
+    fn validate_conditions(&self, errors: &mut ErrorSet) {
+        let set = self
+            .set_conditions
+            .iter()
+            .map(|id| id.value.as_str())
+            .chain(self.planet_set_conditions.iter().map(|c| c.as_str()))
+            .chain(VANILLA_CONDITIONS)
+            .collect::<HashSet<_>>();
+        let used = self
+            .condition_references
+            .iter()
+            .chain(self.entry_conditions.iter())
+            .map(|id| id.value.as_str())
+            .chain(self.planet_condition_references.iter().map(|c| c.as_str()))
+            .collect::<HashSet<_>>();
+        for reference in self.condition_references.iter() {
+            if !set.contains(reference.value.as_str()) {
+                Self::push_condition_error(
+                    errors,
+                    reference,
+                    // Conditions can also be set by the game itself
+                    DiagnosticSeverity::WARNING,
+                    error_codes::DIALOGUE_CONDITION_NEVER_SET,
+                    format!("Condition `{}` is never set", reference.value),
+                    None,
+                );
+            }
+        }
+        for condition in self.set_conditions.iter() {
+            if !used.contains(condition.value.as_str()) {
+                Self::push_condition_error(
+                    errors,
+                    condition,
+                    DiagnosticSeverity::INFORMATION,
+                    error_codes::DIALOGUE_CONDITION_UNUSED,
+                    format!("Condition `{}` is set but never used", condition.value),
+                    Some(vec![DiagnosticTag::UNNECESSARY]),
+                );
+            }
+        }
+    }
+
+    pub fn validate(&self, ship_logs: &ShipLogContext) -> ErrorSet {
+        let mut errors: ErrorSet = vec![];
+        self.validate_targets(&mut errors);
+        self.validate_duplicate_nodes(&mut errors);
+        self.validate_conditions(&mut errors);
+        for reference in self.fact_references.iter() {
+            if !ship_logs.fact_exists(&reference.value) {
+                let message = format!("Unknown Fact: `{}`", reference.value);
+                errors.push((
+                    reference.source_file.clone(),
+                    Diagnostic {
+                        range: reference.range,
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: get_error_code(error_codes::DIALOGUE_INVALID_FACT),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message,
+                        related_information: None,
+                        tags: None,
+                        data: None,
+                    },
+                ))
+            }
+        }
+        errors
+    }
+}
+
+#[derive(Default)]
+pub struct DialogueValidator();
+
+impl Validator for DialogueValidator {
+    fn name() -> &'static str {
+        "dialogue"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .dialogue_files
+            .iter()
+            .chain(project.ship_log_files.iter())
+            .chain(project.planet_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        DialogueContext::from_project(project).validate(&project.ship_logs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::project::ProjectFile;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_invalid_fact() {
+        const SHIP_LOG_STR: &str = include_str!("test_files/test_ship_log.xml");
+        const TEST_STR: &str = include_str!("test_files/dialogue_invalid_fact.xml");
+
+        let mut ship_logs = ShipLogContext::default();
+        let log_file =
+            VersionedTextDocumentIdentifier::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ship_logs
+            .parse(
+                &log_file,
+                &ProjectFile::dummy(),
+                Path::new("."),
+                SHIP_LOG_STR,
+            )
+            .unwrap();
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+
+        assert_eq!(ctx.fact_references.len(), 4);
+        assert_eq!(ctx.revealed_facts.len(), 2);
+
+        let errors = ctx.validate(&ship_logs);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].1.message,
+            "Unknown Fact: `MISSING_CONDITION_FACT`"
+        );
+        assert_eq!(errors[1].1.message, "Unknown Fact: `MISSING_REVEAL_FACT`");
+    }
+
+    #[test]
+    fn test_validate_missing_target() {
+        const TEST_STR: &str = include_str!("test_files/dialogue_missing_target.xml");
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+
+        assert_eq!(ctx.node_names.len(), 2);
+        assert_eq!(ctx.target_references.len(), 3);
+
+        let errors = ctx.validate(&ShipLogContext::default());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Unknown Dialogue Node: `GOODBYE`");
+        assert_eq!(errors[0].1.range.start.line, 17);
+    }
+
+    #[test]
+    fn test_validate_duplicate_nodes() {
+        const TEST_STR: &str = include_str!("test_files/dialogue_duplicate_node.xml");
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+        let other_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://other_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(
+            &other_file,
+            TEST_STR
+                .replace("<Name>START</Name>", "<Name>OTHER</Name>")
+                .as_str(),
+        )
+        .unwrap();
+
+        let errors = ctx.validate(&ShipLogContext::default());
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors
+            .iter()
+            .all(|e| e.1.message == "Duplicate Dialogue Node: `START`"
+                || e.1.message == "Duplicate Dialogue Node: `OTHER`"));
+        assert_eq!(errors[0].1.range.start.line, 5);
+        assert_eq!(errors[1].1.range.start.line, 13);
+        let related = errors[0].1.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].location.range, errors[1].1.range);
+    }
+
+    #[test]
+    fn test_validate_unknown_condition() {
+        const TEST_STR: &str = include_str!("test_files/dialogue_conditions.xml");
+        const OTHER_STR: &str = include_str!("test_files/dialogue_set_conditions.xml");
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+        let other_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://other_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&other_file, OTHER_STR).unwrap();
+
+        let errors = ctx.validate(&ShipLogContext::default());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Condition `NEVER_SET` is never set");
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::DIALOGUE_CONDITION_NEVER_SET)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(errors[0].1.range.start.character, 35);
+        assert_eq!(errors[0].0.uri, test_file.uri);
+    }
+
+    #[test]
+    fn test_validate_unused_condition() {
+        const TEST_STR: &str = include_str!("test_files/dialogue_condition_unused.xml");
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+        ctx.parse_planet(r#"{"Props": {"details": [{"activationCondition": "OPENS_DOOR"}]}}"#);
+
+        let errors = ctx.validate(&ShipLogContext::default());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.message,
+            "Condition `NEVER_USED` is set but never used"
+        );
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::DIALOGUE_CONDITION_UNUSED)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::INFORMATION));
+        assert_eq!(errors[0].1.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+
+    #[test]
+    fn test_planet_sets_condition() {
+        const TEST_STR: &str = include_str!("test_files/dialogue_conditions.xml");
+
+        let mut ctx = DialogueContext::default();
+        let test_file = VersionedTextDocumentIdentifier::new(
+            Url::parse("file://test_dialogue.xml").unwrap(),
+            0,
+        );
+        ctx.parse(&test_file, TEST_STR).unwrap();
+        ctx.parse_planet(
+            r#"{"Volumes": {"conditionTriggerVolumes": [{"condition": "NEVER_SET"}, {"setPersistentCondition": "HEARD_STORY"}]}}"#,
+        );
+
+        assert!(ctx.validate(&ShipLogContext::default()).is_empty());
+    }
+}
@@ -1,41 +1,266 @@
-use json_position_parser::tree::EntryType;
-use lsp_types::{Diagnostic, DiagnosticSeverity};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    env, fs,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+use json_position_parser::{tree::EntryType, types::Range as JSONRange};
+use lsp_types::{Diagnostic, DiagnosticSeverity, VersionedTextDocumentIdentifier};
 use serde_json::Value;
 
 use crate::{
+    manifest::ADDON_MANIFEST_FILE,
     project::{Project, ProjectFile},
     utils::{
         error_codes::{self, get_error_code},
-        find_paths_with_x_prop, json_path_to_json_pos_path, json_pos_range_to_diag_range,
+        index_schema, json_path_to_json_pos_path, json_pos_range_to_diag_range, SchemaIndex,
     },
-    validation::{ErrorSet, Validator},
+    validation::{ErrorSet, ValidationOptions, Validator},
 };
 
-type JsonPathSet = Vec<String>;
+const BODY_SCHEMA_URL: &str = "https://raw.githubusercontent.com/Outer-Wilds-New-Horizons/new-horizons/main/NewHorizons/Schemas/body_schema.json";
+const BUNDLED_BODY_SCHEMA: &str = include_str!("schemas/body_schema.json");
+const SYSTEM_SCHEMA_URL: &str = "https://raw.githubusercontent.com/Outer-Wilds-New-Horizons/new-horizons/main/NewHorizons/Schemas/star_system_schema.json";
+const BUNDLED_SYSTEM_SCHEMA: &str = include_str!("schemas/star_system_schema.json");
+const ADDON_MANIFEST_SCHEMA_URL: &str = "https://raw.githubusercontent.com/Outer-Wilds-New-Horizons/new-horizons/main/NewHorizons/Schemas/addon_manifest_schema.json";
+const BUNDLED_ADDON_MANIFEST_SCHEMA: &str = include_str!("schemas/addon_manifest_schema.json");
+
+const SCHEMA_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ConfigType {
+    Body,
+    System,
+    AddonManifest,
+}
+
+impl ConfigType {
+    const ALL: [ConfigType; 3] = [
+        ConfigType::Body,
+        ConfigType::System,
+        ConfigType::AddonManifest,
+    ];
+
+    fn schema_url<'a>(&self, options: &'a ValidationOptions) -> &'a str {
+        match self {
+            ConfigType::Body => options
+                .body_schema_url
+                .as_deref()
+                .unwrap_or(BODY_SCHEMA_URL),
+            ConfigType::System => options
+                .system_schema_url
+                .as_deref()
+                .unwrap_or(SYSTEM_SCHEMA_URL),
+            ConfigType::AddonManifest => options
+                .addon_manifest_schema_url
+                .as_deref()
+                .unwrap_or(ADDON_MANIFEST_SCHEMA_URL),
+        }
+    }
+
+    fn bundled_schema(&self) -> &'static str {
+        match self {
+            ConfigType::Body => BUNDLED_BODY_SCHEMA,
+            ConfigType::System => BUNDLED_SYSTEM_SCHEMA,
+            ConfigType::AddonManifest => BUNDLED_ADDON_MANIFEST_SCHEMA,
+        }
+    }
+
+    fn files<'a>(&self, project: &'a Project) -> Vec<&'a ProjectFile> {
+        match self {
+            ConfigType::Body => project.planet_files.iter().map(|f| f.as_ref()).collect(),
+            ConfigType::System => project.system_files.iter().map(|f| f.as_ref()).collect(),
+            // The other meta files aren't New Horizons configs
+            ConfigType::AddonManifest => project
+                .meta_files
+                .iter()
+                .filter(|f| {
+                    f.nice_path.file_name().and_then(|n| n.to_str()) == Some(ADDON_MANIFEST_FILE)
+                })
+                .map(|f| f.as_ref())
+                .collect(),
+        }
+    }
+}
+
+type SchemaIndexMap = HashMap<ConfigType, SchemaIndex>;
+
+/// Where the latest schemas come from, tests never touch the network or the real cache
+#[derive(Clone, Debug)]
+pub struct SchemaSource {
+    pub fetch: fn(&str) -> Option<String>,
+    /// Fetched schemas are kept here between runs, `None` skips caching
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for SchemaSource {
+    #[cfg(not(test))]
+    fn default() -> Self {
+        let cache_dir = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+        Self {
+            fetch: Self::fetch_from_network,
+            cache_dir: cache_dir.map(|dir| dir.join("nh-language-server").join("schemas")),
+        }
+    }
+
+    #[cfg(test)]
+    fn default() -> Self {
+        Self {
+            fetch: |_| None,
+            cache_dir: None,
+        }
+    }
+}
+
+impl SchemaSource {
+    #[cfg_attr(test, allow(dead_code))]
+    fn fetch_from_network(url: &str) -> Option<String> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(SCHEMA_FETCH_TIMEOUT)
+            .build()
+            .ok()?;
+        let res = client.get(url).send().and_then(|r| r.error_for_status());
+        match res.and_then(|r| r.text()) {
+            Ok(text) => Some(text),
+            Err(why) => {
+                eprintln!("Failed to fetch schema {url}: {why:?}");
+                None
+            }
+        }
+    }
+
+    fn cache_path(&self, url: &str) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        Some(
+            self.cache_dir
+                .as_ref()?
+                .join(format!("{:x}.json", hasher.finish())),
+        )
+    }
+
+    fn load_cached(&self, url: &str) -> Option<Value> {
+        let text = fs::read_to_string(self.cache_path(url)?).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn fetch(&self, url: &str) -> Option<Value> {
+        let text = (self.fetch)(url)?;
+        let schema = serde_json::from_str::<Value>(&text).ok()?;
+        if let Some(cache_path) = self.cache_path(url) {
+            let res = cache_path
+                .parent()
+                .map(fs::create_dir_all)
+                .unwrap_or(Ok(()))
+                .and_then(|_| fs::write(&cache_path, text));
+            if let Err(why) = res {
+                eprintln!("Failed to cache schema {url}: {why:?}");
+            }
+        }
+        Some(schema)
+    }
+}
+
+pub enum SchemaFetch {
+    /// Waits for the latest schemas, for one-off checks
+    Blocking,
+    /// Starts from the cached or bundled schemas, this is called once the latest ones are in
+    Background(Box<dyn FnOnce() + Send>),
+}
 
 #[derive(Debug, Default)]
 pub struct FilePathValidator {
-    body_schema_file_paths: JsonPathSet,
+    schema_indexes: Arc<RwLock<SchemaIndexMap>>,
 }
 
 impl FilePathValidator {
-    fn prepare_from_schema(url: &str, files: &mut JsonPathSet) {
-        if let Ok(Ok(schema)) = reqwest::blocking::get(url).map(|r| r.text()) {
-            if let Ok(schema) = serde_json::from_str::<Value>(&schema) {
-                files.extend(find_paths_with_x_prop("x-file-path", "", &schema, &schema));
-            }
+    pub fn new(options: &ValidationOptions, source: SchemaSource, fetch: SchemaFetch) -> Self {
+        let urls = ConfigType::ALL
+            .iter()
+            .map(|config_type| (*config_type, config_type.schema_url(options).to_string()))
+            .collect::<Vec<_>>();
+        let load = |config_type: &ConfigType, url: &str, fetch_now: bool| {
+            let schema = fetch_now
+                .then(|| source.fetch(url))
+                .flatten()
+                .or_else(|| source.load_cached(url))
+                .unwrap_or_else(|| {
+                    serde_json::from_str(config_type.bundled_schema()).unwrap_or_default()
+                });
+            (*config_type, Self::index_from_schema(&schema))
+        };
+        let blocking = matches!(fetch, SchemaFetch::Blocking);
+        let schema_indexes: SchemaIndexMap = urls
+            .iter()
+            .map(|(config_type, url)| load(config_type, url, blocking))
+            .collect();
+        let this = Self {
+            schema_indexes: Arc::new(RwLock::new(schema_indexes)),
+        };
+        if let SchemaFetch::Background(on_update) = fetch {
+            let schema_indexes = this.schema_indexes.clone();
+            thread::spawn(move || {
+                let mut updated = false;
+                for (config_type, url) in urls {
+                    if let Some(schema) = source.fetch(&url) {
+                        let index = Self::index_from_schema(&schema);
+                        if let Ok(mut map) = schema_indexes.write() {
+                            map.insert(config_type, index);
+                            updated = true;
+                        }
+                    }
+                }
+                // Diagnostics so far were found with the old schemas
+                if updated {
+                    on_update();
+                }
+            });
         }
+        this
+    }
+
+    fn index_from_schema(schema: &Value) -> SchemaIndex {
+        index_schema("x-file-path", "", schema, schema)
+    }
+
+    fn error(
+        config: &ProjectFile,
+        range: JSONRange,
+        code: &str,
+        message: String,
+    ) -> (VersionedTextDocumentIdentifier, Diagnostic) {
+        (
+            config.id.clone(),
+            Diagnostic {
+                range: json_pos_range_to_diag_range(&config.contents, range),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: get_error_code(code),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message,
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )
     }
 
     fn validate_file_or_folder_paths(
         &self,
         project: &Project,
-        files: &[ProjectFile],
+        files: &[&ProjectFile],
         json_paths: &[String],
         errors: &mut ErrorSet,
     ) {
         for config in files.iter() {
-            let tree = json_position_parser::parse_json(&config.contents);
+            let tree = json_position_parser::parse_json(&config.json());
             if let Ok(tree) = tree {
                 for path_to_check in json_paths.iter() {
                     let parsed_path = json_path_to_json_pos_path(path_to_check);
@@ -43,21 +268,11 @@ impl FilePathValidator {
                         if let EntryType::String(file_path) = &found.entry_type {
                             let complete_path = project.root_path.join(file_path);
                             if !complete_path.is_file() && !complete_path.is_dir() {
-                                errors.push((
-                                    config.id.clone(),
-                                    Diagnostic {
-                                        range: json_pos_range_to_diag_range(found.range),
-                                        severity: Some(DiagnosticSeverity::ERROR),
-                                        code: get_error_code(
-                                            error_codes::CONFIG_FILE_PATH_NOT_FOUND,
-                                        ),
-                                        code_description: None,
-                                        source: Some(error_codes::ERROR_SOURCE.to_string()),
-                                        message: format!("File path {file_path} not found",),
-                                        related_information: None,
-                                        tags: None,
-                                        data: None,
-                                    },
+                                errors.push(Self::error(
+                                    config,
+                                    found.range,
+                                    error_codes::CONFIG_FILE_PATH_NOT_FOUND,
+                                    format!("File path {file_path} not found"),
                                 ))
                             }
                         }
@@ -66,13 +281,53 @@ impl FilePathValidator {
             }
         }
     }
+
+    fn validate_enum_values(
+        files: &[&ProjectFile],
+        enum_paths: &[(String, Vec<String>)],
+        errors: &mut ErrorSet,
+    ) {
+        for config in files.iter() {
+            let Ok(tree) = json_position_parser::parse_json(&config.json()) else {
+                continue;
+            };
+            for (path, values) in enum_paths.iter() {
+                for found in tree.value_at(&json_path_to_json_pos_path(path)) {
+                    let EntryType::String(value) = &found.entry_type else {
+                        continue;
+                    };
+                    // New Horizons reads enums case-insensitively
+                    if values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+                        continue;
+                    }
+                    let options = values
+                        .iter()
+                        .map(|v| format!("`{v}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    errors.push(Self::error(
+                        config,
+                        found.range,
+                        error_codes::CONFIG_INVALID_ENUM_VALUE,
+                        format!("Invalid value `{value}`, expected one of {options}"),
+                    ));
+                }
+            }
+        }
+    }
 }
 
 impl Validator for FilePathValidator {
+    fn name() -> &'static str {
+        "file_paths"
+    }
+
     fn prepare() -> Self {
-        let mut this = Self::default();
-        Self::prepare_from_schema("https://gist.github.com/Bwc9876/d54b0a1185f223cac6fdc0110832f929/raw/ca628288f4c168140bd6014ab49bfaf4f54d3f5d/test-schema.json", &mut this.body_schema_file_paths);
-        this
+        Self::new(
+            &ValidationOptions::default(),
+            SchemaSource::default(),
+            SchemaFetch::Background(Box::new(|| {})),
+        )
     }
 
     fn should_invalidate(&self, _: &[lsp_types::Url], _: &Project) -> bool {
@@ -82,12 +337,205 @@ impl Validator for FilePathValidator {
 
     fn validate(&self, project: &Project) -> ErrorSet {
         let mut errors = vec![];
-        self.validate_file_or_folder_paths(
-            project,
-            &project.planet_files,
-            &self.body_schema_file_paths,
-            &mut errors,
-        );
+        let Ok(schema_indexes) = self.schema_indexes.read() else {
+            return errors;
+        };
+        for (config_type, index) in schema_indexes.iter() {
+            let files = config_type.files(project);
+            // Paths are relative to the mod folder, which we don't have
+            if !project.unrooted {
+                self.validate_file_or_folder_paths(
+                    project,
+                    &files,
+                    &index.x_prop_paths,
+                    &mut errors,
+                );
+            }
+            Self::validate_enum_values(&files, &index.enum_paths, &mut errors);
+        }
         errors
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_schema_file_paths() {
+        let schema: Value = serde_json::from_str(BUNDLED_BODY_SCHEMA).unwrap();
+        let paths = index_schema("x-file-path", "", &schema, &schema).x_prop_paths;
+        assert!(paths.contains(&"/ShipLog/xmlFile".to_string()));
+        assert!(paths.contains(&"/Props/dialogue/*/xmlFile".to_string()));
+
+        let schema: Value = serde_json::from_str(BUNDLED_SYSTEM_SCHEMA).unwrap();
+        let paths = index_schema("x-file-path", "", &schema, &schema).x_prop_paths;
+        assert!(paths.contains(&"/Skybox/rightPath".to_string()));
+        assert!(!paths.contains(&"/entryPositions/*/id".to_string()));
+
+        let schema: Value = serde_json::from_str(BUNDLED_ADDON_MANIFEST_SCHEMA).unwrap();
+        let paths = index_schema("x-file-path", "", &schema, &schema).x_prop_paths;
+        assert!(paths.contains(&"/subtitlePath".to_string()));
+    }
+
+    #[test]
+    fn test_addon_manifest_file_paths() {
+        let root = env::temp_dir().join("nh_test_addon_manifest_paths");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("subtitle.png"), "").unwrap();
+        let meta_file = |name: &str, contents: &str| {
+            ProjectFile::new(
+                lsp_types::Url::from_file_path(root.join(name)).unwrap(),
+                0,
+                contents.to_string(),
+            )
+        };
+        let project = Project {
+            root_path: root.clone(),
+            meta_files: vec![
+                meta_file(
+                    ADDON_MANIFEST_FILE,
+                    r#"{"subtitlePath": "subtitle.png", "preloadAssetBundles": ["planets/missing"]}"#,
+                ).into(),
+                // Not an addon manifest.into(), so it isn't checked against that schema
+                meta_file("manifest.json", r#"{"subtitlePath": "missing.png"}"#).into(),
+            ],
+            ..Default::default()
+        };
+        let errors = FilePathValidator::prepare().validate(&project);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.uri.path().ends_with(ADDON_MANIFEST_FILE));
+        assert_eq!(errors[0].1.message, "File path planets/missing not found");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_starts_with_bundled_schema() {
+        let options = ValidationOptions {
+            body_schema_url: Some("http://127.0.0.1:9/body_schema.json".to_string()),
+            system_schema_url: Some("http://127.0.0.1:9/star_system_schema.json".to_string()),
+            ..Default::default()
+        };
+        let validator = FilePathValidator::new(
+            &options,
+            SchemaSource::default(),
+            SchemaFetch::Background(Box::new(|| {})),
+        );
+        let schema_indexes = validator.schema_indexes.read().unwrap();
+        assert!(schema_indexes[&ConfigType::Body]
+            .x_prop_paths
+            .contains(&"/ShipLog/xmlFile".to_string()));
+        assert!(schema_indexes[&ConfigType::System]
+            .x_prop_paths
+            .contains(&"/Skybox/rightPath".to_string()));
+    }
+
+    const FETCHED_SCHEMA: &str =
+        r#"{"type": "object", "properties": {"fetched": {"type": "string", "x-file-path": true}}}"#;
+
+    fn fetched_source(cache_dir: &str) -> SchemaSource {
+        let cache_dir = env::temp_dir().join(cache_dir);
+        let _ = fs::remove_dir_all(&cache_dir);
+        SchemaSource {
+            fetch: |_| Some(FETCHED_SCHEMA.to_string()),
+            cache_dir: Some(cache_dir),
+        }
+    }
+
+    fn has_fetched_path(validator: &FilePathValidator) -> bool {
+        validator.schema_indexes.read().unwrap()[&ConfigType::Body]
+            .x_prop_paths
+            .contains(&"/fetched".to_string())
+    }
+
+    #[test]
+    fn test_fetch_schemas_in_background() {
+        let source = fetched_source("nh_test_schema_cache_background");
+        let (updated, on_update) = std::sync::mpsc::channel();
+        let validator = FilePathValidator::new(
+            &ValidationOptions::default(),
+            source.clone(),
+            SchemaFetch::Background(Box::new(move || updated.send(()).unwrap())),
+        );
+        on_update.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(has_fetched_path(&validator));
+
+        // The next start uses the cached copy until the fetch is done
+        let options = ValidationOptions::default();
+        let url = ConfigType::Body.schema_url(&options);
+        assert!(source.cache_path(url).unwrap().is_file());
+        let cached = SchemaSource {
+            fetch: |_| None,
+            ..source.clone()
+        };
+        let validator = FilePathValidator::new(
+            &ValidationOptions::default(),
+            cached,
+            SchemaFetch::Background(Box::new(|| {})),
+        );
+        assert!(has_fetched_path(&validator));
+        fs::remove_dir_all(source.cache_dir.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_schemas_blocking() {
+        let source = fetched_source("nh_test_schema_cache_blocking");
+        let validator = FilePathValidator::new(
+            &ValidationOptions::default(),
+            source.clone(),
+            SchemaFetch::Blocking,
+        );
+        assert!(has_fetched_path(&validator));
+        fs::remove_dir_all(source.cache_dir.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_bundled_schema_enums() {
+        let schema: Value = serde_json::from_str(BUNDLED_BODY_SCHEMA).unwrap();
+        let enum_paths = index_schema("x-file-path", "", &schema, &schema).enum_paths;
+        assert!(enum_paths.contains(&(
+            "/Base/gravityFallOff".to_string(),
+            vec!["linear".to_string(), "inverseSquared".to_string()]
+        )));
+    }
+
+    #[test]
+    fn test_invalid_enum_values() {
+        let index = SchemaIndex {
+            x_prop_paths: vec![],
+            enum_paths: vec![(
+                "/Base/fallOff".to_string(),
+                vec!["linear".to_string(), "inverseSquared".to_string()],
+            )],
+        };
+        let validator = FilePathValidator {
+            schema_indexes: Arc::new(RwLock::new(HashMap::from([(ConfigType::Body, index)]))),
+        };
+        let mut project = Project::unrooted();
+        for (name, contents) in [
+            ("valid", r#"{"Base": {"fallOff": "Linear"}}"#),
+            (
+                "typo",
+                "{\n  \"Base\": {\"fallOff\": \"inverseSqaured\"}\n}",
+            ),
+        ] {
+            project.planet_files.push(
+                ProjectFile::new(
+                    lsp_types::Url::parse(&format!("file:///planets/{name}.json")).unwrap(),
+                    0,
+                    contents.to_string(),
+                )
+                .into(),
+            );
+        }
+        let errors = validator.validate(&project);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.uri.path().ends_with("typo.json"));
+        assert_eq!(errors[0].1.range.start.line, 1);
+        assert_eq!(
+            errors[0].1.message,
+            "Invalid value `inverseSqaured`, expected one of `linear`, `inverseSquared`"
+        );
+    }
+}
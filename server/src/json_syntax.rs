@@ -0,0 +1,99 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+use serde_json::Value;
+
+use crate::{
+    project::Project,
+    utils::{
+        error_codes::{self, get_error_code},
+        serde_error_range,
+    },
+    validation::{ErrorSet, Validator},
+};
+
+pub struct JsonSyntaxValidator();
+
+impl Validator for JsonSyntaxValidator {
+    fn name() -> &'static str {
+        "json_syntax"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .planet_files
+            .iter()
+            .chain(project.system_files.iter())
+            .chain(project.meta_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        let mut errors = vec![];
+        for file in project
+            .planet_files
+            .iter()
+            .chain(project.system_files.iter())
+            .chain(project.meta_files.iter())
+        {
+            if let Err(why) = serde_json::from_str::<Value>(&file.json()) {
+                errors.push((
+                    file.id.clone(),
+                    Diagnostic {
+                        range: serde_error_range(&file.contents, &why),
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: get_error_code(error_codes::JSON_SYNTAX_ERROR),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message: format!("Invalid JSON: {why}"),
+                        related_information: None,
+                        tags: None,
+                        data: None,
+                    },
+                ));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::ProjectFile;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_json_syntax() {
+        let project = Project {
+            planet_files: vec![
+                ProjectFile {
+                    contents: "{\n  \"name\": \"Test\",\n  \"Base\": \n}".to_string(),
+                    ..ProjectFile::dummy()
+                }
+                .into(),
+                // New Horizons allows comments and trailing commas
+                ProjectFile {
+                    contents: "{\n  // Comment\n  \"name\": \"Test\", /* Other */\n}".to_string(),
+                    ..ProjectFile::dummy()
+                }
+                .into(),
+            ],
+            system_files: vec![ProjectFile {
+                contents: "{}".to_string(),
+                ..ProjectFile::dummy()
+            }
+            .into()],
+            ..Default::default()
+        };
+        let errors = JsonSyntaxValidator::prepare().validate(&project);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::JSON_SYNTAX_ERROR)
+        );
+        assert_eq!(errors[0].1.range.start.line, 3);
+    }
+}
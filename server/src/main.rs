@@ -1,130 +1,841 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
 use anyhow::Result;
-use lsp_server::{Connection, Message, Response};
+use crossbeam_channel::Sender;
+use lsp_server::{Connection, ErrorCode, Message, Response};
 use lsp_types::{
     notification::{
-        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification,
+        DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument,
+        DidOpenTextDocument, DidSaveTextDocument, Notification, ShowMessage,
+    },
+    request::{
+        CodeActionRequest, ColorPresentationRequest, Completion, DocumentColor,
+        DocumentLinkRequest, DocumentSymbolRequest, GotoDefinition, HoverRequest, InlayHintRequest,
+        PrepareRenameRequest, References, RegisterCapability, Rename, Request,
+        SemanticTokensFullRequest, WillRenameFiles, WorkspaceSymbolRequest,
     },
-    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    InitializeParams, PositionEncodingKind, ServerCapabilities, TextDocumentSyncKind,
-    VersionedTextDocumentIdentifier,
+    CodeActionProviderCapability, ColorProviderCapability, CompletionOptions, CompletionResponse,
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentLinkOptions,
+    DocumentSymbolResponse, FileChangeType, FileOperationFilter, FileOperationPattern,
+    FileOperationPatternKind, FileOperationRegistrationOptions, FileSystemWatcher, GlobPattern,
+    GotoDefinitionResponse, HoverProviderCapability, InitializeParams, InitializeResult,
+    MessageType, OneOf, PrepareRenameResponse, Registration, RegistrationParams, RenameOptions,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities, ShowMessageParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, VersionedTextDocumentIdentifier,
+    WorkspaceFileOperationsServerCapabilities, WorkspaceServerCapabilities,
+    WorkspaceSymbolResponse,
+};
+use requests::{
+    parse_params, GetCuriosities, GetEntriesForSystem, GetEntry, GetFactsForEntry,
+    GetFactsForSystem, GetProjectStats, GetShipLogGraph, GetSystems,
 };
 use serde_json::Value;
 use ship_log::ShipLogContext;
-use validation::MainValidator;
+use validation::{MainValidator, ValidationOptions, ValidationWorker};
 
-use crate::project::Project;
+use crate::{
+    file_paths::SchemaFetch,
+    project::{exclude_patterns, Project, LAYOUT_FILE},
+    utils::PositionEncoding,
+};
 
+mod cli;
+mod colors;
+mod dialogue;
 mod file_paths;
+mod json_syntax;
+mod manifest;
+mod nomai_text;
 mod planets;
 mod project;
+mod requests;
+mod reveals;
 mod ship_log;
 mod systems;
 mod utils;
 mod validation;
 
-fn main_loop(connection: Connection, params: Value) -> Result<()> {
-    let params: InitializeParams = serde_json::from_value(params).unwrap();
-    let validator = MainValidator::new();
-    if let Some(root_uri) = params.root_uri {
-        let path = root_uri.to_file_path().unwrap();
-        eprintln!("Detected Project At {}, Loading...", path.to_str().unwrap());
-        let mut project = Project::default();
-        project.load_from(&path);
-        eprintln!("Performing initial validation");
-        validator.force_validate(&connection, &mut project);
-        eprintln!("Starting main event loop");
-        for msg in &connection.receiver {
-            match msg {
-                Message::Request(req) => match req.method.as_str() {
-                    "getSystems" => {
-                        let systems = project.find_all_systems();
-                        let response = Response::new_ok(req.id, systems);
-                        connection.sender.send(Message::Response(response))?;
-                    }
-                    "getEntriesForSystem" => {
-                        let ctx = ShipLogContext::from_project(&project);
-                        eprintln!("Received request for entries {}", req.params);
-                        if let Some(system) = req
-                            .params
-                            .as_array()
-                            .and_then(|a| a.get(0))
-                            .and_then(|v| v.as_str())
-                        {
-                            let entries = ctx.get_entries_for_system(system);
-                            let response = Response::new_ok(req.id, entries);
-                            connection.sender.send(Message::Response(response))?;
-                        }
-                    }
-                    _ => {
-                        if connection.handle_shutdown(&req)? {
-                            return Ok(());
+fn register_file_watchers(connection: &Connection) -> Result<()> {
+    let watchers = ["**/*.json", "**/*.jsonc", "**/*.xml"]
+        .iter()
+        .map(|pattern| FileSystemWatcher {
+            glob_pattern: GlobPattern::String(pattern.to_string()),
+            kind: None,
+        })
+        .collect();
+    let params = RegistrationParams {
+        registrations: vec![Registration {
+            id: "nh-watched-files".to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: Some(serde_json::to_value(
+                DidChangeWatchedFilesRegistrationOptions { watchers },
+            )?),
+        }],
+    };
+    connection
+        .sender
+        .send(Message::Request(lsp_server::Request::new(
+            "nh-register-watchers".to_string().into(),
+            RegisterCapability::METHOD.to_string(),
+            params,
+        )))?;
+    Ok(())
+}
+
+fn show_message(connection: &Connection, typ: MessageType, message: String) -> Result<()> {
+    connection
+        .sender
+        .send(Message::Notification(lsp_server::Notification::new(
+            ShowMessage::METHOD.to_string(),
+            ShowMessageParams { typ, message },
+        )))?;
+    Ok(())
+}
+
+fn handle_notification(
+    validator: &mut ValidationWorker,
+    project: &RwLock<Project>,
+    not: lsp_server::Notification,
+) -> Result<()> {
+    let mut project = project.write().unwrap();
+    match not.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+            project.open_file(
+                VersionedTextDocumentIdentifier::new(
+                    params.text_document.uri.clone(),
+                    params.text_document.version,
+                ),
+                &params.text_document.text,
+            );
+            validator.on_change(vec![params.text_document.uri], &project);
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+            project.apply_changes(params.text_document.clone(), &params.content_changes);
+            validator.queue_change(params.text_document.uri, &project);
+        }
+        DidSaveTextDocument::METHOD => {
+            let params: DidSaveTextDocumentParams = serde_json::from_value(not.params)?;
+            validator.on_save(params.text_document.uri, &project);
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+            project.close_file(&params.text_document.uri);
+            validator.on_change(vec![params.text_document.uri], &project);
+        }
+        DidChangeWatchedFiles::METHOD => {
+            let params: DidChangeWatchedFilesParams = serde_json::from_value(not.params)?;
+            for event in params.changes.iter() {
+                match event.typ {
+                    FileChangeType::CREATED => {
+                        if let Ok(path) = event.uri.to_file_path() {
+                            project.add_file(&path);
                         }
                     }
-                },
-                Message::Response(_) => {}
-                Message::Notification(not) => match not.method.as_str() {
-                    DidOpenTextDocument::METHOD => {
-                        let params: DidOpenTextDocumentParams =
-                            serde_json::from_value(not.params).unwrap();
-                        project.open_file(
-                            VersionedTextDocumentIdentifier::new(
-                                params.text_document.uri.clone(),
-                                params.text_document.version,
-                            ),
-                            &params.text_document.text,
-                        );
-                        validator.on_change(
-                            &connection,
-                            vec![params.text_document.uri],
-                            &mut project,
-                        );
-                    }
-                    DidChangeTextDocument::METHOD => {
-                        let params: DidChangeTextDocumentParams =
-                            serde_json::from_value(not.params).unwrap();
-                        dbg!(params.text_document.uri.clone());
-                        project.open_file(
-                            params.text_document.clone(),
-                            &params.content_changes.first().unwrap().text,
-                        );
-                        validator.on_change(
-                            &connection,
-                            vec![params.text_document.uri],
-                            &mut project,
-                        );
-                    }
-                    DidCloseTextDocument::METHOD => {
-                        let params: DidCloseTextDocumentParams =
-                            serde_json::from_value(not.params).unwrap();
-                        project.close_file(&params.text_document.uri);
-                        validator.on_change(
-                            &connection,
-                            vec![params.text_document.uri],
-                            &mut project,
-                        );
-                    }
-                    _ => {}
-                },
+                    FileChangeType::DELETED => project.remove_file(&event.uri),
+                    _ => project.reload_file(&event.uri),
+                }
+            }
+            validator.on_change(
+                params.changes.into_iter().map(|e| e.uri).collect(),
+                &project,
+            );
+        }
+        DidChangeConfiguration::METHOD => {
+            let params: DidChangeConfigurationParams = serde_json::from_value(not.params)?;
+            let options: ValidationOptions = serde_json::from_value(params.settings)?;
+            if !project.unrooted {
+                let excludes = exclude_patterns(
+                    &project.root_path,
+                    &options.exclude_globs,
+                    options.use_gitignore,
+                );
+                project.set_excludes(excludes);
+            }
+            project.set_external_projects(&options.external_projects);
+            validator.configure(options);
+            validator.force_validate(&project);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn find_project_root(params: &InitializeParams) -> Option<PathBuf> {
+    let folders = params
+        .workspace_folders
+        .iter()
+        .flatten()
+        .filter_map(|f| f.uri.to_file_path().ok())
+        .collect::<Vec<_>>();
+    // Prefer a folder that actually looks like a New Horizons mod in multi-root setups
+    folders
+        .iter()
+        .find(|path| {
+            path.join("planets").is_dir()
+                || path.join("systems").is_dir()
+                || path.join(LAYOUT_FILE).is_file()
+        })
+        .or_else(|| folders.first())
+        .cloned()
+        .or_else(|| params.root_uri.as_ref()?.to_file_path().ok())
+}
+
+fn handle_request(
+    sender: &Sender<Message>,
+    project: &Project,
+    files_with_diagnostics: &AtomicUsize,
+    req: lsp_server::Request,
+) -> Result<()> {
+    let response = respond(project, files_with_diagnostics, req);
+    sender.send(Message::Response(response))?;
+    Ok(())
+}
+
+fn respond(
+    project: &Project,
+    files_with_diagnostics: &AtomicUsize,
+    req: lsp_server::Request,
+) -> Response {
+    match req.method.as_str() {
+        GetProjectStats::METHOD => {
+            let count = files_with_diagnostics.load(Ordering::Relaxed);
+            requests::get_project_stats(project, count, req)
+        }
+        GetSystems::METHOD => requests::get_systems(project, req),
+        GetEntriesForSystem::METHOD => requests::get_entries_for_system(project, req),
+        GetShipLogGraph::METHOD => requests::get_ship_log_graph(project, req),
+        GetEntry::METHOD => requests::get_entry(project, req),
+        GetFactsForSystem::METHOD => requests::get_facts_for_system(project, req),
+        GetFactsForEntry::METHOD => requests::get_facts_for_entry(project, req),
+        GetCuriosities::METHOD => requests::get_curiosities(project, req),
+        HoverRequest::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<HoverRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let hover = ctx.hover(
+                &params.text_document_position_params.text_document.uri,
+                params.text_document_position_params.position,
+            );
+            Response::new_ok(id, hover)
+        }
+        GotoDefinition::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<GotoDefinition>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let location = ctx
+                .definition(
+                    &params.text_document_position_params.text_document.uri,
+                    params.text_document_position_params.position,
+                )
+                .map(GotoDefinitionResponse::Scalar);
+            Response::new_ok(id, location)
+        }
+        References::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<References>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let locations = ctx.references(
+                &project.system_files,
+                &params.text_document_position.text_document.uri,
+                params.text_document_position.position,
+                params.context.include_declaration,
+            );
+            Response::new_ok(id, locations)
+        }
+        Completion::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<Completion>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let uri = params.text_document_position.text_document.uri;
+            let items = project
+                .ship_log_files
+                .iter()
+                .find(|f| f.id.uri == uri)
+                .and_then(|f| {
+                    ctx.completion(
+                        &project.system_files,
+                        &f.contents,
+                        params.text_document_position.position,
+                    )
+                })
+                .map(CompletionResponse::Array);
+            Response::new_ok(id, items)
+        }
+        CodeActionRequest::METHOD => {
+            let (id, params) = match parse_params::<CodeActionRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let actions = ShipLogContext::code_actions(
+                project,
+                &params.text_document.uri,
+                params.range,
+                &params.context.diagnostics,
+            );
+            Response::new_ok(id, actions)
+        }
+        WillRenameFiles::METHOD => {
+            let (id, params) = match parse_params::<WillRenameFiles>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let edit = project.file_rename_edits(&params.files);
+            Response::new_ok(id, edit)
+        }
+        PrepareRenameRequest::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<PrepareRenameRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let range = ctx
+                .prepare_rename(&params.text_document.uri, params.position)
+                .map(PrepareRenameResponse::Range);
+            Response::new_ok(id, range)
+        }
+        Rename::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<Rename>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            match ctx.rename(
+                &project.system_files,
+                &params.text_document_position.text_document.uri,
+                params.text_document_position.position,
+                &params.new_name,
+            ) {
+                Ok(edit) => Response::new_ok(id, edit),
+                Err(why) => Response::new_err(id, ErrorCode::InvalidRequest as i32, why),
+            }
+        }
+        WorkspaceSymbolRequest::METHOD => {
+            let ctx = &project.ship_logs;
+            let (id, params) = match parse_params::<WorkspaceSymbolRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let symbols = ctx.workspace_symbols(&params.query);
+            Response::new_ok(id, WorkspaceSymbolResponse::Flat(symbols))
+        }
+        DocumentSymbolRequest::METHOD => {
+            let (id, params) = match parse_params::<DocumentSymbolRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let symbols = project
+                .ship_log_files
+                .iter()
+                .find(|f| f.id.uri == params.text_document.uri)
+                .and_then(|f| ShipLogContext::document_symbols(&f.contents))
+                .map(DocumentSymbolResponse::Nested);
+            Response::new_ok(id, symbols)
+        }
+        InlayHintRequest::METHOD => {
+            let (id, params) = match parse_params::<InlayHintRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let hints = project
+                .ship_logs
+                .inlay_hints(&params.text_document.uri, params.range);
+            Response::new_ok(id, hints)
+        }
+        SemanticTokensFullRequest::METHOD => {
+            let (id, params) = match parse_params::<SemanticTokensFullRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let data = project.ship_logs.semantic_tokens(&params.text_document.uri);
+            let tokens = SemanticTokensResult::Tokens(SemanticTokens {
+                result_id: None,
+                data,
+            });
+            Response::new_ok(id, tokens)
+        }
+        DocumentLinkRequest::METHOD => {
+            let (id, params) = match parse_params::<DocumentLinkRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let links = project.document_links(&params.text_document.uri);
+            Response::new_ok(id, links)
+        }
+        DocumentColor::METHOD => {
+            let (id, params) = match parse_params::<DocumentColor>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let colors = project
+                .planet_files
+                .iter()
+                .chain(project.system_files.iter())
+                .find(|f| f.id.uri == params.text_document.uri)
+                .map(|f| colors::document_colors(&f.json()))
+                .unwrap_or_default();
+            Response::new_ok(id, colors)
+        }
+        ColorPresentationRequest::METHOD => {
+            let (id, params) = match parse_params::<ColorPresentationRequest>(req) {
+                Ok(parsed) => parsed,
+                Err(response) => return response,
+            };
+            let presentations = project
+                .planet_files
+                .iter()
+                .chain(project.system_files.iter())
+                .find(|f| f.id.uri == params.text_document.uri)
+                .map(|f| colors::color_presentations(&f.json(), params.range, &params.color))
+                .unwrap_or_default();
+            Response::new_ok(id, presentations)
+        }
+        _ => Response::new_err(
+            req.id,
+            ErrorCode::MethodNotFound as i32,
+            format!("Unknown method {}", req.method),
+        ),
+    }
+}
+
+/// Answers requests on another thread so they don't wait on notifications being handled
+fn spawn_request_worker(
+    sender: Sender<Message>,
+    project: Arc<RwLock<Project>>,
+    files_with_diagnostics: Arc<AtomicUsize>,
+) -> (Sender<lsp_server::Request>, JoinHandle<()>) {
+    let (requests, receiver) = crossbeam_channel::unbounded::<lsp_server::Request>();
+    let handle = thread::spawn(move || {
+        for req in receiver {
+            let project = project.read().unwrap();
+            if let Err(why) = handle_request(&sender, &project, &files_with_diagnostics, req) {
+                eprintln!("Error handling request: {why:?}");
             }
         }
+    });
+    (requests, handle)
+}
+
+fn main_loop(connection: Connection, params: Value) -> Result<()> {
+    let params: InitializeParams = serde_json::from_value(params).unwrap();
+    let options: ValidationOptions = params
+        .initialization_options
+        .clone()
+        .and_then(|o| serde_json::from_value(o).ok())
+        .unwrap_or_default();
+    let (exclude_globs, use_gitignore) = (options.exclude_globs.clone(), options.use_gitignore);
+    let external_projects = options.external_projects.clone();
+    let validate_on = options.validate_on;
+    let mut validator = ValidationWorker::spawn(
+        Duration::from_millis(options.debounce_ms),
+        connection.sender.clone(),
+        move |revalidate| MainValidator::new(&options, SchemaFetch::Background(revalidate)),
+    );
+    validator.validate_on = validate_on;
+    let can_watch_files = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|w| w.did_change_watched_files)
+        .and_then(|w| w.dynamic_registration)
+        .unwrap_or(false);
+    if can_watch_files {
+        register_file_watchers(&connection)?;
     }
+    let project = match find_project_root(&params) {
+        Some(path) if path.is_dir() => {
+            eprintln!("Detected Project At {}, Loading...", path.display());
+            let mut project = Project {
+                excludes: exclude_patterns(&path, &exclude_globs, use_gitignore),
+                ..Default::default()
+            };
+            project.load_from(&path);
+            project.set_external_projects(&external_projects);
+            eprintln!("Performing initial validation");
+            validator.force_validate(&project);
+            project
+        }
+        root => {
+            let message = match root {
+                Some(path) => format!(
+                    "Project folder {} doesn't exist, only open files will be checked",
+                    path.display()
+                ),
+                None => "No local workspace folder was found, only open files will be checked"
+                    .to_string(),
+            };
+            eprintln!("{message}");
+            show_message(&connection, MessageType::WARNING, message)?;
+            let mut project = Project::unrooted();
+            project.set_external_projects(&external_projects);
+            project
+        }
+    };
+    let project = Arc::new(RwLock::new(project));
+    let (requests, request_worker) = spawn_request_worker(
+        connection.sender.clone(),
+        project.clone(),
+        validator.files_with_diagnostics.clone(),
+    );
+    eprintln!("Starting main event loop");
+    loop {
+        // Edits are batched until nothing has come in for the debounce interval
+        let msg = if validator.has_pending_changes() {
+            match connection.receiver.recv_timeout(validator.debounce) {
+                Ok(msg) => msg,
+                Err(why) if why.is_timeout() => {
+                    validator.flush_pending_changes(&project.read().unwrap());
+                    continue;
+                }
+                Err(_) => break,
+            }
+        } else {
+            match connection.receiver.recv() {
+                Ok(msg) => msg,
+                Err(_) => break,
+            }
+        };
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    break;
+                }
+                requests.send(req)?;
+            }
+            Message::Response(_) => {}
+            Message::Notification(not) => {
+                if let Err(why) = handle_notification(&mut validator, &project, not) {
+                    eprintln!("Error handling notification: {why:?}");
+                }
+            }
+        }
+    }
+    // Lets requests that came in before the shutdown finish
+    drop(requests);
+    let _ = request_worker.join();
     Ok(())
 }
 
 pub fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|a| a == "--check").unwrap_or(false) {
+        std::process::exit(cli::check(&args[1..])?);
+    }
+
     let (connection, _) = Connection::stdio();
 
+    let (initialize_id, initialization_params) = connection.initialize_start()?;
+    let client_encodings =
+        serde_json::from_value::<InitializeParams>(initialization_params.clone())
+            .ok()
+            .and_then(|params| params.capabilities.general)
+            .and_then(|general| general.position_encodings);
+    let encoding = PositionEncoding::negotiate(client_encodings.as_deref());
+    utils::set_position_encoding(encoding);
+
     let capabilities = ServerCapabilities {
-        position_encoding: Some(PositionEncodingKind::UTF16),
-        workspace: None,
-        text_document_sync: Some(TextDocumentSyncKind::FULL.into()),
+        position_encoding: Some(encoding.kind()),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: None,
+            file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                will_rename: Some(FileOperationRegistrationOptions {
+                    filters: vec![
+                        FileOperationFilter {
+                            scheme: Some("file".to_string()),
+                            pattern: FileOperationPattern {
+                                glob: "**/*.{xml,json}".to_string(),
+                                matches: Some(FileOperationPatternKind::File),
+                                options: None,
+                            },
+                        },
+                        FileOperationFilter {
+                            scheme: Some("file".to_string()),
+                            pattern: FileOperationPattern {
+                                glob: "**/*".to_string(),
+                                matches: Some(FileOperationPatternKind::Folder),
+                                options: None,
+                            },
+                        },
+                    ],
+                }),
+                ..Default::default()
+            }),
+        }),
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        color_provider: Some(ColorProviderCapability::Simple(true)),
+        inlay_hint_provider: Some(OneOf::Left(true)),
+        document_link_provider: Some(DocumentLinkOptions {
+            resolve_provider: Some(false),
+            work_done_progress_options: Default::default(),
+        }),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: ship_log::SEMANTIC_TOKEN_TYPES.to_vec(),
+                    token_modifiers: vec![],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                range: None,
+                work_done_progress_options: Default::default(),
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(
+                std::iter::once('<')
+                    .chain('a'..='z')
+                    .chain('A'..='Z')
+                    .map(|c| c.to_string())
+                    .collect(),
+            ),
+            ..Default::default()
+        }),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: Default::default(),
+        })),
         ..Default::default()
     };
 
-    let server_capabilities = serde_json::to_value(capabilities).unwrap();
-    let initialization_params = connection.initialize(server_capabilities)?;
+    let initialize_result = InitializeResult {
+        capabilities,
+        server_info: None,
+    };
+    connection.initialize_finish(initialize_id, serde_json::to_value(initialize_result)?)?;
 
     main_loop(connection, initialization_params)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{
+        FileEvent, PublishDiagnosticsParams, TextDocumentContentChangeEvent, Url, WorkspaceFolder,
+    };
+    use serde_json::json;
+
+    use crate::project::ProjectFile;
+
+    use super::*;
+
+    fn did_change(version: i32, changes: Vec<TextDocumentContentChangeEvent>) -> Value {
+        serde_json::to_value(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(
+                Url::parse("file:///dev/null").unwrap(),
+                version,
+            ),
+            content_changes: changes,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_handle_notification() {
+        let (connection, _client) = Connection::memory();
+        let mut validator =
+            ValidationWorker::spawn(Duration::ZERO, connection.sender.clone(), |_| {
+                MainValidator::default()
+            });
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "<ID>FOO</ID>".to_string();
+        let project = RwLock::new(project);
+
+        let not = lsp_server::Notification::new(
+            DidChangeTextDocument::METHOD.to_string(),
+            did_change(1, vec![]),
+        );
+        handle_notification(&mut validator, &project, not).unwrap();
+        assert_eq!(project.read().unwrap().ship_log_files[0].id.version, 1);
+        assert_eq!(
+            project.read().unwrap().ship_log_files[0].contents,
+            "<ID>FOO</ID>"
+        );
+
+        let changes = ["<ID>BAR</ID>", "<ID>BAZ</ID>"]
+            .iter()
+            .map(|text| TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_string(),
+            })
+            .collect();
+        let not = lsp_server::Notification::new(
+            DidChangeTextDocument::METHOD.to_string(),
+            did_change(2, changes),
+        );
+        handle_notification(&mut validator, &project, not).unwrap();
+        assert_eq!(project.read().unwrap().ship_log_files[0].id.version, 2);
+        assert_eq!(
+            project.read().unwrap().ship_log_files[0].contents,
+            "<ID>BAZ</ID>"
+        );
+
+        let not = lsp_server::Notification::new(
+            DidChangeTextDocument::METHOD.to_string(),
+            json!({ "textDocument": "garbage" }),
+        );
+        assert!(handle_notification(&mut validator, &project, not).is_err());
+        assert_eq!(
+            project.read().unwrap().ship_log_files[0].contents,
+            "<ID>BAZ</ID>"
+        );
+    }
+
+    #[test]
+    fn test_handle_watched_file_deleted() {
+        let (connection, client) = Connection::memory();
+        let mut validator =
+            ValidationWorker::spawn(Duration::ZERO, connection.sender.clone(), |_| {
+                MainValidator::default()
+            });
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "<ID>FOO</ID>".to_string();
+        let project = RwLock::new(project);
+
+        let params = DidChangeWatchedFilesParams {
+            changes: vec![FileEvent::new(
+                Url::parse("file:///dev/null").unwrap(),
+                FileChangeType::DELETED,
+            )],
+        };
+        let not = lsp_server::Notification::new(
+            DidChangeWatchedFiles::METHOD.to_string(),
+            serde_json::to_value(params).unwrap(),
+        );
+        handle_notification(&mut validator, &project, not).unwrap();
+        assert!(project.read().unwrap().ship_log_files.is_empty());
+
+        let cleared = client
+            .receiver
+            .recv_timeout(Duration::from_secs(5))
+            .ok()
+            .and_then(|msg| match msg {
+                Message::Notification(not) => {
+                    serde_json::from_value::<PublishDiagnosticsParams>(not.params).ok()
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(cleared.uri, Url::parse("file:///dev/null").unwrap());
+        assert!(cleared.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_request_worker() {
+        let (connection, client) = Connection::memory();
+        let project = Arc::new(RwLock::new(Project::default()));
+        let (requests, worker) = spawn_request_worker(
+            connection.sender.clone(),
+            project.clone(),
+            Arc::new(AtomicUsize::new(0)),
+        );
+
+        // Requests are answered with whatever the project holds once they're picked up
+        project.write().unwrap().system_files.push(
+            ProjectFile::new(
+                Url::parse("file:///systems/Test.json").unwrap(),
+                0,
+                "{}".to_string(),
+            )
+            .into(),
+        );
+        requests
+            .send(lsp_server::Request::new(
+                1.into(),
+                GetSystems::METHOD.to_string(),
+                Value::Null,
+            ))
+            .unwrap();
+        let response = match client.receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(Message::Response(response)) => response,
+            other => panic!("Expected a response, got {other:?}"),
+        };
+        assert_eq!(response.id, 1.into());
+        let systems: Vec<String> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(systems.contains(&"Test".to_string()));
+
+        drop(requests);
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_respond_to_bad_requests() {
+        let project = Project::default();
+        let count = AtomicUsize::new(0);
+        let request = |method: &str| {
+            lsp_server::Request::new(1.into(), method.to_string(), serde_json::json!({}))
+        };
+
+        let response = respond(&project, &count, request(HoverRequest::METHOD));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+
+        let response = respond(&project, &count, request("nh/unknown"));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::MethodNotFound as i32
+        );
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let folder = |path: &str| WorkspaceFolder {
+            uri: Url::parse(path).unwrap(),
+            name: "folder".to_string(),
+        };
+        let mut params = InitializeParams {
+            root_uri: Some(Url::parse("file:///root").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(find_project_root(&params), Some(PathBuf::from("/root")));
+
+        params.workspace_folders = Some(vec![folder("file:///first"), folder("file:///second")]);
+        assert_eq!(find_project_root(&params), Some(PathBuf::from("/first")));
+
+        params.workspace_folders = None;
+        params.root_uri = None;
+        assert_eq!(find_project_root(&params), None);
+    }
+}
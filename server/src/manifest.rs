@@ -0,0 +1,340 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, Url, VersionedTextDocumentIdentifier};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{error::Category, Map, Value};
+
+use crate::{
+    project::{Project, ProjectFile},
+    utils::{
+        error_codes::{self, get_error_code},
+        json_value_range, serde_error_range,
+    },
+    validation::{ErrorSet, Validator},
+};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const ADDON_MANIFEST_FILE: &str = "addon-manifest.json";
+pub const DEFAULT_CONFIG_FILE: &str = "default-config.json";
+
+/// Files at the root of the mod that aren't New Horizons configs themselves
+pub const META_FILE_NAMES: [&str; 3] = [MANIFEST_FILE, ADDON_MANIFEST_FILE, DEFAULT_CONFIG_FILE];
+
+const NEW_HORIZONS_UNIQUE_NAME: &str = "xen.NewHorizons";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub author: Option<String>,
+    pub name: Option<String>,
+    pub unique_name: Option<String>,
+    pub version: Option<String>,
+    pub owml_version: Option<String>,
+    pub dependencies: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)] // Only deserialized to check the file's shape
+pub struct AddonAchievement {
+    pub id: String,
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(default, rename = "factIDs")]
+    pub fact_ids: Vec<String>,
+    #[serde(default, rename = "signalIDs")]
+    pub signal_ids: Vec<String>,
+    #[serde(default, rename = "conditionIDs")]
+    pub condition_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)] // Only deserialized to check the file's shape
+pub struct AddonManifest {
+    #[serde(default)]
+    pub achievements: Vec<AddonAchievement>,
+    #[serde(default)]
+    pub credits: Vec<String>,
+    pub popup_message: Option<String>,
+    #[serde(default)]
+    pub repeat_popup: bool,
+    #[serde(default)]
+    pub preload_asset_bundles: Vec<String>,
+    pub subtitle_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)] // Only deserialized to check the file's shape
+pub struct DefaultConfig {
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+}
+
+/// `MAJOR.MINOR.PATCH` with an optional pre-release and build suffix
+pub fn is_semver(version: &str) -> bool {
+    let version = version.split_once('+').map(|(v, _)| v).unwrap_or(version);
+    let (core, pre_release) = match version.split_once('-') {
+        Some((core, pre_release)) => (core, Some(pre_release)),
+        None => (version, None),
+    };
+    let is_number = |part: &str| {
+        !part.is_empty()
+            && part.chars().all(|c| c.is_ascii_digit())
+            && (part == "0" || !part.starts_with('0'))
+    };
+    let parts = core.split('.').collect::<Vec<_>>();
+    parts.len() == 3
+        && parts.iter().all(|p| is_number(p))
+        && pre_release
+            .map(|p| p.split('.').all(|id| !id.is_empty()))
+            .unwrap_or(true)
+}
+
+pub struct ManifestValidator();
+
+impl ManifestValidator {
+    fn error(
+        file: &ProjectFile,
+        path: &str,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+    ) -> (VersionedTextDocumentIdentifier, Diagnostic) {
+        (
+            file.id.clone(),
+            Diagnostic {
+                range: json_value_range(&file.json(), path).unwrap_or_default(),
+                severity: Some(severity),
+                code: get_error_code(code),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message,
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )
+    }
+
+    fn parse<T: DeserializeOwned>(file: &ProjectFile, errors: &mut ErrorSet) -> Option<T> {
+        match serde_json::from_str::<T>(&file.json()) {
+            Ok(parsed) => Some(parsed),
+            Err(why) => {
+                // Syntax errors are reported by JsonSyntaxValidator
+                if why.classify() == Category::Data {
+                    errors.push((
+                        file.id.clone(),
+                        Diagnostic {
+                            range: serde_error_range(&file.contents, &why),
+                            severity: Some(DiagnosticSeverity::ERROR),
+                            code: get_error_code(error_codes::MANIFEST_INVALID_CONFIG),
+                            code_description: None,
+                            source: Some(error_codes::ERROR_SOURCE.to_string()),
+                            message: format!("Invalid config: {why}"),
+                            related_information: None,
+                            tags: None,
+                            data: None,
+                        },
+                    ));
+                }
+                None
+            }
+        }
+    }
+
+    fn validate_manifest(file: &ProjectFile, errors: &mut ErrorSet) {
+        let Some(manifest) = Self::parse::<Manifest>(file, errors) else {
+            return;
+        };
+        for (field, value) in [
+            ("author", &manifest.author),
+            ("name", &manifest.name),
+            ("uniqueName", &manifest.unique_name),
+            ("version", &manifest.version),
+            ("owmlVersion", &manifest.owml_version),
+        ] {
+            if value.is_none() {
+                errors.push(Self::error(
+                    file,
+                    "",
+                    DiagnosticSeverity::ERROR,
+                    error_codes::MANIFEST_MISSING_FIELD,
+                    format!("Missing required field `{field}`"),
+                ));
+            }
+        }
+        if let Some(unique_name) = manifest.unique_name.filter(|n| !n.contains('.')) {
+            errors.push(Self::error(
+                file,
+                "/uniqueName",
+                DiagnosticSeverity::ERROR,
+                error_codes::MANIFEST_INVALID_UNIQUE_NAME,
+                format!("Unique name `{unique_name}` should look like `Author.ModName`"),
+            ));
+        }
+        if let Some(version) = manifest.version.filter(|v| !is_semver(v)) {
+            errors.push(Self::error(
+                file,
+                "/version",
+                DiagnosticSeverity::ERROR,
+                error_codes::MANIFEST_INVALID_VERSION,
+                format!("Version `{version}` isn't a valid semantic version (e.g. `1.0.0`)"),
+            ));
+        }
+        let depends_on_nh = manifest
+            .dependencies
+            .as_ref()
+            .map(|deps| deps.iter().any(|d| d == NEW_HORIZONS_UNIQUE_NAME))
+            .unwrap_or(false);
+        if !depends_on_nh {
+            let path = if manifest.dependencies.is_some() {
+                "/dependencies"
+            } else {
+                ""
+            };
+            errors.push(Self::error(
+                file,
+                path,
+                DiagnosticSeverity::WARNING,
+                error_codes::MANIFEST_MISSING_DEPENDENCY,
+                format!("`{NEW_HORIZONS_UNIQUE_NAME}` should be listed in `dependencies`"),
+            ));
+        }
+    }
+}
+
+impl Validator for ManifestValidator {
+    fn name() -> &'static str {
+        "manifest"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .meta_files
+            .iter()
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        let mut errors = vec![];
+        for file in project.meta_files.iter() {
+            match file.nice_path.file_name().and_then(|n| n.to_str()) {
+                Some(MANIFEST_FILE) => Self::validate_manifest(file, &mut errors),
+                Some(ADDON_MANIFEST_FILE) => {
+                    Self::parse::<AddonManifest>(file, &mut errors);
+                }
+                Some(DEFAULT_CONFIG_FILE) => {
+                    Self::parse::<DefaultConfig>(file, &mut errors);
+                }
+                _ => {}
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta_project(name: &str, contents: &str) -> Project {
+        Project {
+            meta_files: vec![ProjectFile::new(
+                Url::parse(&format!("file:///mod/{name}")).unwrap(),
+                0,
+                contents.to_string(),
+            )
+            .into()],
+            ..Default::default()
+        }
+    }
+
+    fn codes(errors: &ErrorSet) -> Vec<&str> {
+        errors
+            .iter()
+            .filter_map(|e| match &e.1.code {
+                Some(lsp_types::NumberOrString::String(code)) => Some(code.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_is_semver() {
+        for version in ["1.0.0", "0.12.3", "1.2.3-beta.1", "1.2.3+build.5"] {
+            assert!(is_semver(version), "{version}");
+        }
+        for version in ["1.0", "v1.0.0", "1.0.0.0", "01.0.0", "1.a.0", "1.0.0-", ""] {
+            assert!(!is_semver(version), "{version}");
+        }
+    }
+
+    #[test]
+    fn test_validate_manifest() {
+        let validator = ManifestValidator::prepare();
+
+        let errors = validator.validate(&meta_project(
+            MANIFEST_FILE,
+            r#"{
+  "author": "Test",
+  "name": "Test Mod",
+  "uniqueName": "Test.TestMod",
+  "version": "1.0.0",
+  "owmlVersion": "2.9.0",
+  "dependencies": ["xen.NewHorizons"]
+}"#,
+        ));
+        assert!(errors.is_empty());
+
+        let errors = validator.validate(&meta_project(
+            MANIFEST_FILE,
+            r#"{
+  "author": "Test",
+  "uniqueName": "TestMod",
+  "version": "1.0",
+  "owmlVersion": "2.9.0",
+  "dependencies": []
+}"#,
+        ));
+        assert_eq!(
+            codes(&errors),
+            vec![
+                error_codes::MANIFEST_MISSING_FIELD,
+                error_codes::MANIFEST_INVALID_UNIQUE_NAME,
+                error_codes::MANIFEST_INVALID_VERSION,
+                error_codes::MANIFEST_MISSING_DEPENDENCY,
+            ]
+        );
+        assert_eq!(errors[0].1.message, "Missing required field `name`");
+        assert_eq!(errors[1].1.range.start.line, 2);
+        assert_eq!(errors[2].1.range.start.line, 3);
+        assert_eq!(errors[3].1.range.start.line, 5);
+        assert_eq!(errors[3].1.severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn test_validate_meta_file_shapes() {
+        let validator = ManifestValidator::prepare();
+
+        let errors = validator.validate(&meta_project(
+            ADDON_MANIFEST_FILE,
+            r#"{"achievements": [{"id": "TEST", "factIDs": ["FACT"]}], "credits": ["Me"]}"#,
+        ));
+        assert!(errors.is_empty());
+
+        let errors = validator.validate(&meta_project(ADDON_MANIFEST_FILE, r#"{"credits": "Me"}"#));
+        assert_eq!(codes(&errors), vec![error_codes::MANIFEST_INVALID_CONFIG]);
+
+        let errors =
+            validator.validate(&meta_project(DEFAULT_CONFIG_FILE, r#"{"enabled": "yes"}"#));
+        assert_eq!(codes(&errors), vec![error_codes::MANIFEST_INVALID_CONFIG]);
+
+        // Syntax errors are left to JsonSyntaxValidator
+        let errors = validator.validate(&meta_project(DEFAULT_CONFIG_FILE, "{"));
+        assert!(errors.is_empty());
+    }
+}
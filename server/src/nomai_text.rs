@@ -0,0 +1,219 @@
+use anyhow::Result;
+use lsp_types::{Diagnostic, DiagnosticSeverity, Url, VersionedTextDocumentIdentifier};
+use roxmltree::Document;
+
+use crate::{
+    project::Project,
+    ship_log::{find_duplicate_ids, related_duplicates, IdSet, ShipLogContext, ID},
+    utils::error_codes::{self, get_error_code},
+    validation::{ErrorSet, Validator},
+};
+
+#[derive(Default, Debug)]
+pub struct NomaiTextContext {
+    pub text_block_ids: IdSet,
+    pub parent_references: IdSet,
+    pub fact_references: IdSet,
+}
+
+impl NomaiTextContext {
+    pub fn parse(
+        &mut self,
+        text_file: &VersionedTextDocumentIdentifier,
+        raw_str: &str,
+    ) -> Result<()> {
+        let tree = Document::parse(raw_str)?;
+        for block in tree
+            .descendants()
+            .filter(|n| n.tag_name().name() == "TextBlock")
+        {
+            for node in block.children().filter(|n| n.is_element()) {
+                match node.tag_name().name() {
+                    "ID" => self.text_block_ids.push(ID::new(&tree, &node, text_file)),
+                    // Older configs use Parent instead of ParentID
+                    "ParentID" | "Parent" => self
+                        .parent_references
+                        .push(ID::new(&tree, &node, text_file)),
+                    _ => {}
+                }
+            }
+        }
+        for node in tree
+            .descendants()
+            .filter(|n| n.tag_name().name() == "RevealFact")
+            .flat_map(|n| n.children())
+            .filter(|n| n.tag_name().name() == "FactID")
+        {
+            self.fact_references.push(ID::new(&tree, &node, text_file));
+        }
+        Ok(())
+    }
+
+    pub fn from_project(project: &Project) -> Self {
+        let mut ctx = Self::default();
+        for file in project.text_files.iter() {
+            if let Err(why) = ctx.parse(&file.id, &file.contents) {
+                eprintln!("Error parsing nomai text file: {why:?}");
+            }
+        }
+        ctx
+    }
+
+    fn error(
+        id: &ID,
+        code: &str,
+        message: String,
+    ) -> (VersionedTextDocumentIdentifier, Diagnostic) {
+        (
+            id.source_file.clone(),
+            Diagnostic {
+                range: id.range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: get_error_code(code),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message,
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )
+    }
+
+    fn same_file(a: &ID, b: &ID) -> bool {
+        a.source_file.uri == b.source_file.uri
+    }
+
+    pub fn validate(&self, ship_logs: &ShipLogContext) -> ErrorSet {
+        let mut errors: ErrorSet = vec![];
+        let groups = find_duplicate_ids(&self.text_block_ids, |id| {
+            (id.source_file.uri.clone(), id.value.clone())
+        });
+        // The first definition is the one that counts, only the ones after it are reported
+        for group in groups.iter() {
+            for id in group.iter().skip(1) {
+                let mut error = Self::error(
+                    id,
+                    error_codes::TEXT_DUPLICATE_ID,
+                    format!("Duplicate TextBlock ID: `{}`", id.value),
+                );
+                error.1.related_information = Some(related_duplicates(group, id));
+                errors.push(error);
+            }
+        }
+        for reference in self.parent_references.iter() {
+            let exists = self
+                .text_block_ids
+                .iter()
+                .any(|id| Self::same_file(id, reference) && id.value == reference.value);
+            if !exists {
+                errors.push(Self::error(
+                    reference,
+                    error_codes::TEXT_MISSING_PARENT,
+                    format!("Unknown TextBlock ID: `{}`", reference.value),
+                ));
+            }
+        }
+        for reference in self.fact_references.iter() {
+            if !ship_logs.fact_exists(&reference.value) {
+                errors.push(Self::error(
+                    reference,
+                    error_codes::TEXT_INVALID_FACT,
+                    format!("Unknown Fact: `{}`", reference.value),
+                ));
+            }
+        }
+        errors
+    }
+}
+
+#[derive(Default)]
+pub struct NomaiTextValidator();
+
+impl Validator for NomaiTextValidator {
+    fn name() -> &'static str {
+        "nomai_text"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .text_files
+            .iter()
+            .chain(project.ship_log_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        NomaiTextContext::from_project(project).validate(&project.ship_logs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::project::ProjectFile;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_nomai_text() {
+        const SHIP_LOG_STR: &str = include_str!("test_files/test_ship_log.xml");
+        const TEST_STR: &str = include_str!("test_files/nomai_text_invalid.xml");
+
+        let mut ship_logs = ShipLogContext::default();
+        let log_file =
+            VersionedTextDocumentIdentifier::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ship_logs
+            .parse(
+                &log_file,
+                &ProjectFile::dummy(),
+                Path::new("."),
+                SHIP_LOG_STR,
+            )
+            .unwrap();
+
+        let mut ctx = NomaiTextContext::default();
+        let test_file =
+            VersionedTextDocumentIdentifier::new(Url::parse("file://test_text.xml").unwrap(), 0);
+        ctx.parse(&test_file, TEST_STR).unwrap();
+
+        assert_eq!(ctx.text_block_ids.len(), 3);
+        assert_eq!(ctx.fact_references.len(), 2);
+
+        let errors = ctx.validate(&ship_logs);
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].1.message, "Duplicate TextBlock ID: `2`");
+        assert_eq!(errors[0].1.range.start.line, 12);
+        let related = errors[0].1.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert!(related[0].location.range.start.line < 12);
+        assert_eq!(errors[1].1.message, "Unknown TextBlock ID: `3`");
+        assert_eq!(errors[2].1.message, "Unknown Fact: `MISSING_TEXT_FACT`");
+    }
+
+    #[test]
+    fn test_validate_parent_tag() {
+        let mut ctx = NomaiTextContext::default();
+        let test_file =
+            VersionedTextDocumentIdentifier::new(Url::parse("file://test_text.xml").unwrap(), 0);
+        ctx.parse(
+            &test_file,
+            "<NomaiObject><TextBlock><ID>1</ID></TextBlock>\
+             <TextBlock><ID>2</ID><Parent>1</Parent></TextBlock>\
+             <TextBlock><ID>3</ID><Parent>4</Parent></TextBlock></NomaiObject>",
+        )
+        .unwrap();
+
+        assert_eq!(ctx.parent_references.len(), 2);
+
+        let errors = ctx.validate(&ShipLogContext::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Unknown TextBlock ID: `4`");
+    }
+}
@@ -1,4 +1,17 @@
+use json_position_parser::tree::EntryType;
+use lsp_types::{Diagnostic, DiagnosticSeverity, Range, Url};
 use serde::Deserialize;
+use serde_json::{error::Category, Value};
+
+use crate::{
+    project::{Project, ProjectFile},
+    utils::{
+        error_codes::{self, get_error_code},
+        json_path_to_json_pos_path, json_pos_range_to_diag_range, json_value_range,
+        serde_error_range,
+    },
+    validation::{ErrorSet, Validator},
+};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -8,6 +21,38 @@ pub struct ShipLogModule {
 
 const DEFAULT_SOLAR_SYSTEM: &str = "SolarSystem";
 
+/// Keys anywhere in a planet config that hold a fact ID or a list of them
+pub const FACT_ID_KEYS: [&str; 4] = ["reveals", "initialReveal", "revealFacts", "factID"];
+
+/// Paths to every fact ID string, array items are wildcards
+fn fact_id_paths(value: &Value, path: String, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter() {
+                let key_path = format!("{path}/{key}");
+                if FACT_ID_KEYS.contains(&key.as_str()) {
+                    let fact_path = match value {
+                        Value::String(_) => key_path,
+                        Value::Array(_) => format!("{key_path}/*"),
+                        _ => continue,
+                    };
+                    if !paths.contains(&fact_path) {
+                        paths.push(fact_path);
+                    }
+                } else {
+                    fact_id_paths(value, key_path, paths);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values.iter() {
+                fact_id_paths(value, format!("{path}/*"), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn default_star_system() -> String {
     DEFAULT_SOLAR_SYSTEM.to_string()
 }
@@ -20,3 +65,257 @@ pub struct Planet {
     pub starSystem: String,
     pub ShipLog: Option<ShipLogModule>,
 }
+
+pub struct PlanetValidator();
+
+impl PlanetValidator {
+    fn diagnostic(
+        range: Range,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            range,
+            severity: Some(severity),
+            code: get_error_code(code),
+            code_description: None,
+            source: Some(error_codes::ERROR_SOURCE.to_string()),
+            message,
+            related_information: None,
+            tags: None,
+            data: None,
+        }
+    }
+
+    fn value_range(file: &ProjectFile, path: &str) -> Range {
+        json_value_range(&file.json(), path).unwrap_or_default()
+    }
+
+    fn validate_planet(project: &Project, file: &ProjectFile, errors: &mut ErrorSet) {
+        let planet = match serde_json::from_str::<Planet>(&file.json()) {
+            Ok(planet) => planet,
+            Err(why) => {
+                // Syntax errors are reported by JsonSyntaxValidator
+                if why.classify() == Category::Data {
+                    errors.push((
+                        file.id.clone(),
+                        Self::diagnostic(
+                            serde_error_range(&file.contents, &why),
+                            DiagnosticSeverity::ERROR,
+                            error_codes::PLANET_INVALID_CONFIG,
+                            format!("Invalid planet config: {why}"),
+                        ),
+                    ));
+                }
+                return;
+            }
+        };
+        let xml_file = planet
+            .ShipLog
+            .and_then(|m| m.xml_file)
+            .filter(|_| !project.unrooted);
+        if let Some(xml_file) = xml_file {
+            let xml_path = project.root_path.join(&xml_file);
+            let is_loaded = project.ship_log_files.iter().any(|f| {
+                f.id.uri
+                    .to_file_path()
+                    .map(|p| p == xml_path)
+                    .unwrap_or(false)
+            });
+            if !is_loaded {
+                errors.push((
+                    file.id.clone(),
+                    Self::diagnostic(
+                        Self::value_range(file, "/ShipLog/xmlFile"),
+                        DiagnosticSeverity::ERROR,
+                        error_codes::PLANET_MISSING_SHIP_LOG,
+                        format!("Ship log file `{xml_file}` could not be loaded"),
+                    ),
+                ));
+            }
+        }
+        if planet.starSystem != DEFAULT_SOLAR_SYSTEM
+            && !project.find_all_systems().contains(&planet.starSystem)
+        {
+            errors.push((
+                file.id.clone(),
+                Self::diagnostic(
+                    Self::value_range(file, "/starSystem"),
+                    DiagnosticSeverity::WARNING,
+                    error_codes::PLANET_UNKNOWN_SYSTEM,
+                    format!("No config found for star system `{}`", planet.starSystem),
+                ),
+            ));
+        }
+    }
+
+    fn validate_fact_references(project: &Project, file: &ProjectFile, errors: &mut ErrorSet) {
+        let text = file.json();
+        let (Ok(json), Ok(tree)) = (
+            serde_json::from_str::<Value>(&text),
+            json_position_parser::parse_json(&text),
+        ) else {
+            return;
+        };
+        let mut paths = vec![];
+        fact_id_paths(&json, String::new(), &mut paths);
+        for path in paths.iter() {
+            for found in tree.value_at(&json_path_to_json_pos_path(path)) {
+                let EntryType::String(fact) = &found.entry_type else {
+                    continue;
+                };
+                if project.ship_logs.fact_exists(fact) {
+                    continue;
+                }
+                errors.push((
+                    file.id.clone(),
+                    Self::diagnostic(
+                        json_pos_range_to_diag_range(&file.contents, found.range),
+                        DiagnosticSeverity::ERROR,
+                        error_codes::PLANET_UNKNOWN_FACT,
+                        format!("Unknown Fact: `{fact}`"),
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+impl Validator for PlanetValidator {
+    fn name() -> &'static str {
+        "planets"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .planet_files
+            .iter()
+            .chain(project.system_files.iter())
+            .chain(project.ship_log_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        let mut errors = vec![];
+        for file in project.planet_files.iter() {
+            Self::validate_planet(project, file, &mut errors);
+            Self::validate_fact_references(project, file, &mut errors);
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planet_project(contents: &str) -> Project {
+        Project {
+            planet_files: vec![ProjectFile {
+                contents: contents.to_string(),
+                ..ProjectFile::dummy()
+            }
+            .into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_planets() {
+        let validator = PlanetValidator::prepare();
+
+        let errors = validator.validate(&planet_project(r#"{"name": "Test"}"#));
+        assert!(errors.is_empty());
+
+        let errors = validator.validate(&planet_project("{\n  \"name\": \"Test\",\n}"));
+        assert!(errors.is_empty());
+
+        let errors = validator.validate(&planet_project(r#"{"starSystem": "SolarSystem"}"#));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::PLANET_INVALID_CONFIG)
+        );
+
+        let errors = validator.validate(&planet_project(
+            r#"{"name": "Test", "starSystem": "Missing", "ShipLog": {"xmlFile": "missing.xml"}}"#,
+        ));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::PLANET_MISSING_SHIP_LOG)
+        );
+        assert_eq!(
+            errors[1].1.code,
+            get_error_code(error_codes::PLANET_UNKNOWN_SYSTEM)
+        );
+        assert_eq!(errors[1].1.severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn test_validate_unrooted_planet() {
+        let project = Project {
+            unrooted: true,
+            ..planet_project(r#"{"name": "Test", "ShipLog": {"xmlFile": "missing.xml"}}"#)
+        };
+        let errors = PlanetValidator::prepare().validate(&project);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validate_fact_references() {
+        let mut project = planet_project(
+            r#"{
+  "name": "Test",
+  "ShipLog": {"initialReveal": ["MY_FACT", "MY_TYPO"]},
+  "Volumes": {"revealVolumes": [{"reveals": ["TH_VILLAGE_X1", "OTHER_TYPO"]}]},
+  "Props": {"signals": [{"reveals": "MY_FACT"}], "details": [{"factID": "NESTED_TYPO"}]}
+}"#,
+        );
+        project.ship_log_files = vec![ProjectFile::new(
+            Url::parse("file:///logs/test.xml").unwrap(),
+            0,
+            "<AstroObjectEntry><ID>TEST</ID><Entry><ID>ENTRY</ID>\
+             <ExploreFact><ID>MY_FACT</ID></ExploreFact></Entry></AstroObjectEntry>"
+                .to_string(),
+        )
+        .into()];
+        project.rebuild_ship_logs();
+
+        let mut errors = vec![];
+        PlanetValidator::validate_fact_references(&project, &project.planet_files[0], &mut errors);
+        let mut messages = errors
+            .iter()
+            .map(|e| e.1.message.as_str())
+            .collect::<Vec<_>>();
+        messages.sort();
+        assert_eq!(
+            messages,
+            vec![
+                "Unknown Fact: `MY_TYPO`",
+                "Unknown Fact: `NESTED_TYPO`",
+                "Unknown Fact: `OTHER_TYPO`",
+            ]
+        );
+        let typo = errors
+            .iter()
+            .find(|e| e.1.message.contains("MY_TYPO"))
+            .unwrap();
+        assert_eq!(
+            typo.1.range,
+            Range::new(
+                lsp_types::Position::new(2, 43),
+                lsp_types::Position::new(2, 52)
+            )
+        );
+        assert_eq!(
+            typo.1.code,
+            get_error_code(error_codes::PLANET_UNKNOWN_FACT)
+        );
+    }
+}
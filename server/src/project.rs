@@ -1,13 +1,95 @@
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
     time::Instant,
 };
 
-use glob::glob;
-use lsp_types::{Url, VersionedTextDocumentIdentifier};
+use glob::{MatchOptions, Pattern};
+use json_position_parser::tree::EntryType;
+use lsp_types::{
+    DocumentLink, FileRename, TextDocumentContentChangeEvent, TextEdit, Url,
+    VersionedTextDocumentIdentifier, WorkspaceEdit,
+};
+use roxmltree::Document;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    manifest::META_FILE_NAMES,
+    ship_log::{ShipLogCache, ShipLogContext},
+    utils::{
+        json_path_to_json_pos_path, json_pos_string_content_range, lenient_json,
+        lsp_position_to_offset,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlFileKind {
+    ShipLog,
+    Dialogue,
+    Text,
+}
+
+impl XmlFileKind {
+    /// What an object with an `xmlFile` points at, going by the key it's under
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "ShipLog" => Some(Self::ShipLog),
+            "dialogue" => Some(Self::Dialogue),
+            "translatorText" | "nomaiText" | "whiteboard" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+fn walk_xml_references(
+    value: &Value,
+    key: Option<&str>,
+    path: String,
+    references: &mut Vec<(XmlFileKind, String, String)>,
+) {
+    match value {
+        Value::Object(map) => {
+            let kind = key.and_then(XmlFileKind::from_key);
+            if let (Some(kind), Some(Value::String(xml_file))) = (kind, map.get("xmlFile")) {
+                references.push((kind, format!("{path}/xmlFile"), xml_file.clone()));
+            }
+            for (key, value) in map.iter() {
+                walk_xml_references(value, Some(key), format!("{path}/{key}"), references);
+            }
+        }
+        // Items are classified by the key of the array they're in
+        Value::Array(values) => {
+            for value in values.iter() {
+                walk_xml_references(value, key, format!("{path}/*"), references);
+            }
+        }
+        _ => {}
+    }
+}
 
-#[derive(Debug)]
+/// Deduplicated paths to every `xmlFile` in a planet config, for use with `value_at`
+fn xml_reference_paths(planet: &Value) -> Vec<String> {
+    let mut paths = find_xml_references(planet)
+        .into_iter()
+        .map(|(_, path, _)| path)
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Every `xmlFile` in a planet config at any depth, as its kind, the path to it (with
+/// wildcards for array items) and the file it points at
+pub fn find_xml_references(planet: &Value) -> Vec<(XmlFileKind, String, String)> {
+    let mut references = vec![];
+    walk_xml_references(planet, None, String::new(), &mut references);
+    references
+}
+
+#[derive(Clone, Debug)]
 pub struct ProjectFile {
     pub id: VersionedTextDocumentIdentifier,
     pub nice_path: PathBuf,
@@ -25,12 +107,32 @@ impl ProjectFile {
     }
 
     pub fn get_relative(&self, root_path: &Path) -> Option<PathBuf> {
+        // Unrooted projects have nothing to be relative to
+        if root_path.as_os_str().is_empty() {
+            return None;
+        }
         self.nice_path
             .strip_prefix(root_path)
             .ok()
             .map(|p| p.to_owned())
     }
 
+    /// Contents ready to be parsed as JSON, see `lenient_json`
+    pub fn json(&self) -> String {
+        lenient_json(&self.contents)
+    }
+
+    pub fn apply_change(&mut self, change: &TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => {
+                let start = lsp_position_to_offset(&self.contents, range.start);
+                let end = lsp_position_to_offset(&self.contents, range.end).max(start);
+                self.contents.replace_range(start..end, &change.text);
+            }
+            None => self.contents = change.text.clone(),
+        }
+    }
+
     #[cfg(test)]
     pub fn dummy() -> Self {
         Self {
@@ -44,22 +146,224 @@ impl ProjectFile {
     }
 }
 
-type ProjectFiles = Vec<ProjectFile>;
+/// Files are shared with validation snapshots, editing one only copies that file
+type ProjectFiles = Vec<Arc<ProjectFile>>;
+
+pub fn normalize_config_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_start_matches("./").to_string()
+}
+
+pub const LAYOUT_FILE: &str = ".nh-language-server.json";
+
+const DEFAULT_CONFIG_PATTERNS: [&str; 2] = ["*.json", "*.jsonc"];
+
+fn is_json_like(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e == "json" || e == "jsonc")
+        .unwrap_or(false)
+}
+
+/// Folders planet and system configs are found in, relative to the project root,
+/// and the globs config files in them have to match.
+/// Mods with a different layout can override these in `LAYOUT_FILE`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProjectLayout {
+    pub planets: Vec<String>,
+    pub systems: Vec<String>,
+    pub planet_patterns: Vec<String>,
+    pub system_patterns: Vec<String>,
+}
+
+impl Default for ProjectLayout {
+    fn default() -> Self {
+        let patterns = DEFAULT_CONFIG_PATTERNS.map(String::from).to_vec();
+        Self {
+            planets: vec!["planets".to_string()],
+            systems: vec!["systems".to_string()],
+            planet_patterns: patterns.clone(),
+            system_patterns: patterns,
+        }
+    }
+}
+
+impl ProjectLayout {
+    pub fn load(root: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(root.join(LAYOUT_FILE)) else {
+            return Self::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|why| {
+            eprintln!("Invalid {LAYOUT_FILE}, using the default layout: {why}");
+            Self::default()
+        })
+    }
+
+    fn in_folders(folders: &[String], patterns: &[Pattern], relative: &Path) -> bool {
+        folders.iter().any(|folder| {
+            relative
+                .strip_prefix(normalize_config_path(folder))
+                .map(|inner| patterns.iter().any(|p| p.matches_path(inner)))
+                .unwrap_or(false)
+        })
+    }
+
+    pub fn is_planet(&self, relative: &Path) -> bool {
+        Self::in_folders(
+            &self.planets,
+            &compile_patterns(&self.planet_patterns),
+            relative,
+        )
+    }
+
+    pub fn is_system(&self, relative: &Path) -> bool {
+        Self::in_folders(
+            &self.systems,
+            &compile_patterns(&self.system_patterns),
+            relative,
+        )
+    }
+}
+
+/// Turns `.gitignore` lines into globs relative to the folder it's in, negations aren't supported
+fn gitignore_globs(contents: &str) -> Vec<String> {
+    let mut globs = vec![];
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        let pattern = line.trim_end_matches('/');
+        // Patterns with a slash before the end are relative to the .gitignore, others match anywhere
+        let pattern = match pattern.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if pattern.contains('/') => pattern.to_string(),
+            None => format!("**/{pattern}"),
+        };
+        globs.push(format!("{pattern}/**"));
+        if !line.ends_with('/') {
+            globs.push(pattern);
+        }
+    }
+    globs
+}
+
+/// Patterns for the files discovery should skip, from `globs` and optionally `root/.gitignore`
+pub fn exclude_patterns(root: &Path, globs: &[String], use_gitignore: bool) -> Vec<Pattern> {
+    let gitignore = use_gitignore
+        .then(|| fs::read_to_string(root.join(".gitignore")).ok())
+        .flatten()
+        .map(|contents| gitignore_globs(&contents))
+        .unwrap_or_default();
+    globs
+        .iter()
+        .chain(gitignore.iter())
+        .filter_map(|glob| match Pattern::new(glob) {
+            Ok(pattern) => Some(pattern),
+            Err(why) => {
+                eprintln!("Ignoring invalid exclude glob {glob}: {why:?}");
+                None
+            }
+        })
+        .collect()
+}
+
+fn compile_patterns(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Pattern::new(pattern) {
+            Ok(pattern) => Some(pattern),
+            Err(why) => {
+                eprintln!("Invalid config pattern `{pattern}`: {why}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Every file under `folder` matching one of `patterns`, in order.
+/// Symlinked folders are followed, but each real folder is only visited once so cycles end
+fn walk_folder(
+    root: &Path,
+    folder: &Path,
+    patterns: &[Pattern],
+    visited: &mut HashSet<PathBuf>,
+    found: &mut Vec<PathBuf>,
+) {
+    let Ok(real) = folder.canonicalize() else {
+        return;
+    };
+    if !visited.insert(real) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(folder) else {
+        return;
+    };
+    let mut entries = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        // `is_dir` and `is_file` follow symlinks
+        if entry.is_dir() {
+            walk_folder(root, &entry, patterns, visited, found);
+        } else if entry.is_file() {
+            let relative = entry.strip_prefix(root).unwrap_or(&entry);
+            if patterns.iter().any(|p| p.matches_path(relative)) {
+                found.push(entry);
+            }
+        }
+    }
+}
+
+fn is_excluded(excludes: &[Pattern], root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    excludes
+        .iter()
+        .any(|pattern| pattern.matches_path_with(relative, options))
+}
 
 #[derive(Default, Debug)]
 pub struct Project {
     pub root_path: PathBuf,
+    /// No folder on disk backs this project, files only come from didOpen
+    pub unrooted: bool,
+    /// Files matching these are skipped by discovery and not validated, see `exclude_patterns`
+    pub excludes: Vec<Pattern>,
+    pub layout: ProjectLayout,
 
     pub planet_files: ProjectFiles,
     pub system_files: ProjectFiles,
     pub ship_log_files: ProjectFiles,
     pub dialogue_files: ProjectFiles,
     pub text_files: ProjectFiles,
-    pub files_with_diagnostics: Vec<VersionedTextDocumentIdentifier>,
+    /// OWML and New Horizons files at the root of the mod, like manifest.json
+    pub meta_files: ProjectFiles,
+    /// Which planets reference each ship log, dialogue and text file
+    pub planet_references: Arc<HashMap<Url, Vec<Url>>>,
+    /// Shared with validation snapshots like the files
+    pub ship_logs: Arc<ShipLogContext>,
+    pub ship_log_cache: ShipLogCache,
+    /// Other mods whose ship logs this one references, see `set_external_projects`
+    pub external_projects: Vec<PathBuf>,
+    pub external_ship_logs: Arc<ShipLogContext>,
 }
 
 impl Project {
-    fn read_project_file(files: &mut ProjectFiles, path: &Path) {
+    pub fn unrooted() -> Self {
+        Self {
+            unrooted: true,
+            ..Default::default()
+        }
+    }
+
+    fn read_project_file(files: &mut ProjectFiles, path: &Path) -> Option<Url> {
         let mut path = path
             .iter()
             .map(|s| urlencoding::encode(&s.to_str().unwrap()).into_owned())
@@ -68,114 +372,139 @@ impl Project {
             .unwrap()
             .to_string();
         path = urlencoding::decode(&path).unwrap().into_owned();
-        let url = Url::from_file_path(dbg!(&path));
+        let url = Url::from_file_path(&path);
 
         eprintln!("Attempt read {}", path);
 
         match url {
             Ok(url) => {
+                if files.iter().any(|f| f.id.uri == url) {
+                    return Some(url);
+                }
+
                 let contents = fs::read_to_string(path);
 
                 match contents {
-                    Ok(contents) => files.push(ProjectFile::new(url, 0, contents)),
+                    Ok(contents) => {
+                        files.push(Arc::new(ProjectFile::new(url.clone(), 0, contents)));
+                        Some(url)
+                    }
                     Err(why) => {
                         eprintln!("Failed to read {url:?}: {why:?}");
+                        None
                     }
                 }
             }
-            Err(why) => eprintln!("Failed to construct URL: {why:?} (path was {})", path),
+            Err(why) => {
+                eprintln!("Failed to construct URL: {why:?} (path was {})", path);
+                None
+            }
         }
     }
 
-    fn crawl_folder(files: &mut ProjectFiles, path: &Path, folder: &str) {
-        for entry in glob(
-            path.join(folder)
-                .join("**")
-                .join("*.json")
-                .to_str()
-                .unwrap(),
-        )
-        .unwrap()
-        {
-            match entry {
-                Ok(entry) => {
-                    Self::read_project_file(files, entry.as_path());
-                }
-                Err(why) => eprintln!("Failed to get glob entry: {why:?}"),
+    /// Several planets can point at the same XML file, it's only read once
+    fn read_referenced_file(
+        files: &mut ProjectFiles,
+        planet_references: &mut HashMap<Url, Vec<Url>>,
+        excludes: &[Pattern],
+        planet: &Url,
+        root: &Path,
+        xml_file: &str,
+    ) {
+        let path = root.join(normalize_config_path(xml_file));
+        if is_excluded(excludes, root, &path) {
+            return;
+        }
+        if let Some(url) = Self::read_project_file(files, &path) {
+            let planets = planet_references.entry(url).or_default();
+            if !planets.contains(planet) {
+                planets.push(planet.clone());
             }
         }
     }
 
-    fn find_planets(&mut self, path: &Path) {
-        Self::crawl_folder(&mut self.planet_files, path, "planets");
-    }
-
-    fn find_systems(&mut self, path: &Path) {
-        Self::crawl_folder(&mut self.system_files, path, "systems");
+    fn crawl_folder(
+        files: &mut ProjectFiles,
+        excludes: &[Pattern],
+        path: &Path,
+        folder: &str,
+        patterns: &[String],
+    ) {
+        let folder = path.join(normalize_config_path(folder));
+        let mut found = vec![];
+        walk_folder(
+            &folder,
+            &folder,
+            &compile_patterns(patterns),
+            &mut HashSet::new(),
+            &mut found,
+        );
+        for entry in found {
+            if !is_excluded(excludes, path, &entry) {
+                Self::read_project_file(files, &entry);
+            }
+        }
     }
 
-    fn find_ship_logs(&mut self, path: &Path) {
-        for file in self.planet_files.iter() {
-            let json: Result<serde_json::Value, _> = serde_json::from_str(&file.contents);
-            if let Ok(json) = json {
-                let xml_file = json.pointer("/ShipLog/xmlFile").map(|vv| vv.as_str());
-                if let Some(Some(xml_file)) = xml_file {
-                    Self::read_project_file(&mut self.ship_log_files, &path.join(xml_file))
-                }
+    fn find_meta_files(&mut self, path: &Path) {
+        for name in META_FILE_NAMES {
+            let file_path = path.join(name);
+            if file_path.is_file() {
+                Self::read_project_file(&mut self.meta_files, &file_path);
             }
         }
     }
 
-    fn find_dialogue(&mut self, path: &Path) {
-        for file in self.planet_files.iter() {
-            let json: Result<serde_json::Value, _> = serde_json::from_str(&file.contents);
-            if let Ok(json) = json {
-                let arr = json.pointer("/Props/dialogue").map(|a| a.as_array());
-                if let Some(Some(arr)) = arr {
-                    for value in arr.iter().filter(|v| v.is_object()) {
-                        if let Some(Some(xml_file)) = value.get("xmlFile").map(|v| v.as_str()) {
-                            Self::read_project_file(&mut self.dialogue_files, &path.join(xml_file))
-                        }
-                    }
-                }
-            }
+    fn find_planets(&mut self, path: &Path) {
+        for folder in self.layout.planets.iter() {
+            Self::crawl_folder(
+                &mut self.planet_files,
+                &self.excludes,
+                path,
+                folder,
+                &self.layout.planet_patterns,
+            );
         }
     }
 
-    fn find_text(&mut self, path: &Path) {
-        for file in self.planet_files.iter() {
-            let json: Result<serde_json::Value, _> = serde_json::from_str(&file.contents);
-            if let Ok(json) = json {
-                let arr = json.pointer("/Props/translatorText").map(|a| a.as_array());
-                if let Some(Some(arr)) = arr {
-                    for value in arr.iter().filter(|v| v.is_object()) {
-                        if let Some(Some(xml_file)) = value.get("xmlFile").map(|v| v.as_str()) {
-                            Self::read_project_file(&mut self.text_files, &path.join(xml_file))
-                        }
-                    }
-                }
-            }
+    fn find_systems(&mut self, path: &Path) {
+        for folder in self.layout.systems.iter() {
+            Self::crawl_folder(
+                &mut self.system_files,
+                &self.excludes,
+                path,
+                folder,
+                &self.layout.system_patterns,
+            );
         }
+    }
+
+    fn find_referenced_files(&mut self, path: &Path) {
         for file in self.planet_files.iter() {
-            let json: Result<serde_json::Value, _> = serde_json::from_str(&file.contents);
-            if let Ok(json) = json {
-                let arr = json.pointer("/Props/remotes").map(|a| a.as_array());
-                if let Some(Some(arr)) = arr {
-                    for value in arr.iter().filter(|v| v.is_object()) {
-                        if let Some(Some(xml_file)) = value
-                            .get("whiteboard/nomaiText/xmlFile")
-                            .map(|v| v.as_str())
-                        {
-                            Self::read_project_file(&mut self.text_files, &path.join(xml_file))
-                        }
-                    }
-                }
+            let Ok(json) = serde_json::from_str::<Value>(&file.json()) else {
+                continue;
+            };
+            for (kind, _, xml_file) in find_xml_references(&json) {
+                let files = match kind {
+                    XmlFileKind::ShipLog => &mut self.ship_log_files,
+                    XmlFileKind::Dialogue => &mut self.dialogue_files,
+                    XmlFileKind::Text => &mut self.text_files,
+                };
+                Self::read_referenced_file(
+                    files,
+                    Arc::make_mut(&mut self.planet_references),
+                    &self.excludes,
+                    &file.id.uri,
+                    path,
+                    &xml_file,
+                );
             }
         }
     }
 
     pub fn load_from(&mut self, path: &Path) {
         self.root_path = path.to_owned();
+        self.layout = ProjectLayout::load(path);
 
         eprintln!("Begin Project Discovery");
 
@@ -189,19 +518,206 @@ impl Project {
 
         eprintln!("Found {} Star Systems", self.system_files.len());
 
-        self.find_ship_logs(path);
+        self.find_referenced_files(path);
 
         eprintln!("Found {} Ship Logs", self.ship_log_files.len());
-
-        self.find_dialogue(path);
-
         eprintln!("Found {} Dialogue Trees", self.dialogue_files.len());
+        eprintln!("Found {} Nomai Text Definitions", self.text_files.len());
 
-        self.find_text(path);
+        self.find_meta_files(path);
 
-        eprintln!("Found {} Nomai Text Definitions", self.text_files.len());
+        eprintln!("Found {} Mod Metadata Files", self.meta_files.len());
 
         eprintln!("Project Discovery Complete in {:?}", now.elapsed());
+
+        self.rebuild_ship_logs();
+    }
+
+    /// Discovers the project's files again with different excludes, open files keep their edits
+    pub fn set_excludes(&mut self, excludes: Vec<Pattern>) {
+        if self.excludes == excludes {
+            return;
+        }
+        let old = std::mem::take(self);
+        self.excludes = excludes;
+        self.external_projects = old.external_projects;
+        self.external_ship_logs = old.external_ship_logs;
+        self.load_from(&old.root_path);
+        for (files, old_files) in [
+            (&mut self.dialogue_files, old.dialogue_files),
+            (&mut self.ship_log_files, old.ship_log_files),
+            (&mut self.system_files, old.system_files),
+            (&mut self.planet_files, old.planet_files),
+            (&mut self.text_files, old.text_files),
+            (&mut self.meta_files, old.meta_files),
+        ] {
+            Self::keep_open_files(files, old_files);
+        }
+        self.rebuild_ship_logs();
+    }
+
+    /// A copy for validating on another thread, without the parse cache
+    pub fn snapshot(&self) -> Self {
+        Self {
+            root_path: self.root_path.clone(),
+            unrooted: self.unrooted,
+            excludes: self.excludes.clone(),
+            layout: self.layout.clone(),
+            planet_files: self.planet_files.clone(),
+            system_files: self.system_files.clone(),
+            ship_log_files: self.ship_log_files.clone(),
+            dialogue_files: self.dialogue_files.clone(),
+            text_files: self.text_files.clone(),
+            meta_files: self.meta_files.clone(),
+            planet_references: self.planet_references.clone(),
+            ship_logs: self.ship_logs.clone(),
+            ship_log_cache: ShipLogCache::new(),
+            external_projects: self.external_projects.clone(),
+            external_ship_logs: self.external_ship_logs.clone(),
+        }
+    }
+
+    /// Loads the ship logs of the given mod folders, returns `false` if they're already loaded
+    pub fn set_external_projects(&mut self, paths: &[PathBuf]) -> bool {
+        if self.external_projects == paths {
+            return false;
+        }
+        self.external_projects = paths.to_vec();
+        self.external_ship_logs = Arc::new(ShipLogContext::load_external(paths));
+        self.rebuild_ship_logs();
+        true
+    }
+
+    pub fn rebuild_ship_logs(&mut self) {
+        let mut cache = std::mem::take(&mut self.ship_log_cache);
+        self.ship_logs = Arc::new(ShipLogContext::from_project_cached(self, &mut cache));
+        self.ship_log_cache = cache;
+    }
+
+    fn refresh_ship_logs(&mut self, url: &Url) {
+        if self.meta_files.iter().any(|f| &f.id.uri == url) {
+            return;
+        }
+        if self.is_planet(url) {
+            // The planet may now point at different XML files
+            self.refresh_referenced_files();
+            return;
+        }
+        let is_system = self.system_files.iter().any(|f| &f.id.uri == url);
+        // Only copies the ship logs if a validation snapshot still has them
+        if is_system || !Arc::make_mut(&mut self.ship_logs).remove_file(url, &self.root_path) {
+            self.rebuild_ship_logs();
+        } else if let Some(file) = self.ship_log_files.iter().find(|f| &f.id.uri == url) {
+            let res = Arc::make_mut(&mut self.ship_logs).parse(
+                &file.id,
+                file,
+                &self.root_path,
+                &file.contents,
+            );
+            if let Err(why) = res {
+                eprintln!("Error parsing ship log file: {why:?}");
+            }
+        }
+    }
+
+    fn keep_open_files(files: &mut ProjectFiles, old_files: ProjectFiles) {
+        for old_file in old_files.into_iter().filter(|f| f.id.version > 0) {
+            if let Some(file) = files.iter_mut().find(|f| f.id.uri == old_file.id.uri) {
+                *file = old_file;
+            }
+        }
+    }
+
+    fn refresh_referenced_files(&mut self) {
+        if self.unrooted {
+            self.rebuild_ship_logs();
+            return;
+        }
+        let path = self.root_path.clone();
+        let old_ship_logs = std::mem::take(&mut self.ship_log_files);
+        let old_dialogue = std::mem::take(&mut self.dialogue_files);
+        let old_text = std::mem::take(&mut self.text_files);
+        self.planet_references = Default::default();
+        self.find_referenced_files(&path);
+        Self::keep_open_files(&mut self.ship_log_files, old_ship_logs);
+        Self::keep_open_files(&mut self.dialogue_files, old_dialogue);
+        Self::keep_open_files(&mut self.text_files, old_text);
+        self.rebuild_ship_logs();
+    }
+
+    fn is_planet(&self, url: &Url) -> bool {
+        self.planet_files.iter().any(|f| &f.id.uri == url)
+    }
+
+    pub fn add_file(&mut self, path: &Path) {
+        if self.unrooted {
+            return;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root_path) else {
+            return;
+        };
+        if is_excluded(&self.excludes, &self.root_path, path) {
+            return;
+        }
+        let is_json = is_json_like(path);
+        let is_known = Url::from_file_path(path)
+            .map(|url| self.iter_all().any(|f| f.id.uri == url))
+            .unwrap_or(false);
+        if is_known {
+            return;
+        }
+        if META_FILE_NAMES
+            .iter()
+            .any(|name| relative == Path::new(name))
+        {
+            Self::read_project_file(&mut self.meta_files, path);
+        } else if is_json && self.layout.is_planet(relative) {
+            Self::read_project_file(&mut self.planet_files, path);
+            self.refresh_referenced_files();
+        } else if is_json && self.layout.is_system(relative) {
+            Self::read_project_file(&mut self.system_files, path);
+            self.rebuild_ship_logs();
+        } else {
+            self.refresh_referenced_files();
+        }
+    }
+
+    pub fn remove_file(&mut self, url: &Url) {
+        let was_planet = self.is_planet(url);
+        let was_system = self.system_files.iter().any(|f| &f.id.uri == url);
+        let was_meta = self.meta_files.iter().any(|f| &f.id.uri == url);
+        for files in [
+            &mut self.dialogue_files,
+            &mut self.ship_log_files,
+            &mut self.system_files,
+            &mut self.planet_files,
+            &mut self.text_files,
+            &mut self.meta_files,
+        ] {
+            files.retain(|f| &f.id.uri != url);
+        }
+        if was_meta {
+            return;
+        }
+        if was_planet {
+            self.refresh_referenced_files();
+        } else if was_system {
+            self.rebuild_ship_logs();
+        } else {
+            self.refresh_ship_logs(url);
+        }
+    }
+
+    pub fn reload_file(&mut self, url: &Url) {
+        if let Some(file) = self
+            .iter_all_mut()
+            .find(|f| &f.id.uri == url && f.id.version == 0)
+        {
+            if let Ok(contents) = fs::read_to_string(url.path()) {
+                *file = Arc::new(ProjectFile::new(url.clone(), 0, contents));
+            }
+        }
+        self.refresh_ship_logs(url);
     }
 
     fn check_file_add(
@@ -211,8 +727,11 @@ impl Project {
     ) -> bool {
         for file in files.iter_mut() {
             if id.uri == file.id.uri && id.version > file.id.version {
-                file.id = id.clone();
-                file.contents = contents.to_string();
+                *file = Arc::new(ProjectFile::new(
+                    id.uri.clone(),
+                    id.version,
+                    contents.to_string(),
+                ));
                 return true;
             }
         }
@@ -222,6 +741,7 @@ impl Project {
     fn check_file_remove(files: &mut ProjectFiles, url: &Url) -> bool {
         for file in files.iter_mut() {
             if url == &file.id.uri {
+                let file = Arc::make_mut(file);
                 file.id.version = 0;
                 if let Ok(contents) = fs::read_to_string(url.path()) {
                     file.contents = contents;
@@ -232,32 +752,214 @@ impl Project {
         false
     }
 
+    /// For files discovery doesn't pick up, guess what kind of file was opened from its path and root tag
+    fn untracked_files_for(&mut self, uri: &Url, contents: &str) -> Option<&mut ProjectFiles> {
+        let segments = uri.path_segments()?.collect::<Vec<_>>();
+        let name = segments.last()?;
+        if META_FILE_NAMES.contains(name) {
+            Some(&mut self.meta_files)
+        } else if is_json_like(Path::new(name)) {
+            // Unrooted files can only be told apart by the folders they're in
+            let relative = uri
+                .to_file_path()
+                .ok()
+                .filter(|_| !self.unrooted)
+                .and_then(|path| Some(path.strip_prefix(&self.root_path).ok()?.to_owned()));
+            let (is_planet, is_system) = match relative {
+                Some(relative) => (
+                    self.layout.is_planet(&relative),
+                    self.layout.is_system(&relative),
+                ),
+                None => (segments.contains(&"planets"), segments.contains(&"systems")),
+            };
+            if is_planet {
+                Some(&mut self.planet_files)
+            } else if is_system {
+                Some(&mut self.system_files)
+            } else {
+                None
+            }
+        } else if name.ends_with(".xml") {
+            let tree = Document::parse(contents).ok()?;
+            match tree.root_element().tag_name().name() {
+                "AstroObjectEntry" => Some(&mut self.ship_log_files),
+                "DialogueTree" => Some(&mut self.dialogue_files),
+                "NomaiObject" => Some(&mut self.text_files),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
     pub fn open_file(&mut self, id: VersionedTextDocumentIdentifier, contents: &str) {
+        let untracked = self.unrooted || self.is_excluded(&id.uri);
+        if untracked && !self.iter_all().any(|f| f.id.uri == id.uri) {
+            if let Some(files) = self.untracked_files_for(&id.uri, contents) {
+                files.push(Arc::new(ProjectFile::new(
+                    id.uri.clone(),
+                    id.version,
+                    contents.to_string(),
+                )));
+            }
+        }
         for files in [
             &mut self.dialogue_files,
             &mut self.ship_log_files,
             &mut self.system_files,
             &mut self.planet_files,
             &mut self.text_files,
+            &mut self.meta_files,
         ] {
             if Self::check_file_add(files, &id, contents) {
                 break;
             }
         }
+        self.refresh_ship_logs(&id.uri);
+    }
+
+    pub fn apply_changes(
+        &mut self,
+        id: VersionedTextDocumentIdentifier,
+        changes: &[TextDocumentContentChangeEvent],
+    ) {
+        let Some(file) = self.iter_all_mut().find(|f| f.id.uri == id.uri) else {
+            eprintln!("Ignoring changes to file outside the project: {}", id.uri);
+            return;
+        };
+        let file = Arc::make_mut(file);
+        for change in changes.iter() {
+            file.apply_change(change);
+        }
+        file.id = id.clone();
+        self.refresh_ship_logs(&id.uri);
     }
 
     pub fn close_file(&mut self, url: &Url) {
+        let deleted = url.to_file_path().map(|p| !p.exists()).unwrap_or(true);
+        if self.unrooted || deleted || self.is_excluded(url) {
+            // There's no copy on disk to fall back to
+            self.remove_file(url);
+            return;
+        }
         for files in [
             &mut self.dialogue_files,
             &mut self.ship_log_files,
             &mut self.system_files,
             &mut self.planet_files,
             &mut self.text_files,
+            &mut self.meta_files,
         ] {
             if Self::check_file_remove(files, url) {
                 break;
             }
         }
+        self.refresh_ship_logs(url);
+    }
+
+    /// Excluded files are only tracked while they're open
+    pub fn is_excluded(&self, url: &Url) -> bool {
+        !self.unrooted
+            && url
+                .to_file_path()
+                .map(|path| is_excluded(&self.excludes, &self.root_path, &path))
+                .unwrap_or(false)
+    }
+
+    fn relative_config_path(&self, uri: &str) -> Option<String> {
+        if self.unrooted {
+            return None;
+        }
+        let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+        let relative = path.strip_prefix(&self.root_path).ok()?;
+        Some(normalize_config_path(&relative.to_string_lossy()))
+    }
+
+    pub fn file_rename_edits(&self, renames: &[FileRename]) -> WorkspaceEdit {
+        let renames = renames
+            .iter()
+            .filter_map(|r| {
+                Some((
+                    self.relative_config_path(&r.old_uri)?,
+                    self.relative_config_path(&r.new_uri)?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for file in self.planet_files.iter() {
+            let text = file.json();
+            let (Ok(tree), Ok(json)) = (
+                json_position_parser::parse_json(&text),
+                serde_json::from_str::<Value>(&text),
+            ) else {
+                continue;
+            };
+            for path in xml_reference_paths(&json).iter() {
+                for found in tree.value_at(&json_path_to_json_pos_path(path)) {
+                    let EntryType::String(value) = &found.entry_type else {
+                        continue;
+                    };
+                    let value = normalize_config_path(value);
+                    let new_value = renames.iter().find_map(|(old, new)| {
+                        if &value == old {
+                            Some(new.clone())
+                        } else {
+                            // Renaming a folder moves everything in it
+                            value
+                                .strip_prefix(&format!("{old}/"))
+                                .map(|rest| format!("{new}/{rest}"))
+                        }
+                    });
+                    if let Some(new_value) = new_value {
+                        changes
+                            .entry(file.id.uri.clone())
+                            .or_default()
+                            .push(TextEdit::new(
+                                json_pos_string_content_range(&file.contents, found.range),
+                                new_value,
+                            ));
+                    }
+                }
+            }
+        }
+        WorkspaceEdit::new(changes)
+    }
+
+    /// Links from a planet's `xmlFile`s to the files they point at, missing files are left to
+    /// FilePathValidator
+    pub fn document_links(&self, uri: &Url) -> Vec<DocumentLink> {
+        if self.unrooted {
+            return vec![];
+        }
+        let Some(file) = self.planet_files.iter().find(|f| &f.id.uri == uri) else {
+            return vec![];
+        };
+        let text = file.json();
+        let (Ok(tree), Ok(json)) = (
+            json_position_parser::parse_json(&text),
+            serde_json::from_str::<Value>(&text),
+        ) else {
+            return vec![];
+        };
+        let mut links = vec![];
+        for path in xml_reference_paths(&json).iter() {
+            for found in tree.value_at(&json_path_to_json_pos_path(path)) {
+                let EntryType::String(value) = &found.entry_type else {
+                    continue;
+                };
+                let target = self.root_path.join(normalize_config_path(value));
+                if !target.is_file() {
+                    continue;
+                }
+                links.push(DocumentLink {
+                    range: json_pos_string_content_range(&file.contents, found.range),
+                    target: Url::from_file_path(&target).ok(),
+                    tooltip: None,
+                    data: None,
+                });
+            }
+        }
+        links
     }
 
     pub fn iter_all(&self) -> impl Iterator<Item = &ProjectFile> {
@@ -267,6 +969,19 @@ impl Project {
             .chain(&self.ship_log_files)
             .chain(&self.dialogue_files)
             .chain(&self.text_files)
+            .chain(&self.meta_files)
+            .map(|f| f.as_ref())
+    }
+
+    /// Use `Arc::make_mut` on the file to change it, that only copies it if a snapshot has it
+    pub fn iter_all_mut(&mut self) -> impl Iterator<Item = &mut Arc<ProjectFile>> {
+        self.planet_files
+            .iter_mut()
+            .chain(&mut self.system_files)
+            .chain(&mut self.ship_log_files)
+            .chain(&mut self.dialogue_files)
+            .chain(&mut self.text_files)
+            .chain(&mut self.meta_files)
     }
 
     pub fn find_all_systems(&self) -> Vec<String> {
@@ -282,3 +997,637 @@ impl Project {
         systems
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{Position, Range};
+
+    use crate::{
+        dialogue::DialogueValidator,
+        file_paths::SchemaFetch,
+        nomai_text::NomaiTextValidator,
+        validation::{MainValidator, Validator},
+    };
+
+    use super::*;
+
+    fn change(range: Option<Range>, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range,
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        let uri = project.ship_log_files[0].id.uri.clone();
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "<ID>\n  FOO\n</ID>".to_string();
+
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(uri.clone(), 1),
+            &[
+                change(Some(range((1, 2), (1, 5))), "BAR_ENTRY"),
+                change(Some(range((2, 6), (2, 6))), "\n<Name>🚀 Rocket</Name>"),
+                change(Some(range((3, 9), (3, 15))), "Ship"),
+                change(Some(range((0, 0), (0, 0))), "<!-- é -->"),
+            ],
+        );
+
+        let file = &project.ship_log_files[0];
+        assert_eq!(file.id.version, 1);
+        assert_eq!(
+            file.contents,
+            "<!-- é --><ID>\n  BAR_ENTRY\n</ID>\n<Name>🚀 Ship</Name>"
+        );
+
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(uri, 2),
+            &[change(None, "<ID>BAZ</ID>")],
+        );
+        assert_eq!(project.ship_log_files[0].contents, "<ID>BAZ</ID>");
+    }
+
+    #[test]
+    fn test_apply_changes_full_fallback() {
+        let mut project = Project {
+            planet_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        let uri = project.planet_files[0].id.uri.clone();
+
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(uri, 1),
+            &[
+                change(Some(range((0, 0), (0, 0))), "ignored"),
+                change(None, "{\r\n  \"name\": \"Foo\"\r\n}"),
+                change(Some(range((1, 11), (1, 14))), "Bar"),
+                change(Some(range((1, 15), (1, 15))), ","),
+            ],
+        );
+
+        assert_eq!(
+            project.planet_files[0].contents,
+            "{\r\n  \"name\": \"Bar\",\r\n}"
+        );
+    }
+
+    #[test]
+    fn test_ship_log_cache_per_file() {
+        let log = |uri: &str, entry_id: &str| {
+            ProjectFile::new(
+                Url::parse(uri).unwrap(),
+                0,
+                format!(
+                    "<AstroObjectEntry><ID>{entry_id}_PLANET</ID><Entry><ID>{entry_id}</ID>\
+                     <ExploreFact><ID>{entry_id}_X1</ID></ExploreFact></Entry></AstroObjectEntry>"
+                ),
+            )
+        };
+        let mut project = Project {
+            ship_log_files: vec![
+                log("file:///a.xml", "A").into(),
+                log("file:///b.xml", "B").into(),
+            ],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let entry_ids = |project: &Project| {
+            let mut ids = project
+                .ship_logs
+                .entry_ids
+                .iter()
+                .map(|id| id.value.clone())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(entry_ids(&project), vec!["A", "B"]);
+
+        let new_contents = "<AstroObjectEntry><ID>A_PLANET</ID><Entry><ID>C</ID>\
+                            </Entry></AstroObjectEntry>";
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(Url::parse("file:///a.xml").unwrap(), 1),
+            &[TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: new_contents.to_string(),
+            }],
+        );
+
+        assert_eq!(entry_ids(&project), vec!["B", "C"]);
+        assert!(project.ship_logs.entries.contains_key("B"));
+        assert!(!project.ship_logs.entries.contains_key("A"));
+        assert!(project.ship_logs.fact_exists("B_X1"));
+        assert!(!project.ship_logs.fact_exists("A_X1"));
+        assert_eq!(project.ship_logs.astro_object_ids.len(), 2);
+        let b_id = project
+            .ship_logs
+            .entry_ids
+            .iter()
+            .find(|id| id.value == "B")
+            .unwrap();
+        assert_eq!(b_id.source_file.uri.as_str(), "file:///b.xml");
+    }
+
+    #[test]
+    fn test_ship_log_parse_cache() {
+        let log = |uri: &str, entry_id: &str| {
+            ProjectFile::new(
+                Url::parse(uri).unwrap(),
+                0,
+                format!("<AstroObjectEntry><ID>P</ID><Entry><ID>{entry_id}</ID></Entry></AstroObjectEntry>"),
+            )
+        };
+        let mut project = Project {
+            ship_log_files: vec![
+                log("file:///a.xml", "A").into(),
+                log("file:///b.xml", "B").into(),
+            ],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        assert_eq!(project.ship_log_cache.len(), 2);
+
+        // Tamper with the cached parse of b.xml, it shouldn't be parsed again
+        let b_uri = Url::parse("file:///b.xml").unwrap();
+        project
+            .ship_log_cache
+            .get_mut(&b_uri)
+            .unwrap()
+            .2
+            .entries
+            .clear();
+        project.ship_log_files[0] = log("file:///a.xml", "C").into();
+        project.rebuild_ship_logs();
+        assert!(project.ship_logs.entries.contains_key("C"));
+        assert!(!project.ship_logs.entries.contains_key("A"));
+        assert!(!project.ship_logs.entries.contains_key("B"));
+
+        project.ship_log_files.pop();
+        project.rebuild_ship_logs();
+        assert_eq!(project.ship_log_cache.len(), 1);
+    }
+
+    #[test]
+    fn test_incremental_ship_log_refresh() {
+        let log = |name: &str, entry_id: &str| {
+            ProjectFile::new(
+                Url::parse(&format!("file:///mod/planets/{name}.xml")).unwrap(),
+                0,
+                format!(
+                    "<AstroObjectEntry><ID>SHARED_PLANET</ID><Entry><ID>{entry_id}</ID>\
+                     <ExploreFact><ID>{entry_id}_X1</ID></ExploreFact></Entry></AstroObjectEntry>"
+                ),
+            )
+        };
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            ship_log_files: vec![log("a", "A").into(), log("b", "B").into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let uri = project.ship_log_files[0].id.uri.clone();
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(uri, 1),
+            &[change(Some(range((0, 51), (0, 52))), "C")],
+        );
+        assert!(project.ship_logs.entries.contains_key("C"));
+
+        let summary = |ctx: &ShipLogContext| {
+            let mut entries = ctx
+                .entry_ids
+                .iter()
+                .map(|id| id.value.clone())
+                .collect::<Vec<_>>();
+            entries.sort();
+            let mut facts = ctx.fact_to_entry.iter().collect::<Vec<_>>();
+            facts.sort();
+            let mut paths = ctx.relative_to_astro_object.iter().collect::<Vec<_>>();
+            paths.sort();
+            format!("{entries:?} {facts:?} {paths:?}")
+        };
+        let incremental = summary(&project.ship_logs);
+        project.rebuild_ship_logs();
+        assert_eq!(incremental, summary(&project.ship_logs));
+        assert_eq!(project.ship_logs.relative_to_astro_object.len(), 2);
+    }
+
+    #[test]
+    fn test_planet_change_rediscovers_ship_logs() {
+        let root = std::env::temp_dir().join("nh_test_rediscover");
+        fs::create_dir_all(&root).unwrap();
+        for name in ["a", "b"] {
+            let entry_id = name.to_uppercase();
+            fs::write(
+                root.join(format!("{name}.xml")),
+                format!("<AstroObjectEntry><ID>P</ID><Entry><ID>{entry_id}</ID></Entry></AstroObjectEntry>"),
+            )
+            .unwrap();
+        }
+        let planet_uri = Url::parse("file:///planet.json").unwrap();
+        let mut project = Project {
+            root_path: root.clone(),
+            planet_files: vec![ProjectFile::new(
+                planet_uri.clone(),
+                1,
+                r#"{"ShipLog": {"xmlFile": "a.xml"}}"#.to_string(),
+            )
+            .into()],
+            ..Default::default()
+        };
+        project.refresh_referenced_files();
+        assert_eq!(project.ship_log_files.len(), 1);
+        assert!(project.ship_logs.entries.contains_key("A"));
+
+        project.apply_changes(
+            VersionedTextDocumentIdentifier::new(planet_uri, 2),
+            &[change(None, r#"{"ShipLog": {"xmlFile": "b.xml"}}"#)],
+        );
+        assert_eq!(project.ship_log_files.len(), 1);
+        assert!(project.ship_log_files[0].id.uri.path().ends_with("b.xml"));
+        assert!(project.ship_logs.entries.contains_key("B"));
+        assert!(!project.ship_logs.entries.contains_key("A"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_shared_xml_file() {
+        let root = std::env::temp_dir().join("nh_test_shared_xml");
+        fs::create_dir_all(root.join("planets")).unwrap();
+        fs::create_dir_all(root.join("dialogue")).unwrap();
+        fs::write(
+            root.join("dialogue/shared.xml"),
+            "<DialogueTree><DialogueNode><Name>A</Name>\
+             <DialogueTarget>B</DialogueTarget></DialogueNode></DialogueTree>",
+        )
+        .unwrap();
+        for (name, xml_file) in [("a", "dialogue/shared.xml"), ("b", "./dialogue/shared.xml")] {
+            fs::write(
+                root.join(format!("planets/{name}.json")),
+                format!(r#"{{"Props": {{"dialogue": [{{"xmlFile": "{xml_file}"}}]}}}}"#),
+            )
+            .unwrap();
+        }
+        let mut project = Project::default();
+        project.load_from(&root);
+
+        assert_eq!(project.planet_files.len(), 2);
+        assert_eq!(project.dialogue_files.len(), 1);
+        let dialogue_uri = project.dialogue_files[0].id.uri.clone();
+        assert_eq!(project.planet_references[&dialogue_uri].len(), 2);
+
+        let errors = DialogueValidator::prepare().validate(&project);
+        assert_eq!(errors.len(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_config_path() {
+        assert_eq!(
+            normalize_config_path("planets\\logs\\example.xml"),
+            "planets/logs/example.xml"
+        );
+        assert_eq!(
+            normalize_config_path("./planets/logs/example.xml"),
+            "planets/logs/example.xml"
+        );
+    }
+
+    #[test]
+    fn test_file_rename_edits_outside_project() {
+        let project = Project {
+            root_path: PathBuf::from("/mod"),
+            ..Default::default()
+        };
+        let edit = project.file_rename_edits(&[FileRename {
+            old_uri: "file:///elsewhere/old.xml".to_string(),
+            new_uri: "file:///elsewhere/new.xml".to_string(),
+        }]);
+        assert!(edit.changes.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_load_meta_files() {
+        let root = std::env::temp_dir().join("nh_test_meta_files");
+        fs::create_dir_all(root.join("planets")).unwrap();
+        fs::write(root.join("manifest.json"), "{}").unwrap();
+        fs::write(root.join("default-config.json"), "{}").unwrap();
+        fs::write(root.join("planets").join("manifest.json"), "{}").unwrap();
+
+        let mut project = Project::default();
+        project.load_from(&root);
+        assert_eq!(project.meta_files.len(), 2);
+        assert_eq!(project.planet_files.len(), 1);
+
+        let addon_manifest = root.join("addon-manifest.json");
+        fs::write(&addon_manifest, "{}").unwrap();
+        project.add_file(&addon_manifest);
+        assert_eq!(project.meta_files.len(), 3);
+
+        project.remove_file(&Url::from_file_path(&addon_manifest).unwrap());
+        assert_eq!(project.meta_files.len(), 2);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_xml_references() {
+        let planet = serde_json::json!({
+            "ShipLog": { "xmlFile": "log.xml" },
+            "Props": {
+                "dialogue": [{ "xmlFile": "a.xml" }, { "xmlFile": "b.xml" }],
+                "details": [{ "path": "Prefab", "dialogue": { "xmlFile": "c.xml" } }],
+                "remotes": [{ "whiteboard": { "nomaiText": [{ "xmlFile": "d.xml" }] } }],
+                "translatorText": [{ "xmlFile": "e.xml" }],
+                "signals": [{ "xmlFile": "not_text.xml" }]
+            }
+        });
+        let mut references = find_xml_references(&planet)
+            .into_iter()
+            .map(|(kind, _, file)| (kind, file))
+            .collect::<Vec<_>>();
+        references.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            references,
+            vec![
+                (XmlFileKind::Dialogue, "a.xml".to_string()),
+                (XmlFileKind::Dialogue, "b.xml".to_string()),
+                (XmlFileKind::Dialogue, "c.xml".to_string()),
+                (XmlFileKind::Text, "d.xml".to_string()),
+                (XmlFileKind::Text, "e.xml".to_string()),
+                (XmlFileKind::ShipLog, "log.xml".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_whiteboard_text() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/whiteboard_mod");
+        let mut project = Project::default();
+        project.load_from(&root);
+
+        assert_eq!(project.text_files.len(), 1);
+        let text_file = &project.text_files[0];
+        assert!(text_file.nice_path.ends_with("planets/text/whiteboard.xml"));
+        assert_eq!(project.planet_references[&text_file.id.uri].len(), 1);
+
+        let errors = NomaiTextValidator::prepare().validate(&project);
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|(id, _)| id.uri == text_file.id.uri));
+
+        let old_uri = Url::from_file_path(root.join("planets/text/whiteboard.xml")).unwrap();
+        let new_uri = Url::from_file_path(root.join("planets/text/board.xml")).unwrap();
+        let edit = project.file_rename_edits(&[FileRename {
+            old_uri: old_uri.to_string(),
+            new_uri: new_uri.to_string(),
+        }]);
+        let edits = edit.changes.unwrap();
+        let edits = edits.values().next().unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "planets/text/board.xml");
+    }
+
+    #[test]
+    fn test_document_links() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/whiteboard_mod");
+        let planet_uri = Url::from_file_path(root.join("planets/Linked.json")).unwrap();
+        let contents = r#"{"Props": {
+    "dialogue": [{"xmlFile": "planets/text/missing.xml"}],
+    "translatorText": [{"xmlFile": "./planets/text/whiteboard.xml"}]
+}}"#;
+        let project = Project {
+            root_path: root.clone(),
+            planet_files: vec![
+                ProjectFile::new(planet_uri.clone(), 1, contents.to_string()).into(),
+            ],
+            ..Default::default()
+        };
+
+        let links = project.document_links(&planet_uri);
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            links[0].target,
+            Some(Url::from_file_path(root.join("planets/text/whiteboard.xml")).unwrap())
+        );
+        assert_eq!(
+            links[0].range,
+            Range::new(Position::new(2, 36), Position::new(2, 65))
+        );
+
+        assert!(Project::unrooted().document_links(&planet_uri).is_empty());
+    }
+
+    #[test]
+    fn test_gitignore_globs() {
+        assert_eq!(
+            gitignore_globs("# Comment\n\nbuild/\n/dist\n!keep.json\nout/*.json\n*.bak"),
+            vec![
+                "**/build/**",
+                "dist/**",
+                "dist",
+                "out/*.json/**",
+                "out/*.json",
+                "**/*.bak/**",
+                "**/*.bak",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_with_excludes() {
+        let root = std::env::temp_dir().join("nh_test_excludes");
+        let planet = r#"{"name": "Test", "ShipLog": {"xmlFile": "planets/build/log.xml"}}"#;
+        for folder in ["planets/build", "planets/node_modules/tool"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+            fs::write(root.join(folder).join("Test.json"), planet).unwrap();
+        }
+        fs::write(root.join("planets/Test.json"), planet).unwrap();
+        fs::write(root.join("planets/build/log.xml"), "<AstroObjectEntry/>").unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        let mut project = Project {
+            excludes: exclude_patterns(&root, &["**/node_modules/**".to_string()], true),
+            ..Default::default()
+        };
+        project.load_from(&root);
+        assert_eq!(project.planet_files.len(), 1);
+        assert!(project.planet_files[0]
+            .nice_path
+            .ends_with("planets/Test.json"));
+        assert!(project.ship_log_files.is_empty());
+
+        // Opened files are still tracked, but left out of validation
+        let excluded = Url::from_file_path(root.join("planets/build/Test.json")).unwrap();
+        assert!(project.is_excluded(&excluded));
+        project.open_file(
+            VersionedTextDocumentIdentifier::new(excluded.clone(), 1),
+            "{",
+        );
+        assert_eq!(project.planet_files.len(), 2);
+        let validator = MainValidator::new(&Default::default(), SchemaFetch::Blocking);
+        assert!(validator
+            .validate_all(&project)
+            .iter()
+            .all(|(id, _)| id.uri != excluded));
+        project.close_file(&excluded);
+        assert_eq!(project.planet_files.len(), 1);
+
+        // Changing the excludes discovers files again without losing unsaved edits
+        let opened = Url::from_file_path(root.join("planets/Test.json")).unwrap();
+        project.open_file(
+            VersionedTextDocumentIdentifier::new(opened.clone(), 2),
+            "{}",
+        );
+        project.set_excludes(exclude_patterns(&root, &[], false));
+        assert_eq!(project.planet_files.len(), 3);
+        assert_eq!(project.ship_log_files.len(), 1);
+        let file = project.iter_all().find(|f| f.id.uri == opened).unwrap();
+        assert_eq!(file.contents, "{}");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_load_commented_configs() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/commented_mod");
+        let mut project = Project::default();
+        project.load_from(&root);
+
+        assert_eq!(project.ship_log_files.len(), 1);
+        assert_eq!(
+            project.ship_logs.position_map.get("COMMENTED_ENTRY"),
+            Some(&(0.0, 100.0))
+        );
+        let errors =
+            MainValidator::new(&Default::default(), SchemaFetch::Blocking).validate_all(&project);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_load_custom_layout() {
+        let root = std::env::temp_dir().join("nh_test_layout");
+        let planet = r#"{"name": "Test"}"#;
+        for folder in ["configs/bodies", "extra", "configs/systems", "planets"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        fs::write(root.join("configs/bodies/A.json"), planet).unwrap();
+        fs::write(root.join("extra/B.json"), planet).unwrap();
+        fs::write(root.join("planets/Ignored.json"), planet).unwrap();
+        fs::write(root.join("configs/systems/System.json"), "{}").unwrap();
+        fs::write(
+            root.join(LAYOUT_FILE),
+            r#"{"planets": ["configs/bodies", "./extra"], "systems": ["configs/systems"]}"#,
+        )
+        .unwrap();
+
+        let mut project = Project::default();
+        project.load_from(&root);
+        assert_eq!(project.planet_files.len(), 2);
+        assert_eq!(project.system_files.len(), 1);
+
+        fs::write(root.join("extra/C.json"), planet).unwrap();
+        project.add_file(&root.join("extra/C.json"));
+        assert_eq!(project.planet_files.len(), 3);
+
+        // Without a valid layout file the usual folders are used
+        fs::write(root.join(LAYOUT_FILE), "{").unwrap();
+        let mut project = Project::default();
+        project.load_from(&root);
+        assert_eq!(project.planet_files.len(), 1);
+        assert!(project.system_files.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_jsonc_and_symlinks() {
+        let root = std::env::temp_dir().join("nh_test_symlinks");
+        let shared = std::env::temp_dir().join("nh_test_symlinks_shared");
+        // Links can't be made over the ones left by a failed run
+        let _ = fs::remove_dir_all(&root);
+        for folder in [root.join("planets"), shared.clone()] {
+            fs::create_dir_all(folder).unwrap();
+        }
+        fs::write(
+            root.join("planets/Commented.jsonc"),
+            "{\n  // The planet's name\n  \"name\": \"Commented\"\n}",
+        )
+        .unwrap();
+        fs::write(root.join("planets/notes.txt"), "Not a config").unwrap();
+        fs::write(shared.join("Shared.json"), "{}").unwrap();
+        std::os::unix::fs::symlink(&shared, root.join("systems")).unwrap();
+        // A folder linking to itself shouldn't be walked forever
+        std::os::unix::fs::symlink(root.join("planets"), root.join("planets/loop")).unwrap();
+
+        let mut project = Project::default();
+        project.load_from(&root);
+        assert_eq!(project.planet_files.len(), 1);
+        assert_eq!(project.system_files.len(), 1);
+        assert_eq!(project.find_all_systems(), vec!["Shared".to_string()]);
+
+        let planet = &project.planet_files[0];
+        let value = serde_json::from_str::<Value>(&planet.json()).unwrap();
+        assert_eq!(value["name"], "Commented");
+        assert!(planet.contents.contains("// The planet's name"));
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&shared).unwrap();
+    }
+
+    #[test]
+    fn test_close_deleted_file() {
+        let url = Url::parse("file:///nonexistent/planets/logs/test.xml").unwrap();
+        let mut project = Project {
+            ship_log_files: vec![
+                ProjectFile::new(url.clone(), 3, "<ID>FOO</ID>".to_string()).into()
+            ],
+            ..Default::default()
+        };
+        project.close_file(&url);
+        assert!(project.ship_log_files.is_empty());
+    }
+
+    #[test]
+    fn test_unrooted_project() {
+        let mut project = Project::unrooted();
+        let log_url = Url::parse("vscode-remote://host/mod/planets/logs/test.xml").unwrap();
+        project.open_file(
+            VersionedTextDocumentIdentifier::new(log_url.clone(), 1),
+            "<AstroObjectEntry><ID>TEST</ID><Entry><ID>ENTRY</ID></Entry></AstroObjectEntry>",
+        );
+        let planet_url = Url::parse("vscode-remote://host/mod/planets/test.json").unwrap();
+        project.open_file(
+            VersionedTextDocumentIdentifier::new(planet_url.clone(), 1),
+            r#"{"name": "Test", "ShipLog": {"xmlFile": "planets/logs/test.xml"}}"#,
+        );
+        let other_url = Url::parse("vscode-remote://host/mod/README.json").unwrap();
+        project.open_file(VersionedTextDocumentIdentifier::new(other_url, 1), "{}");
+
+        assert_eq!(project.ship_log_files.len(), 1);
+        assert_eq!(project.planet_files.len(), 1);
+        assert_eq!(project.iter_all().count(), 2);
+        assert!(project.ship_logs.entries.contains_key("ENTRY"));
+        assert!(project.ship_logs.relative_to_astro_object.is_empty());
+
+        // Opening the planet must not crawl the working directory for its references
+        assert!(project.planet_references.is_empty());
+
+        project.close_file(&log_url);
+        assert!(project.ship_log_files.is_empty());
+        assert!(!project.ship_logs.entries.contains_key("ENTRY"));
+    }
+}
@@ -0,0 +1,544 @@
+use lsp_server::{ErrorCode, RequestId, Response};
+use lsp_types::{notification::Notification, request::Request};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    project::Project,
+    ship_log::{
+        CuriosityInfo, EntryDetails, ShipLogFact, ShipLogGraph, SystemEntriesResponse, SystemFact,
+    },
+};
+
+pub enum GetSystems {}
+
+impl Request for GetSystems {
+    type Params = ();
+    type Result = Vec<String>;
+    const METHOD: &'static str = "getSystems";
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SystemParams {
+    // The VS Code client sends a lone string as `[system]`
+    Positional((String,)),
+    Named {
+        system: String,
+        #[serde(default, rename = "includeExternal")]
+        include_external: bool,
+    },
+}
+
+impl From<SystemParams> for GetEntriesForSystemParams {
+    fn from(params: SystemParams) -> Self {
+        match params {
+            SystemParams::Positional((system,)) => Self {
+                system,
+                include_external: false,
+            },
+            SystemParams::Named {
+                system,
+                include_external,
+            } => Self {
+                system,
+                include_external,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "SystemParams", rename_all = "camelCase")]
+pub struct GetEntriesForSystemParams {
+    pub system: String,
+    /// Also send entries from external projects in the system
+    pub include_external: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetEntriesForSystemResponse {
+    /// Unknown systems get an empty response instead of an error
+    pub found: bool,
+    #[serde(flatten)]
+    pub entries: SystemEntriesResponse,
+}
+
+pub enum GetEntriesForSystem {}
+
+impl Request for GetEntriesForSystem {
+    type Params = GetEntriesForSystemParams;
+    type Result = GetEntriesForSystemResponse;
+    const METHOD: &'static str = "getEntriesForSystem";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetShipLogGraphResponse {
+    pub found: bool,
+    #[serde(flatten)]
+    pub graph: ShipLogGraph,
+}
+
+pub enum GetShipLogGraph {}
+
+impl Request for GetShipLogGraph {
+    /// `includeExternal` is ignored
+    type Params = GetEntriesForSystemParams;
+    type Result = GetShipLogGraphResponse;
+    const METHOD: &'static str = "getShipLogGraph";
+}
+
+pub enum GetFactsForSystem {}
+
+impl Request for GetFactsForSystem {
+    /// `includeExternal` is ignored
+    type Params = GetEntriesForSystemParams;
+    type Result = Vec<SystemFact>;
+    const METHOD: &'static str = "getFactsForSystem";
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CuriositiesParams {
+    // `[]` or `[system]`
+    Positional(Vec<String>),
+    Named {
+        #[serde(default)]
+        system: Option<String>,
+    },
+    Empty,
+}
+
+impl From<CuriositiesParams> for GetCuriositiesParams {
+    fn from(params: CuriositiesParams) -> Self {
+        let system = match params {
+            CuriositiesParams::Positional(params) => params.into_iter().next(),
+            CuriositiesParams::Named { system } => system,
+            CuriositiesParams::Empty => None,
+        };
+        Self { system }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "CuriositiesParams")]
+pub struct GetCuriositiesParams {
+    /// Without a system every curiosity in the project is returned
+    pub system: Option<String>,
+}
+
+pub enum GetCuriosities {}
+
+impl Request for GetCuriosities {
+    type Params = GetCuriositiesParams;
+    type Result = Vec<CuriosityInfo>;
+    const METHOD: &'static str = "getCuriosities";
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntryParams {
+    Positional((String,)),
+    Named { id: String },
+}
+
+impl From<EntryParams> for GetEntryParams {
+    fn from(params: EntryParams) -> Self {
+        match params {
+            EntryParams::Positional((id,)) | EntryParams::Named { id } => Self { id },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "EntryParams")]
+pub struct GetEntryParams {
+    pub id: String,
+}
+
+pub enum GetEntry {}
+
+impl Request for GetEntry {
+    type Params = GetEntryParams;
+    /// `null` when there's no entry with the ID
+    type Result = Option<EntryDetails>;
+    const METHOD: &'static str = "getEntry";
+}
+
+pub enum GetFactsForEntry {}
+
+impl Request for GetFactsForEntry {
+    type Params = GetEntryParams;
+    type Result = Vec<ShipLogFact>;
+    const METHOD: &'static str = "getFactsForEntry";
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub planet_files: usize,
+    pub system_files: usize,
+    pub ship_log_files: usize,
+    pub dialogue_files: usize,
+    pub text_files: usize,
+    /// Vanilla and external entries aren't counted
+    pub entries: usize,
+    pub facts: usize,
+    pub files_with_diagnostics: usize,
+}
+
+impl ProjectStats {
+    pub fn new(project: &Project, files_with_diagnostics: usize) -> Self {
+        Self {
+            planet_files: project.planet_files.len(),
+            system_files: project.system_files.len(),
+            ship_log_files: project.ship_log_files.len(),
+            dialogue_files: project.dialogue_files.len(),
+            text_files: project.text_files.len(),
+            entries: project.ship_logs.custom_entry_count(),
+            facts: project.ship_logs.fact_ids.len(),
+            files_with_diagnostics,
+        }
+    }
+}
+
+pub enum GetProjectStats {}
+
+impl Request for GetProjectStats {
+    type Params = ();
+    type Result = ProjectStats;
+    const METHOD: &'static str = "getProjectStats";
+}
+
+/// Sent after every validation pass so the client can keep its status bar up to date
+pub enum ProjectStatsNotification {}
+
+impl Notification for ProjectStatsNotification {
+    type Params = ProjectStats;
+    const METHOD: &'static str = "nh/projectStats";
+}
+
+pub fn parse_params<R: Request>(
+    req: lsp_server::Request,
+) -> Result<(RequestId, R::Params), Response> {
+    match serde_json::from_value(req.params) {
+        Ok(params) => Ok((req.id, params)),
+        Err(why) => Err(Response::new_err(
+            req.id,
+            ErrorCode::InvalidParams as i32,
+            format!("Invalid params for {}: {why}", R::METHOD),
+        )),
+    }
+}
+
+fn not_found(id: RequestId, message: String) -> Response {
+    Response::new_err(id, ErrorCode::RequestFailed as i32, message)
+}
+
+pub fn get_systems(project: &Project, req: lsp_server::Request) -> Response {
+    match parse_params::<GetSystems>(req) {
+        Ok((id, ())) => Response::new_ok(id, project.find_all_systems()),
+        Err(response) => response,
+    }
+}
+
+pub fn get_project_stats(
+    project: &Project,
+    files_with_diagnostics: usize,
+    req: lsp_server::Request,
+) -> Response {
+    match parse_params::<GetProjectStats>(req) {
+        Ok((id, ())) => Response::new_ok(id, ProjectStats::new(project, files_with_diagnostics)),
+        Err(response) => response,
+    }
+}
+
+pub fn get_entries_for_system(project: &Project, req: lsp_server::Request) -> Response {
+    let (id, params) = match parse_params::<GetEntriesForSystem>(req) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    let entries = project
+        .ship_logs
+        .get_entries_for_system(&params.system, params.include_external);
+    Response::new_ok(
+        id,
+        GetEntriesForSystemResponse {
+            found: entries.is_some(),
+            entries: entries.unwrap_or_default(),
+        },
+    )
+}
+
+pub fn get_entry(project: &Project, req: lsp_server::Request) -> Response {
+    match parse_params::<GetEntry>(req) {
+        Ok((id, params)) => Response::new_ok(id, project.ship_logs.get_entry_details(&params.id)),
+        Err(response) => response,
+    }
+}
+
+pub fn get_ship_log_graph(project: &Project, req: lsp_server::Request) -> Response {
+    let (id, params) = match parse_params::<GetShipLogGraph>(req) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    let graph = project.ship_logs.get_ship_log_graph(&params.system);
+    Response::new_ok(
+        id,
+        GetShipLogGraphResponse {
+            found: graph.is_some(),
+            graph: graph.unwrap_or_default(),
+        },
+    )
+}
+
+pub fn get_facts_for_system(project: &Project, req: lsp_server::Request) -> Response {
+    let (id, params) = match parse_params::<GetFactsForSystem>(req) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    match project.ship_logs.get_facts_for_system(&params.system) {
+        Some(facts) => Response::new_ok(id, facts),
+        None => not_found(id, format!("Unknown system {}", params.system)),
+    }
+}
+
+pub fn get_facts_for_entry(project: &Project, req: lsp_server::Request) -> Response {
+    let (id, params) = match parse_params::<GetFactsForEntry>(req) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    if !project.ship_logs.entries.contains_key(&params.id) {
+        return not_found(id, format!("Unknown entry {}", params.id));
+    }
+    Response::new_ok(id, project.ship_logs.get_facts_for_entry(&params.id))
+}
+
+pub fn get_curiosities(project: &Project, req: lsp_server::Request) -> Response {
+    let (id, params) = match parse_params::<GetCuriosities>(req) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    let system = params.system.as_deref();
+    if let Some(system) = system {
+        let known = project.find_all_systems().iter().any(|s| s == system)
+            || project
+                .ship_logs
+                .system_to_relative_path
+                .contains_key(system);
+        if !known {
+            return not_found(id, format!("Unknown system {system}"));
+        }
+    }
+    Response::new_ok(id, project.ship_logs.get_curiosities(system))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn request(method: &str, params: Value) -> lsp_server::Request {
+        lsp_server::Request::new(RequestId::from(1), method.to_string(), params)
+    }
+
+    fn test_project() -> Project {
+        let mut project = Project::default();
+        project.rebuild_ship_logs();
+        Arc::make_mut(&mut project.ship_logs)
+            .system_to_relative_path
+            .insert("SolarSystem".to_string(), vec![]);
+        project
+    }
+
+    #[test]
+    fn test_get_systems() {
+        let response = get_systems(&test_project(), request(GetSystems::METHOD, Value::Null));
+        assert_eq!(response.result, Some(json!([])));
+
+        let response = get_systems(&test_project(), request(GetSystems::METHOD, json!(1)));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+    }
+
+    #[test]
+    fn test_get_project_stats() {
+        let mut project = Project::default();
+        project
+            .load_from(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/commented_mod"));
+        let response =
+            get_project_stats(&project, 2, request(GetProjectStats::METHOD, Value::Null));
+        let stats: ProjectStats = serde_json::from_value(response.result.unwrap()).unwrap();
+        // The vanilla entries merged in for references aren't counted
+        assert_eq!(
+            stats,
+            ProjectStats {
+                planet_files: 1,
+                system_files: 1,
+                ship_log_files: 1,
+                dialogue_files: 0,
+                text_files: 0,
+                entries: 1,
+                facts: 1,
+                files_with_diagnostics: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_entry() {
+        let mut project = Project::default();
+        project
+            .load_from(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/commented_mod"));
+        for params in [
+            json!(["COMMENTED_ENTRY"]),
+            json!({ "id": "COMMENTED_ENTRY" }),
+        ] {
+            let response = get_entry(&project, request(GetEntry::METHOD, params));
+            let result = response.result.unwrap();
+            assert_eq!(result["name"], "Commented Entry");
+            assert_eq!(result["position"], json!([0.0, 100.0]));
+            assert_eq!(result["facts"][0]["id"], "COMMENTED_RUMOR_FACT");
+            assert!(result["location"]["uri"]
+                .as_str()
+                .unwrap()
+                .ends_with("planets/Commented.xml"));
+        }
+
+        // Vanilla entries don't come from any file
+        let response = get_entry(&project, request(GetEntry::METHOD, json!(["S_SUNSTATION"])));
+        assert_eq!(response.result.unwrap()["location"], Value::Null);
+
+        let response = get_entry(
+            &project,
+            request(GetEntry::METHOD, json!(["MISSING_ENTRY"])),
+        );
+        assert_eq!(response.result, Some(Value::Null));
+        assert!(response.error.is_none());
+
+        let response = get_entry(&project, request(GetEntry::METHOD, json!({ "entry": 1 })));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+    }
+
+    #[test]
+    fn test_get_facts_for_system() {
+        let project = test_project();
+        for params in [json!(["SolarSystem"]), json!({ "system": "SolarSystem" })] {
+            let response =
+                get_facts_for_system(&project, request(GetFactsForSystem::METHOD, params));
+            assert!(response.result.unwrap().is_array());
+        }
+
+        let response = get_facts_for_system(
+            &project,
+            request(GetFactsForSystem::METHOD, json!(["MissingSystem"])),
+        );
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::RequestFailed as i32
+        );
+
+        let response = get_facts_for_system(&project, request(GetFactsForSystem::METHOD, json!(1)));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+    }
+
+    #[test]
+    fn test_get_facts_for_entry() {
+        let mut project = Project::default();
+        project
+            .load_from(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/commented_mod"));
+        let response = get_facts_for_entry(
+            &project,
+            request(GetFactsForEntry::METHOD, json!(["COMMENTED_ENTRY"])),
+        );
+        let facts: Vec<ShipLogFact> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(facts.len(), 1);
+
+        let response = get_facts_for_entry(
+            &project,
+            request(GetFactsForEntry::METHOD, json!(["MISSING_ENTRY"])),
+        );
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::RequestFailed as i32
+        );
+
+        let response =
+            get_facts_for_entry(&project, request(GetFactsForEntry::METHOD, Value::Null));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+    }
+
+    #[test]
+    fn test_get_curiosities() {
+        let project = test_project();
+        for params in [
+            Value::Null,
+            json!([]),
+            json!(["SolarSystem"]),
+            json!({}),
+            json!({ "system": "SolarSystem" }),
+        ] {
+            let response = get_curiosities(&project, request(GetCuriosities::METHOD, params));
+            assert_eq!(response.result, Some(json!([])));
+        }
+
+        let response = get_curiosities(
+            &project,
+            request(GetCuriosities::METHOD, json!(["MissingSystem"])),
+        );
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::RequestFailed as i32
+        );
+
+        let response = get_curiosities(&project, request(GetCuriosities::METHOD, json!("x")));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidParams as i32
+        );
+    }
+
+    #[test]
+    fn test_get_entries_for_system() {
+        let project = test_project();
+        for params in [json!(["SolarSystem"]), json!({ "system": "SolarSystem" })] {
+            let response =
+                get_entries_for_system(&project, request(GetEntriesForSystem::METHOD, params));
+            let result: GetEntriesForSystemResponse =
+                serde_json::from_value(response.result.unwrap()).unwrap();
+            assert!(result.found);
+        }
+
+        let response = get_entries_for_system(
+            &project,
+            request(GetEntriesForSystem::METHOD, json!(["MissingSystem"])),
+        );
+        assert_eq!(
+            response.result,
+            Some(json!({ "found": false, "entries": [], "links": [] }))
+        );
+
+        for params in [Value::Null, json!([]), json!({ "name": "SolarSystem" })] {
+            let response =
+                get_entries_for_system(&project, request(GetEntriesForSystem::METHOD, params));
+            assert!(response.result.is_none());
+            assert_eq!(
+                response.error.unwrap().code,
+                ErrorCode::InvalidParams as i32
+            );
+        }
+    }
+}
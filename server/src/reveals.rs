@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+use serde_json::Value;
+
+use crate::{
+    dialogue::DialogueContext,
+    nomai_text::NomaiTextContext,
+    planets::FACT_ID_KEYS,
+    project::Project,
+    ship_log::{IdSet, ShipLogContext},
+    utils::error_codes::{self, get_error_code},
+    validation::{ErrorSet, Validator},
+};
+
+/// Tags on a fact or its entry that mean the fact doesn't need to be revealed
+pub const UNREVEALED_EXEMPT_TAGS: [&str; 2] =
+    ["IgnoreMoreToExplore", "IgnoreMoreToExploreCondition"];
+
+#[derive(Default, Debug)]
+pub struct RevealContext {
+    pub fact_ids: IdSet,
+    pub revealed_facts: HashSet<String>,
+}
+
+impl RevealContext {
+    pub fn add_ship_log_facts(&mut self, ship_logs: &ShipLogContext) {
+        let exempt = ship_logs
+            .fact_definitions
+            .iter()
+            .filter(|fact| fact.reveal_exempt)
+            .map(|fact| (&fact.id, &fact.source_file.uri))
+            .collect::<HashSet<_>>();
+        self.fact_ids.extend(
+            ship_logs
+                .fact_ids
+                .iter()
+                .filter(|id| !exempt.contains(&(&id.value, &id.source_file.uri)))
+                .cloned(),
+        );
+    }
+
+    fn collect_planet_reveals(&mut self, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter() {
+                    if FACT_ID_KEYS.contains(&key.as_str()) {
+                        match value {
+                            Value::String(fact) => {
+                                self.revealed_facts.insert(fact.clone());
+                            }
+                            Value::Array(facts) => self
+                                .revealed_facts
+                                .extend(facts.iter().filter_map(|f| f.as_str().map(String::from))),
+                            _ => {}
+                        }
+                    } else {
+                        self.collect_planet_reveals(value);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values.iter() {
+                    self.collect_planet_reveals(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn parse_planet(&mut self, raw_str: &str) {
+        // Invalid configs are reported by PlanetValidator
+        if let Ok(value) = serde_json::from_str::<Value>(raw_str) {
+            self.collect_planet_reveals(&value);
+        }
+    }
+
+    pub fn from_project(project: &Project) -> Self {
+        let mut ctx = Self::default();
+        ctx.add_ship_log_facts(&project.ship_logs);
+        for file in project.planet_files.iter() {
+            ctx.parse_planet(&file.json());
+        }
+        let dialogue = DialogueContext::from_project(project);
+        let text = NomaiTextContext::from_project(project);
+        ctx.revealed_facts.extend(
+            dialogue
+                .revealed_facts
+                .iter()
+                .chain(text.fact_references.iter())
+                .map(|id| id.value.clone()),
+        );
+        ctx
+    }
+
+    pub fn validate(&self) -> ErrorSet {
+        self.fact_ids
+            .iter()
+            .filter(|id| !id.value.is_empty() && !self.revealed_facts.contains(&id.value))
+            .map(|id| {
+                (
+                    id.source_file.clone(),
+                    Diagnostic {
+                        range: id.range,
+                        // Facts can also be revealed from code
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: get_error_code(error_codes::SHIPLOG_UNREVEALED_FACT),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message: format!("Fact `{}` is never revealed", id.value),
+                        related_information: None,
+                        tags: None,
+                        data: None,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct RevealValidator();
+
+impl Validator for RevealValidator {
+    fn name() -> &'static str {
+        "reveals"
+    }
+
+    fn prepare() -> Self {
+        Self()
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .ship_log_files
+            .iter()
+            .chain(project.dialogue_files.iter())
+            .chain(project.text_files.iter())
+            .chain(project.planet_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> ErrorSet {
+        RevealContext::from_project(project).validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::ProjectFile;
+
+    use super::*;
+
+    const SHIP_LOG_STR: &str = "<AstroObjectEntry><ID>TEST</ID>\
+        <Entry><ID>ENTRY</ID>\
+        <ExploreFact><ID>DIALOGUE_FACT</ID></ExploreFact>\
+        <ExploreFact><ID>TEXT_FACT</ID></ExploreFact>\
+        <ExploreFact><ID>PLANET_FACT</ID></ExploreFact>\
+        <RumorFact><ID>HIDDEN_FACT</ID></RumorFact>\
+        <ExploreFact><ID>IGNORED_FACT</ID><IgnoreMoreToExplore/></ExploreFact>\
+        </Entry>\
+        <Entry><ID>IGNORED_ENTRY</ID><IgnoreMoreToExploreCondition>X</IgnoreMoreToExploreCondition>\
+        <ExploreFact><ID>IGNORED_ENTRY_FACT</ID></ExploreFact>\
+        </Entry></AstroObjectEntry>";
+
+    fn file(path: &str, contents: &str) -> ProjectFile {
+        ProjectFile::new(
+            Url::parse(&format!("file:///mod/{path}")).unwrap(),
+            0,
+            contents.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_exempt_tags() {
+        let mut project = Project {
+            ship_log_files: vec![file("log.xml", SHIP_LOG_STR).into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let mut ctx = RevealContext::default();
+        ctx.add_ship_log_facts(&project.ship_logs);
+        let ids = ctx
+            .fact_ids
+            .iter()
+            .map(|id| id.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec!["DIALOGUE_FACT", "TEXT_FACT", "PLANET_FACT", "HIDDEN_FACT"]
+        );
+    }
+
+    #[test]
+    fn test_validate_unrevealed_facts() {
+        let mut project = Project {
+            ship_log_files: vec![file("log.xml", SHIP_LOG_STR).into()],
+            dialogue_files: vec![file(
+                "dialogue.xml",
+                "<DialogueTree><DialogueNode><Name>START</Name>\
+                 <RevealFacts><FactID>DIALOGUE_FACT</FactID></RevealFacts>\
+                 <DialogueTargetShipLogCondition>HIDDEN_FACT</DialogueTargetShipLogCondition>\
+                 </DialogueNode></DialogueTree>",
+            )
+            .into()],
+            text_files: vec![file(
+                "text.xml",
+                "<NomaiObject><TextBlock><ID>1</ID></TextBlock>\
+                 <RevealFact><FactID>TEXT_FACT</FactID></RevealFact></NomaiObject>",
+            )
+            .into()],
+            planet_files: vec![file(
+                "planet.json",
+                r#"{"name": "Test", "Props": {"dialogue": [{"revealFacts": ["PLANET_FACT"]}]}}"#,
+            )
+            .into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let errors = RevealValidator::prepare().validate(&project);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Fact `HIDDEN_FACT` is never revealed");
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_UNREVEALED_FACT)
+        );
+        assert_eq!(errors[0].0.uri, project.ship_log_files[0].id.uri);
+    }
+}
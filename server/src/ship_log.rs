@@ -1,18 +1,37 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Result;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Range, Url, VersionedTextDocumentIdentifier};
+use json_position_parser::tree::{EntryType, PathType};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CompletionItem, CompletionItemKind,
+    CreateFile, CreateFileOptions, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity,
+    DiagnosticTag, DocumentChangeOperation, DocumentChanges, DocumentSymbol, Documentation, Hover,
+    HoverContents, InlayHint, InlayHintLabel, Location, MarkupContent, MarkupKind, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, Range, ResourceOp, SemanticToken,
+    SemanticTokenType, SymbolInformation, SymbolKind, TextDocumentEdit, TextEdit, Url,
+    VersionedTextDocumentIdentifier, WorkspaceEdit,
+};
 use roxmltree::{Document, Node};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
     planets::Planet,
-    project::{Project, ProjectFile},
-    systems::StarSystem,
+    project::{normalize_config_path, Project, ProjectFile},
+    reveals::UNREVEALED_EXEMPT_TAGS,
+    systems::{Curiosity, CuriosityModule, MColor, StarSystem, DEFAULT_CURIOSITY_COLOR},
     utils::{
+        edit_distance,
         error_codes::{self, get_error_code},
-        xml_range_to_diag_range,
+        json_idx_to_offset, json_path_to_json_pos_path, json_pos_range_to_diag_range,
+        json_pos_string_content_range, json_value_range, lsp_position_to_offset,
+        normalize_astro_object_name, offset_to_lsp_position, range_contains, xml_element_at,
+        xml_node_range, xml_range_to_diag_range,
     },
     validation::{ErrorSet, Validator},
 };
@@ -28,26 +47,123 @@ pub struct ID {
     pub value: String,
     pub source_file: ShipLogFile,
     pub range: Range,
+    pub value_range: Range,
 }
 
 impl ID {
-    fn new(tree: &Document, node: &Node, log_file: &ShipLogFile) -> Self {
+    pub fn new(tree: &Document, node: &Node, log_file: &ShipLogFile) -> Self {
+        let range = xml_node_range(tree, node);
+        let value_range = node
+            .first_child()
+            .filter(|n| n.is_text())
+            .map(|n| xml_node_range(tree, &n))
+            .unwrap_or(range);
         Self {
             value: node.text().unwrap_or_default().to_string(),
             source_file: log_file.clone(),
-            range: xml_range_to_diag_range(
-                tree.text_pos_at(node.range().start),
-                tree.text_pos_at(node.range().end),
-            ),
+            range,
+            value_range,
         }
     }
 }
 
 pub type IdSet = Vec<ID>;
 
+/// Token types for ship log semantic tokens, indexed by the `SEMANTIC_*` constants
+pub const SEMANTIC_TOKEN_TYPES: [SemanticTokenType; 3] = [
+    SemanticTokenType::CLASS,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::PROPERTY,
+];
+const SEMANTIC_ID: u32 = 0;
+const SEMANTIC_REFERENCE: u32 = 1;
+const SEMANTIC_FACT: u32 = 2;
+
+/// Links from one ID in a group of duplicates to the rest of them
+pub fn related_duplicates(group: &[&ID], id: &ID) -> Vec<DiagnosticRelatedInformation> {
+    group
+        .iter()
+        .filter(|other| !std::ptr::eq(**other, id))
+        .map(|other| DiagnosticRelatedInformation {
+            location: Location::new(other.source_file.uri.clone(), other.range),
+            message: format!("`{}` also defined here", other.value),
+        })
+        .collect()
+}
+
+/// Groups IDs that share the same key, empty IDs are skipped
+pub fn find_duplicate_ids<K: Ord>(set: &IdSet, key: impl Fn(&ID) -> K) -> Vec<Vec<&ID>> {
+    let mut sorted = set
+        .iter()
+        .filter(|id| !id.value.trim().is_empty())
+        .collect::<Vec<_>>();
+    sorted.sort_by_key(|id| key(id));
+    let mut groups = vec![];
+    let mut current_buffer: Vec<&ID> = vec![];
+    for id in sorted {
+        if current_buffer
+            .last()
+            .map(|last_id| key(last_id) == key(id))
+            .unwrap_or(false)
+        {
+            current_buffer.push(id);
+        } else {
+            if current_buffer.len() > 1 {
+                groups.push(current_buffer.clone());
+            }
+            current_buffer.clear();
+            current_buffer.push(id);
+        }
+    }
+    if current_buffer.len() > 1 {
+        groups.push(current_buffer);
+    }
+    groups
+}
+
 type Vector2 = (f32, f32);
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShipLogFact {
+    id: String,
+    #[serde(default)]
+    is_rumor: bool,
+    #[serde(default)]
+    text: String,
+    rumor_name: Option<String>,
+    source_id: Option<String>,
+}
+
+/// Where a RumorFact or ExploreFact is defined, `range` covers the whole element
+#[derive(Clone, Debug)]
+pub struct FactDefinition {
+    pub id: String,
+    pub is_rumor: bool,
+    pub source_id: Option<String>,
+    pub entry: String,
+    pub source_file: ShipLogFile,
+    pub range: Range,
+    /// Tagged (or in an entry tagged) to not need revealing, see `UNREVEALED_EXEMPT_TAGS`
+    pub reveal_exempt: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemFact {
+    entry: String,
+    #[serde(flatten)]
+    fact: ShipLogFact,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct ShipLogFacts {
+    #[serde(default)]
+    explore: Vec<ShipLogFact>,
+    #[serde(default)]
+    rumor: Vec<ShipLogFact>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ShipLogEntry {
     id: String,
@@ -55,22 +171,106 @@ pub struct ShipLogEntry {
     position: Option<Vector2>,
     name: String,
     parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
     is_curiosity: bool,
+    #[serde(default)]
     sources: Vec<String>,
+    #[serde(default)]
+    explore_sources: Vec<String>,
+    curiosity: Option<String>,
+    #[serde(default, skip_serializing)]
+    facts: ShipLogFacts,
+    #[serde(skip)]
+    source_file: Option<Url>,
+    /// Defined by one of the `externalProjects`
+    #[serde(default)]
+    external: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShipLogLink {
+    source: String,
+    target: String,
+    is_rumor: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SystemEntriesResponse {
+    entries: Vec<ShipLogEntry>,
+    links: Vec<ShipLogLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntryDetails {
+    #[serde(flatten)]
+    entry: ShipLogEntry,
+    facts: Vec<ShipLogFact>,
+    /// Vanilla entries aren't defined in any file
+    location: Option<Location>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    id: String,
+    name: String,
+    astro_object: String,
+    position: Option<Vector2>,
+    is_curiosity: bool,
     curiosity: Option<String>,
+    /// The curiosity's color, or `DEFAULT_CURIOSITY_COLOR`
+    color: MColor,
+}
+
+/// A rumor arrow, `fact` is the RumorFact with the SourceID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    source: String,
+    target: String,
+    fact: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ShipLogGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    curiosities: Vec<Curiosity>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CuriosityInfo {
+    system: String,
+    #[serde(flatten)]
+    curiosity: CuriosityModule,
+    entries: Vec<String>,
 }
 
-#[derive(Default, Debug)]
+/// What parsing a single ship log file produced, keyed by the file's version and a hash of
+/// its contents (files read from disk always have version 0)
+pub type ShipLogCache = HashMap<Url, (i32, u64, ShipLogContext)>;
+
+#[derive(Clone, Default, Debug)]
 pub struct ShipLogContext {
     pub astro_object_ids: IdSet,
     pub entry_ids: IdSet,
     pub entries: HashMap<String, ShipLogEntry>,
     pub position_map: HashMap<String, Vector2>,
     pub fact_ids: IdSet,
+    pub fact_definitions: Vec<FactDefinition>,
+    pub fact_to_entry: HashMap<String, String>,
     pub system_to_relative_path: HashMap<String, Vec<String>>,
     pub relative_to_astro_object: HashMap<String, String>,
+    pub system_curiosities: HashMap<String, Vec<CuriosityModule>>,
     pub curiosity_references: IdSet,
     pub source_id_references: IdSet,
+    /// SourceID references paired with the ID of the entry they're defined in
+    pub source_links: Vec<(String, ID)>,
+    pub xml_errors: Vec<(ShipLogFile, roxmltree::Error, Range)>,
+    /// Ship logs of other mods this one builds on, only used to resolve references
+    pub external: Option<Arc<ShipLogContext>>,
 }
 
 impl ShipLogContext {
@@ -81,10 +281,16 @@ impl ShipLogContext {
         tree: &Document,
         node: &Node,
         parent: Option<&str>,
-    ) {
+    ) -> String {
         let mut entry = ShipLogEntry::default();
         entry.astro_object = ao_id.to_string();
         entry.parent = parent.map(|s| s.to_string());
+        entry.source_file = Some(log_file.uri.clone());
+        let mut facts: Vec<String> = vec![];
+        let mut definitions: Vec<FactDefinition> = vec![];
+        let mut sources: IdSet = vec![];
+        let is_exempt = |node: &Node| UNREVEALED_EXEMPT_TAGS.contains(&node.tag_name().name());
+        let entry_exempt = node.children().any(|n| is_exempt(&n));
         for node in node.children().filter(|n| n.is_element()) {
             match node.tag_name().name() {
                 "ID" => {
@@ -102,31 +308,79 @@ impl ShipLogContext {
                         .push(ID::new(tree, &node, log_file));
                     entry.curiosity = Some(node.text().unwrap_or_default().to_string());
                 }
-                "RumorFact" | "ExploreFact" => {
+                fact_type @ ("RumorFact" | "ExploreFact") => {
+                    let child_text = |tag: &str| {
+                        node.children()
+                            .find(|n| n.tag_name().name() == tag)
+                            .and_then(|n| n.text())
+                            .map(|t| t.to_string())
+                    };
+                    let fact = ShipLogFact {
+                        id: child_text("ID").unwrap_or_default(),
+                        is_rumor: fact_type == "RumorFact",
+                        text: child_text("Text").unwrap_or_default(),
+                        rumor_name: child_text("RumorName"),
+                        source_id: child_text("SourceID"),
+                    };
+                    definitions.push(FactDefinition {
+                        id: fact.id.clone(),
+                        is_rumor: fact.is_rumor,
+                        source_id: fact.source_id.clone(),
+                        entry: String::new(),
+                        source_file: log_file.clone(),
+                        range: xml_node_range(tree, &node),
+                        reveal_exempt: entry_exempt || node.children().any(|n| is_exempt(&n)),
+                    });
+                    if fact.is_rumor {
+                        entry.facts.rumor.push(fact);
+                    } else {
+                        entry.facts.explore.push(fact);
+                    }
                     if let Some(node) = node.children().find(|n| n.tag_name().name() == "ID") {
                         self.fact_ids.push(ID::new(tree, &node, log_file));
+                        facts.push(node.text().unwrap_or_default().to_string());
                     }
                     if let Some(node) = node.children().find(|n| n.tag_name().name() == "SourceID")
                     {
                         self.source_id_references
                             .push(ID::new(tree, &node, log_file));
-                        entry
-                            .sources
-                            .push(node.text().unwrap_or_default().to_string());
+                        sources.push(ID::new(tree, &node, log_file));
+                        let source = node.text().unwrap_or_default().to_string();
+                        if fact_type == "RumorFact" {
+                            entry.sources.push(source);
+                        } else {
+                            entry.explore_sources.push(source);
+                        }
                     }
                 }
                 "Entry" => {
-                    self.parse_entry(log_file, ao_id, tree, &node, Some(&entry.id));
+                    let child = self.parse_entry(log_file, ao_id, tree, &node, Some(&entry.id));
+                    if !child.is_empty() {
+                        entry.children.push(child);
+                    }
                 }
                 _ => {}
             }
         }
         if !entry.id.is_empty() {
+            self.fact_to_entry
+                .extend(facts.into_iter().map(|fact| (fact, entry.id.clone())));
+            self.fact_definitions
+                .extend(definitions.into_iter().map(|definition| FactDefinition {
+                    entry: entry.id.clone(),
+                    ..definition
+                }));
+            self.source_links
+                .extend(sources.into_iter().map(|id| (entry.id.clone(), id)));
             entry.position = self.position_map.get(&entry.id).cloned();
             if entry.name.is_empty() {
                 entry.name = "UNNAMED".to_string();
             }
-            self.entries.insert(entry.id.clone(), entry);
+            let id = entry.id.clone();
+            self.entries.insert(id.clone(), entry);
+            id
+        } else {
+            String::new()
         }
     }
 
@@ -137,7 +391,17 @@ impl ShipLogContext {
         root_path: &Path,
         raw_str: &str,
     ) -> Result<()> {
-        let tree = Document::parse(raw_str)?;
+        let tree = match Document::parse(raw_str) {
+            Ok(tree) => tree,
+            Err(why) => {
+                self.xml_errors.push((
+                    log_file.clone(),
+                    why.clone(),
+                    xml_range_to_diag_range(raw_str, why.pos(), why.pos()),
+                ));
+                return Err(why.into());
+            }
+        };
         let mut id = String::new();
         if let Some(node) = tree
             .descendants()
@@ -149,8 +413,10 @@ impl ShipLogContext {
                         id = node.text().unwrap_or_default().to_string();
                         self.astro_object_ids.push(ID::new(&tree, &node, log_file));
                         if let Some(relative_path) = project_file.get_relative(root_path) {
-                            self.relative_to_astro_object
-                                .insert(relative_path.to_string_lossy().to_string(), id.clone());
+                            self.relative_to_astro_object.insert(
+                                normalize_config_path(&relative_path.to_string_lossy()),
+                                id.clone(),
+                            );
                         }
                     }
                     "Entry" => {
@@ -164,8 +430,53 @@ impl ShipLogContext {
         Ok(())
     }
 
+    /// Drops everything parsed from the given ship log file.
+    /// Returns `false` if that also dropped an entry defined elsewhere, meaning the context
+    /// must be rebuilt from scratch.
+    pub fn remove_file(&mut self, uri: &Url, root_path: &Path) -> bool {
+        let removed_facts = self
+            .fact_ids
+            .iter()
+            .filter(|id| &id.source_file.uri == uri)
+            .map(|id| id.value.clone())
+            .collect::<Vec<_>>();
+        for set in [
+            &mut self.astro_object_ids,
+            &mut self.entry_ids,
+            &mut self.fact_ids,
+            &mut self.curiosity_references,
+            &mut self.source_id_references,
+        ] {
+            set.retain(|id| &id.source_file.uri != uri);
+        }
+        self.fact_definitions
+            .retain(|fact| &fact.source_file.uri != uri);
+        self.source_links
+            .retain(|(_, id)| &id.source_file.uri != uri);
+        self.xml_errors.retain(|(file, _, _)| &file.uri != uri);
+        let mut removed_entries = vec![];
+        self.entries.retain(|id, entry| {
+            let keep = entry.source_file.as_ref() != Some(uri);
+            if !keep {
+                removed_entries.push(id.clone());
+            }
+            keep
+        });
+        for fact in removed_facts.iter() {
+            self.fact_to_entry.remove(fact);
+        }
+        // Other files can use the same astro object, so only drop this file's path
+        if let Ok(relative) = Path::new(uri.path()).strip_prefix(root_path) {
+            self.relative_to_astro_object
+                .remove(&normalize_config_path(&relative.to_string_lossy()));
+        }
+        !removed_entries
+            .iter()
+            .any(|id| self.entry_ids.iter().any(|other| &other.value == id))
+    }
+
     pub fn parse_system_positions(&mut self, config: &ProjectFile) {
-        let system = serde_json::from_str::<StarSystem>(&config.contents);
+        let system = serde_json::from_str::<StarSystem>(&config.json());
         match system {
             Ok(system) => {
                 if let Some(positions) = system.entry_positions {
@@ -174,6 +485,11 @@ impl ShipLogContext {
                             .insert(entry.id.clone(), (entry.position.x, entry.position.y));
                     }
                 }
+                let name = config.nice_path.file_stem().and_then(|s| s.to_str());
+                if let (Some(name), Some(curiosities)) = (name, system.curiosities) {
+                    self.system_curiosities
+                        .insert(name.to_string(), curiosities);
+                }
             }
             Err(why) => {
                 eprintln!("Error parsing system file, ignoring: {why:?}");
@@ -182,7 +498,7 @@ impl ShipLogContext {
     }
 
     pub fn parse_planet(&mut self, config: &ProjectFile) {
-        let planet = serde_json::from_str::<Planet>(&config.contents);
+        let planet = serde_json::from_str::<Planet>(&config.json());
         match planet {
             Ok(planet) => {
                 let xml_file = planet.ShipLog.and_then(|m| m.xml_file.clone());
@@ -199,69 +515,230 @@ impl ShipLogContext {
         }
     }
 
+    fn merge(&mut self, other: &ShipLogContext) {
+        self.astro_object_ids
+            .extend(other.astro_object_ids.iter().cloned());
+        self.entry_ids.extend(other.entry_ids.iter().cloned());
+        self.entries.extend(
+            other
+                .entries
+                .iter()
+                .map(|(id, entry)| (id.clone(), entry.clone())),
+        );
+        self.fact_ids.extend(other.fact_ids.iter().cloned());
+        self.fact_definitions
+            .extend(other.fact_definitions.iter().cloned());
+        self.fact_to_entry.extend(
+            other
+                .fact_to_entry
+                .iter()
+                .map(|(fact, entry)| (fact.clone(), entry.clone())),
+        );
+        self.relative_to_astro_object.extend(
+            other
+                .relative_to_astro_object
+                .iter()
+                .map(|(path, id)| (path.clone(), id.clone())),
+        );
+        self.curiosity_references
+            .extend(other.curiosity_references.iter().cloned());
+        self.source_id_references
+            .extend(other.source_id_references.iter().cloned());
+        self.source_links.extend(other.source_links.iter().cloned());
+        self.xml_errors.extend(other.xml_errors.iter().cloned());
+    }
+
+    fn contents_hash(contents: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[cfg(test)]
     pub fn from_project(project: &Project) -> Self {
-        let mut ctx = Self::default();
+        Self::from_project_cached(project, &mut ShipLogCache::new())
+    }
+
+    /// Only re-parses ship log files that changed since they were put in the cache
+    pub fn from_project_cached(project: &Project, cache: &mut ShipLogCache) -> Self {
+        let mut ctx = Self {
+            external: Some(project.external_ship_logs.clone()),
+            ..Default::default()
+        };
         for file in project.system_files.iter() {
             ctx.parse_system_positions(&file);
         }
         for file in project.planet_files.iter() {
             ctx.parse_planet(&file);
         }
+        cache.retain(|uri, _| project.ship_log_files.iter().any(|f| &f.id.uri == uri));
         for file in project.ship_log_files.iter() {
-            let res = ctx.parse(&file.id, &file, &project.root_path, &file.contents);
+            let hash = Self::contents_hash(&file.contents);
+            let cached = cache
+                .get(&file.id.uri)
+                .filter(|(version, cached_hash, _)| {
+                    *version == file.id.version && *cached_hash == hash
+                })
+                .map(|(_, _, parsed)| parsed);
+            if let Some(parsed) = cached {
+                ctx.merge(parsed);
+                continue;
+            }
+            let mut parsed = Self::default();
+            let res = parsed.parse(&file.id, &file, &project.root_path, &file.contents);
             if let Err(why) = res {
                 eprintln!("Error parsing ship log file: {why:?}");
             }
+            ctx.merge(&parsed);
+            cache.insert(file.id.uri.clone(), (file.id.version, hash, parsed));
+        }
+        // Positions come from system files, which may have changed since the cached parse
+        for entry in ctx.entries.values_mut() {
+            entry.position = ctx.position_map.get(&entry.id).cloned();
         }
-        let vanilla: Vec<ShipLogEntry> = serde_json::from_str(include_str!("./base_game.json"))
+        let mut vanilla: Vec<ShipLogEntry> = serde_json::from_str(include_str!("./base_game.json"))
             .expect("Failed to parse vanilla ship log entries");
+        // base_game.json only lists parents
+        for i in 0..vanilla.len() {
+            if let Some(parent) = vanilla[i].parent.clone() {
+                let child = vanilla[i].id.clone();
+                if let Some(parent) = vanilla.iter_mut().find(|e| e.id == parent) {
+                    parent.children.push(child);
+                }
+            }
+        }
         ctx.entries
             .extend(vanilla.into_iter().map(|entry| (entry.id.clone(), entry)));
         ctx
     }
 
-    fn process_duplicate_buffer(errors: &mut ErrorSet, id_name: &str, buffer: &[&ID]) {
-        errors.extend(buffer.iter().map(|id| {
-            let message = format!("Duplicate {id_name} ID: `{}`", id.value);
-            (
-                id.source_file.clone(),
-                Diagnostic {
-                    range: id.range,
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    code: get_error_code(error_codes::SHIPLOG_DUPLICATE_ID),
-                    code_description: None,
-                    source: Some(error_codes::ERROR_SOURCE.to_string()),
-                    message,
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                },
-            )
-        }));
+    /// Loads each folder as its own project, none of their files are validated
+    pub fn load_external(paths: &[PathBuf]) -> Self {
+        let mut ctx = Self::default();
+        for path in paths.iter() {
+            if !path.is_dir() {
+                eprintln!(
+                    "External project {} doesn't exist, ignoring",
+                    path.display()
+                );
+                continue;
+            }
+            eprintln!("Loading External Project At {}", path.display());
+            let mut project = Project::default();
+            project.load_from(path);
+            let external = Arc::unwrap_or_clone(project.ship_logs);
+            ctx.merge(&external);
+            for (system, paths) in external.system_to_relative_path {
+                ctx.system_to_relative_path
+                    .entry(system)
+                    .or_default()
+                    .extend(paths);
+            }
+            for (system, curiosities) in external.system_curiosities {
+                ctx.system_curiosities
+                    .entry(system)
+                    .or_default()
+                    .extend(curiosities);
+            }
+        }
+        ctx
     }
 
-    fn validate_id_set_duplicates(&self, errors: &mut ErrorSet, id_name: &str, set: &IdSet) {
-        let mut set = set.clone();
-        let mut current_buffer: Vec<&ID> = vec![];
-        set.sort_unstable_by_key(|a| a.value.to_string());
-        for id in set.iter() {
-            if current_buffer
-                .last()
-                .map(|last_id| id.value == last_id.value)
-                .unwrap_or(false)
-            {
-                current_buffer.push(id);
-            } else {
-                if current_buffer.len() > 1 {
-                    Self::process_duplicate_buffer(errors, id_name, &current_buffer)
+    /// Entries defined in the project's own ship log files, leaving out vanilla ones
+    pub fn custom_entry_count(&self) -> usize {
+        self.entries
+            .values()
+            .filter(|entry| entry.source_file.is_some() && !entry.external)
+            .count()
+    }
+
+    /// Looks in external projects for entries this project doesn't define
+    fn find_entry(&self, id: &str) -> Option<&ShipLogEntry> {
+        self.entries
+            .get(id)
+            .or_else(|| self.external.as_ref()?.entries.get(id))
+    }
+
+    fn is_external_curiosity(&self, id: &str) -> bool {
+        self.external.iter().any(|external| {
+            external
+                .system_curiosities
+                .values()
+                .flatten()
+                .any(|curiosity| curiosity.id == id)
+        })
+    }
+
+    fn process_duplicate_buffer(errors: &mut ErrorSet, id_name: &str, buffer: &[&ID], id: &ID) {
+        let message = format!("Duplicate {id_name} ID: `{}`", id.value);
+        let related = related_duplicates(buffer, id);
+        errors.push((
+            id.source_file.clone(),
+            Diagnostic {
+                range: id.range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: get_error_code(error_codes::SHIPLOG_DUPLICATE_ID),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message,
+                related_information: Some(related),
+                tags: None,
+                data: None,
+            },
+        ));
+    }
+
+    /// The star systems each ship log file is loaded in, going by the planets that use it
+    fn systems_by_file<'a>(&'a self, project: &Project) -> HashMap<Url, Vec<&'a str>> {
+        project
+            .ship_log_files
+            .iter()
+            .filter_map(|file| {
+                let relative = file.get_relative(&project.root_path)?;
+                let relative = normalize_config_path(&relative.to_string_lossy());
+                let systems = self
+                    .system_to_relative_path
+                    .iter()
+                    .filter(|(_, paths)| paths.iter().any(|p| normalize_config_path(p) == relative))
+                    .map(|(system, _)| system.as_str())
+                    .collect::<Vec<_>>();
+                Some((file.id.uri.clone(), systems))
+            })
+            .collect()
+    }
+
+    /// NH keeps each star system's ship log separate, so IDs only clash within a system
+    fn validate_id_set_duplicates(
+        &self,
+        errors: &mut ErrorSet,
+        id_name: &str,
+        set: &IdSet,
+        systems: &HashMap<Url, Vec<&str>>,
+    ) {
+        let share_system = |a: &ID, b: &ID| {
+            let systems_of = |id: &ID| {
+                systems
+                    .get(&id.source_file.uri)
+                    .filter(|systems| !systems.is_empty())
+            };
+            match (systems_of(a), systems_of(b)) {
+                (Some(a), Some(b)) => a.iter().any(|system| b.contains(system)),
+                // Files no planet uses could end up anywhere
+                _ => true,
+            }
+        };
+        for group in find_duplicate_ids(set, |id| id.value.clone()) {
+            for id in group.iter() {
+                let colliding = group
+                    .iter()
+                    .copied()
+                    .filter(|other| share_system(id, other))
+                    .collect::<Vec<_>>();
+                if colliding.len() > 1 {
+                    Self::process_duplicate_buffer(errors, id_name, &colliding, id);
                 }
-                current_buffer.clear();
-                current_buffer.push(id);
             }
         }
-        if current_buffer.len() > 1 {
-            Self::process_duplicate_buffer(errors, id_name, &current_buffer)
-        }
     }
 
     fn validate_id_taken(
@@ -292,21 +769,21 @@ impl ShipLogContext {
         }
     }
 
-    fn validate_curiosity_references(&self, system_files: &[ProjectFile], errors: &mut ErrorSet) {
-        const KNOWN_CURIOSITIES: [&str; 7] = [
-            "None",
-            "QuantumMoon",
-            "SunkenModule",
-            "Vessel",
-            "TimeLoop",
-            "CometCore",
-            "InvisiblePlanet",
-        ];
+    const KNOWN_CURIOSITIES: [&'static str; 7] = [
+        "None",
+        "QuantumMoon",
+        "SunkenModule",
+        "Vessel",
+        "TimeLoop",
+        "CometCore",
+        "InvisiblePlanet",
+    ];
 
+    pub fn find_custom_curiosities(system_files: &[Arc<ProjectFile>]) -> Vec<String> {
         let mut custom_curiosities: Vec<String> = vec![];
 
         for file in system_files.iter() {
-            if let Ok(contents) = serde_json::from_str::<Value>(&file.contents) {
+            if let Ok(contents) = serde_json::from_str::<Value>(&file.json()) {
                 if let Some(Some(values)) = contents.get("curiosities").map(|v| v.as_array()) {
                     custom_curiosities.extend(
                         values
@@ -319,203 +796,2689 @@ impl ShipLogContext {
             }
         }
 
-        for reference in self.curiosity_references.iter() {
-            if !KNOWN_CURIOSITIES.contains(&reference.value.as_str())
-                && !custom_curiosities.contains(&reference.value)
-            {
-                let message = format!(
-                    "Unknown Curiosity: `{}`. Please define it in a system config",
-                    reference.value
-                );
+        custom_curiosities
+    }
+
+    fn validate_curiosity_colors(file: &ProjectFile, errors: &mut ErrorSet) {
+        let text = file.json();
+        let Ok(contents) = serde_json::from_str::<Value>(&text) else {
+            return;
+        };
+        let Some(curiosities) = contents.get("curiosities").and_then(|v| v.as_array()) else {
+            return;
+        };
+        let tree = json_position_parser::parse_json(&text).ok();
+        for (i, curiosity) in curiosities.iter().enumerate() {
+            for key in ["color", "highlightColor"] {
+                let Some(reason) = curiosity.get(key).and_then(MColor::invalid_reason) else {
+                    continue;
+                };
+                let range = tree
+                    .as_ref()
+                    .and_then(|tree| {
+                        tree.value_at(&[
+                            PathType::Object("curiosities"),
+                            PathType::Array(i),
+                            PathType::Object(key),
+                        ])
+                        .first()
+                        .map(|found| json_pos_range_to_diag_range(&file.contents, found.range))
+                    })
+                    .unwrap_or_default();
                 errors.push((
-                    reference.source_file.clone(),
+                    file.id.clone(),
                     Diagnostic {
-                        range: reference.range,
+                        range,
                         severity: Some(DiagnosticSeverity::ERROR),
-                        code: get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY),
+                        code: get_error_code(error_codes::CONFIG_INVALID_COLOR),
                         code_description: None,
                         source: Some(error_codes::ERROR_SOURCE.to_string()),
-                        message,
+                        message: reason,
                         related_information: None,
                         tags: None,
                         data: None,
                     },
-                ))
+                ));
             }
         }
     }
 
-    fn validate_source_ids(&self, errors: &mut ErrorSet) {
-        let flattened_entry_ids: Vec<&String> = self.entry_ids.iter().map(|i| &i.value).collect();
+    fn validate_curiosity_references(
+        &self,
+        system_files: &[Arc<ProjectFile>],
+        errors: &mut ErrorSet,
+    ) {
+        let custom_curiosities = Self::find_custom_curiosities(system_files);
 
-        for reference in self.source_id_references.iter() {
-            if !flattened_entry_ids.contains(&&reference.value)
-                && !VANILLA_ENTRY_IDS.contains(&reference.value.as_str())
+        for file in system_files.iter() {
+            Self::validate_curiosity_colors(file, errors);
+        }
+
+        for reference in self.curiosity_references.iter() {
+            let entry = self.find_entry(&reference.value);
+            // A regular entry that belongs to another curiosity, usually copied from a sibling
+            let hub = entry
+                .filter(|e| !e.is_curiosity)
+                .and_then(|e| e.curiosity.as_ref())
+                .filter(|hub| **hub != reference.value);
+            if hub.is_none()
+                && (Self::KNOWN_CURIOSITIES.contains(&reference.value.as_str())
+                    || custom_curiosities.contains(&reference.value)
+                    || self.is_external_curiosity(&reference.value)
+                    || entry.map(|e| e.is_curiosity).unwrap_or(false))
             {
-                let message = format!("Unknown Entry: `{}`", reference.value);
-                errors.push((
-                    reference.source_file.clone(),
-                    Diagnostic {
-                        range: reference.range,
-                        severity: Some(DiagnosticSeverity::ERROR),
-                        code: get_error_code(error_codes::SHIPLOG_MISSING_SOURCE_ID),
-                        code_description: None,
-                        source: Some(error_codes::ERROR_SOURCE.to_string()),
-                        message,
-                        related_information: None,
-                        tags: None,
-                        data: None,
-                    },
-                ))
+                continue;
             }
+            let (message, severity, code, data) = if let Some(hub) = hub {
+                (
+                    format!(
+                        "Entry `{}` isn't a Curiosity, it belongs to the Curiosity `{hub}`",
+                        reference.value
+                    ),
+                    DiagnosticSeverity::WARNING,
+                    error_codes::SHIPLOG_CURIOSITY_NOT_HUB,
+                    None,
+                )
+            } else if entry.is_some() {
+                (
+                    format!(
+                        "Entry `{}` is used as a Curiosity but isn't marked with IsCuriosity",
+                        reference.value
+                    ),
+                    DiagnosticSeverity::WARNING,
+                    error_codes::SHIPLOG_CURIOSITY_NOT_FLAGGED,
+                    None,
+                )
+            } else {
+                (
+                    format!(
+                        "Unknown Curiosity: `{}`. Please define it in a system config",
+                        reference.value
+                    ),
+                    DiagnosticSeverity::ERROR,
+                    error_codes::SHIPLOG_MISSING_CURIOSITY,
+                    // Lets the quick fix know what to define
+                    Some(Value::String(reference.value.clone())),
+                )
+            };
+            errors.push((
+                reference.source_file.clone(),
+                Diagnostic {
+                    range: reference.range,
+                    severity: Some(severity),
+                    code: get_error_code(code),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message,
+                    related_information: None,
+                    tags: None,
+                    data,
+                },
+            ))
         }
     }
 
-    pub fn validate(&self, project: &Project) -> ErrorSet {
-        let mut errors: ErrorSet = vec![];
-
-        self.validate_id_set_duplicates(&mut errors, "Astro Object", &self.astro_object_ids);
-        self.validate_id_set_duplicates(&mut errors, "Entry", &self.entry_ids);
-        self.validate_id_set_duplicates(&mut errors, "Fact", &self.fact_ids);
-
-        self.validate_id_taken(&mut errors, "Entry", &self.entry_ids, &VANILLA_ENTRY_IDS);
-        self.validate_id_taken(&mut errors, "Fact", &self.fact_ids, &VANILLA_FACT_IDS);
-
-        self.validate_curiosity_references(&project.system_files, &mut errors);
-        self.validate_source_ids(&mut errors);
-
-        errors
+    /// The star system the planet using this ship log is in
+    fn system_for_log<'a>(project: &'a Project, uri: &Url) -> Option<&'a String> {
+        let relative = project
+            .ship_log_files
+            .iter()
+            .find(|f| &f.id.uri == uri)?
+            .get_relative(&project.root_path)?;
+        let relative = normalize_config_path(&relative.to_string_lossy());
+        project
+            .ship_logs
+            .system_to_relative_path
+            .iter()
+            .find(|(_, paths)| paths.iter().any(|p| normalize_config_path(p) == relative))
+            .map(|(system, _)| system)
     }
 
-    const VANILLA_ASTRO_OBJECTS: [&'static str; 14] = [
-        "SUN_STATION",
-        "CAVE_TWIN",
-        "TOWER_TWIN",
-        "TIMBER_HEARTH",
-        "TIMBER_MOON",
-        "BRITTLE_HOLLOW",
-        "VOLCANIC_MOON",
-        "GIANTS_DEEP",
-        "ORBITAL_PROBE_CANNON",
-        "DARK_BRAMBLE",
-        "WHITE_HOLE",
-        "COMET",
-        "QUANTUM_MOON",
-        "INVISIBLE_PLANET",
-    ];
-
-    pub fn get_entries_for_system(&self, system: &str) -> Option<Vec<&ShipLogEntry>> {
-        let paths = self.system_to_relative_path.get(system)?;
-        eprintln!("PATHS: {:?}", paths);
-        let mut ao_ids = paths
+    fn system_file_for_log<'a>(project: &'a Project, uri: &Url) -> Option<&'a ProjectFile> {
+        let system = Self::system_for_log(project, uri)?;
+        project
+            .system_files
             .iter()
-            .filter_map(|path| self.relative_to_astro_object.get(path))
-            .map(|s| s.as_str())
-            .collect::<Vec<_>>();
+            .find(|f| f.nice_path.file_stem().and_then(|s| s.to_str()) == Some(system))
+            .map(|f| f.as_ref())
+    }
 
-        ao_ids.extend(Self::VANILLA_ASTRO_OBJECTS.iter());
+    /// Adds `item` to the end of a top level array of objects, creating the property if needed
+    fn json_array_append_edit(system_file: &ProjectFile, property: &str, item: &str) -> TextEdit {
+        let text = system_file.json();
+        let contents = text.as_str();
+        // Works whether or not the parser's range end is inclusive
+        let last_before =
+            |end: usize, c: char| contents[..json_idx_to_offset(contents, end + 1)].rfind(c);
+        let array_path = format!("/{property}");
+        let items_path = format!("/{property}/*");
+        let (array_end, last_item_end) = json_position_parser::parse_json(contents)
+            .ok()
+            .map(|tree| {
+                let array_end = tree
+                    .value_at(&json_path_to_json_pos_path(&array_path))
+                    .first()
+                    .and_then(|array| last_before(array.range.end.idx, ']'));
+                let last_item_end = tree
+                    .value_at(&json_path_to_json_pos_path(&items_path))
+                    .last()
+                    .and_then(|item| last_before(item.range.end.idx, '}'))
+                    .map(|idx| idx + 1);
+                (array_end, last_item_end)
+            })
+            .unwrap_or_default();
+        let (offset, new_text) = match (array_end, last_item_end) {
+            (_, Some(offset)) => (offset, format!(", {item}")),
+            (Some(offset), None) => (offset, item.to_string()),
+            (None, None) => {
+                let offset = contents.rfind('}').unwrap_or(contents.len());
+                let needs_comma = !contents[..offset].trim_end().ends_with('{');
+                (
+                    offset,
+                    format!(
+                        "{}\n    \"{property}\": [{item}]\n",
+                        if needs_comma { "," } else { "" }
+                    ),
+                )
+            }
+        };
+        let position = offset_to_lsp_position(contents, offset);
+        TextEdit::new(Range::new(position, position), new_text)
+    }
 
-        eprintln!("AO IDS: {:?}", ao_ids);
-        Some(
-            self.entries
-                .iter()
-                .filter_map(|(_id, entry)| {
-                    if ao_ids.contains(&entry.astro_object.as_str()) {
-                        Some(entry)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
+    fn curiosity_definition(curiosity: &str) -> String {
+        format!(
+            "{{ \"id\": \"{curiosity}\", \"color\": {{ \"r\": 255, \"g\": 255, \"b\": 255, \"a\": 255 }} }}"
         )
     }
-}
 
-#[derive(Default)]
-pub struct ShipLogValidator();
-
-impl Validator for ShipLogValidator {
-    fn prepare() -> Self {
-        Self()
+    fn define_curiosity_edit(system_file: &ProjectFile, curiosity: &str) -> TextEdit {
+        let definition = Self::curiosity_definition(curiosity);
+        Self::json_array_append_edit(system_file, "curiosities", &definition)
     }
 
-    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
-        project
-            .ship_log_files
+    /// Without any system config to add to, missing curiosities are defined in a new one
+    fn create_system_actions(
+        project: &Project,
+        uri: &Url,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeActionOrCommand> {
+        if project.unrooted {
+            return vec![];
+        }
+        // Nothing says which system a log no planet uses belongs in
+        let Some(system) = Self::system_for_log(project, uri) else {
+            return vec![];
+        };
+        let Ok(system_uri) = Url::from_file_path(
+            project
+                .root_path
+                .join("systems")
+                .join(format!("{system}.json")),
+        ) else {
+            return vec![];
+        };
+        diagnostics
             .iter()
-            .chain(project.system_files.iter())
-            .any(|file| changed_paths.contains(&file.id.uri))
+            .filter(|d| d.code == get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY))
+            .filter_map(|d| {
+                let curiosity = d.data.as_ref()?.as_str()?;
+                let contents = format!(
+                    "{{\n    \"curiosities\": [{}]\n}}\n",
+                    Self::curiosity_definition(curiosity)
+                );
+                let operations = vec![
+                    DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                        uri: system_uri.clone(),
+                        options: Some(CreateFileOptions {
+                            overwrite: Some(false),
+                            ignore_if_exists: Some(true),
+                        }),
+                        annotation_id: None,
+                    })),
+                    DocumentChangeOperation::Edit(TextDocumentEdit {
+                        text_document: OptionalVersionedTextDocumentIdentifier {
+                            uri: system_uri.clone(),
+                            version: None,
+                        },
+                        edits: vec![OneOf::Left(TextEdit::new(Range::default(), contents))],
+                    }),
+                ];
+                Some(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Create systems/{system}.json defining curiosity {curiosity}"),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![d.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        document_changes: Some(DocumentChanges::Operations(operations)),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }))
+            })
+            .collect()
     }
 
-    fn validate(&self, project: &Project) -> Vec<(VersionedTextDocumentIdentifier, Diagnostic)> {
-        ShipLogContext::from_project(project).validate(project)
+    fn entry_position_edit(system_file: &ProjectFile, entry_id: &str) -> TextEdit {
+        let position =
+            format!("{{ \"id\": \"{entry_id}\", \"position\": {{ \"x\": 0, \"y\": 0 }} }}");
+        Self::json_array_append_edit(system_file, "entryPositions", &position)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use lsp_types::{Position, Url};
-    use serde_json::json;
 
-    use super::*;
+    pub fn code_actions(
+        project: &Project,
+        uri: &Url,
+        range: Range,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeActionOrCommand> {
+        let Some(system_file) = Self::system_file_for_log(project, uri) else {
+            return Self::create_system_actions(project, uri, diagnostics);
+        };
+        let system_name = system_file
+            .nice_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut actions = diagnostics
+            .iter()
+            .filter(|d| d.code == get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY))
+            .filter_map(|d| {
+                let curiosity = d.data.as_ref()?.as_str()?;
+                let edit = Self::define_curiosity_edit(system_file, curiosity);
+                Some(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Define curiosity {curiosity} in {system_name}"),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![d.clone()]),
+                    edit: Some(WorkspaceEdit::new(HashMap::from([(
+                        system_file.id.uri.clone(),
+                        vec![edit],
+                    )]))),
+                    ..Default::default()
+                }))
+            })
+            .collect::<Vec<_>>();
+        let ctx = &project.ship_logs;
+        let unpositioned_entry = Self::find_id_at(&ctx.entry_ids, uri, range.start)
+            .filter(|id| !ctx.position_map.contains_key(&id.value));
+        if let Some(id) = unpositioned_entry {
+            let entry_id = &id.value;
+            let fixes = diagnostics
+                .iter()
+                .filter(|d| {
+                    d.code == get_error_code(error_codes::SHIPLOG_ENTRY_MISSING_POSITION)
+                        && d.data.as_ref().and_then(|data| data.get("entry"))
+                            == Some(&Value::String(entry_id.clone()))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            let edit = Self::entry_position_edit(system_file, entry_id);
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Add position for {entry_id} to {system_name}"),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(fixes),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    system_file.id.uri.clone(),
+                    vec![edit],
+                )]))),
+                ..Default::default()
+            }));
+        }
+        actions
+    }
 
-    fn get_test_file() -> Vec<ProjectFile> {
-        let contents = json!({
-            "curiosities": [{
-                "id": "EXAMPLE_ENTRY"
-            }]
-        });
-        let new_file = ProjectFile::new(
+    fn validate_xml_errors(&self, errors: &mut ErrorSet) {
+        for (file, why, range) in self.xml_errors.iter() {
+            errors.push((
+                file.clone(),
+                Diagnostic {
+                    range: *range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: get_error_code(error_codes::SHIPLOG_XML_PARSE_ERROR),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message: format!("Invalid XML: {why}"),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    fn validate_empty_ids(&self, errors: &mut ErrorSet) {
+        for id in self
+            .entry_ids
+            .iter()
+            .filter(|id| id.value.trim().is_empty())
+        {
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: get_error_code(error_codes::SHIPLOG_EMPTY_ID),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message: "Entry ID is empty, this entry will be ignored".to_string(),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    fn validate_parent_references(&self, errors: &mut ErrorSet) {
+        for id in self.entry_ids.iter() {
+            let Some(entry) = self.entries.get(&id.value) else {
+                continue;
+            };
+            let Some(parent) = &entry.parent else {
+                continue;
+            };
+            let message = match self.entries.get(parent) {
+                Some(parent_entry) if parent_entry.astro_object == entry.astro_object => continue,
+                Some(parent_entry) => format!(
+                    "Parent Entry `{parent}` belongs to astro object `{}`, not `{}`",
+                    parent_entry.astro_object, entry.astro_object
+                ),
+                None => format!("Unknown parent Entry: `{parent}`"),
+            };
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: get_error_code(error_codes::SHIPLOG_MISSING_PARENT),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message,
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    fn validate_entry_positions(&self, system_files: &[Arc<ProjectFile>], errors: &mut ErrorSet) {
+        let path = json_path_to_json_pos_path("/entryPositions/*/id");
+        for file in system_files.iter() {
+            let Ok(tree) = json_position_parser::parse_json(&file.json()) else {
+                continue;
+            };
+            let mut seen: HashMap<&str, Range> = HashMap::new();
+            for found in tree.value_at(&path) {
+                let EntryType::String(id) = &found.entry_type else {
+                    continue;
+                };
+                let range = json_pos_range_to_diag_range(&file.contents, found.range);
+                let (message, code, related_information) = if let Some(first) =
+                    seen.get(id.as_str())
+                {
+                    let related = DiagnosticRelatedInformation {
+                        location: Location::new(file.id.uri.clone(), *first),
+                        message: format!("`{id}` first positioned here"),
+                    };
+                    (
+                        format!("Duplicate position for Entry: `{id}`"),
+                        error_codes::SYSTEM_DUPLICATE_ENTRY_POSITION,
+                        Some(vec![related]),
+                    )
+                } else {
+                    seen.insert(id, range);
+                    if self.entries.contains_key(id) || VANILLA_ENTRY_IDS.contains(&id.as_str()) {
+                        continue;
+                    }
+                    (
+                        format!("Position set for unknown Entry: `{id}`"),
+                        error_codes::SYSTEM_UNKNOWN_ENTRY_POSITION,
+                        None,
+                    )
+                };
+                errors.push((
+                    file.id.clone(),
+                    Diagnostic {
+                        range,
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: get_error_code(code),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message,
+                        related_information,
+                        tags: None,
+                        data: None,
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Finds a path of rumor sources leading from `from` to `to`
+    fn find_source_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut came_from: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![current.to_string()];
+                let mut node = current;
+                while let Some(prev) = came_from.get(node) {
+                    path.push(prev.to_string());
+                    node = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let Some(entry) = self.entries.get(current) else {
+                continue;
+            };
+            for source in entry.sources.iter().chain(entry.explore_sources.iter()) {
+                if source != from && !came_from.contains_key(source.as_str()) {
+                    came_from.insert(source, current);
+                    queue.push_back(source);
+                }
+            }
+        }
+        None
+    }
+
+    fn push_circular_reference(errors: &mut ErrorSet, id: &ID, path: &[String]) {
+        errors.push((
+            id.source_file.clone(),
+            Diagnostic {
+                range: id.range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: get_error_code(error_codes::SHIPLOG_CIRCULAR_REFERENCE),
+                code_description: None,
+                source: Some(error_codes::ERROR_SOURCE.to_string()),
+                message: format!("Circular reference: {}", path.join(" -> ")),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        ))
+    }
+
+    fn validate_circular_references(&self, errors: &mut ErrorSet) {
+        for (owner, reference) in self.source_links.iter() {
+            if let Some(path) = self.find_source_path(&reference.value, owner) {
+                let mut cycle = vec![owner.clone()];
+                cycle.extend(path);
+                Self::push_circular_reference(errors, reference, &cycle);
+            }
+        }
+
+        for id in self.entry_ids.iter() {
+            let mut chain = vec![id.value.clone()];
+            let mut current = self.entries.get(&id.value).and_then(|e| e.parent.as_ref());
+            while let Some(parent) = current {
+                chain.push(parent.clone());
+                if parent == &id.value {
+                    Self::push_circular_reference(errors, id, &chain);
+                    break;
+                }
+                if chain[..chain.len() - 1].contains(parent) {
+                    break;
+                }
+                current = self.entries.get(parent).and_then(|e| e.parent.as_ref());
+            }
+        }
+    }
+
+    fn validate_source_ids(&self, errors: &mut ErrorSet) {
+        let flattened_entry_ids: Vec<&String> = self.entry_ids.iter().map(|i| &i.value).collect();
+
+        for reference in self.source_id_references.iter() {
+            if !flattened_entry_ids.contains(&&reference.value)
+                && !VANILLA_ENTRY_IDS.contains(&reference.value.as_str())
+                && !self
+                    .external
+                    .as_ref()
+                    .is_some_and(|external| external.entries.contains_key(&reference.value))
+            {
+                let message = format!("Unknown Entry: `{}`", reference.value);
+                errors.push((
+                    reference.source_file.clone(),
+                    Diagnostic {
+                        range: reference.range,
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: get_error_code(error_codes::SHIPLOG_MISSING_SOURCE_ID),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message,
+                        related_information: None,
+                        tags: None,
+                        data: None,
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Optional lint for non-root entries that no SourceID or Curiosity ever points at
+    pub fn validate_unreachable_entries(&self, errors: &mut ErrorSet) {
+        let referenced = self
+            .source_id_references
+            .iter()
+            .chain(self.curiosity_references.iter())
+            .map(|id| id.value.as_str())
+            .collect::<HashSet<_>>();
+        for id in self.entry_ids.iter() {
+            let Some(entry) = self.entries.get(&id.value) else {
+                continue;
+            };
+            if entry.parent.is_none()
+                || entry.is_curiosity
+                || referenced.contains(id.value.as_str())
+            {
+                continue;
+            }
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    code: get_error_code(error_codes::SHIPLOG_UNREACHABLE_ENTRY),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message: format!(
+                        "Entry `{}` is never referenced by a SourceID or Curiosity",
+                        id.value
+                    ),
+                    related_information: None,
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    fn validate_unused_curiosities(&self, errors: &mut ErrorSet) {
+        // A lone entry has nothing that could reference it, vanilla entries can't either
+        if self.custom_entry_count() <= 1 {
+            return;
+        }
+        let referenced = self
+            .curiosity_references
+            .iter()
+            .map(|id| id.value.as_str())
+            .collect::<HashSet<_>>();
+        for id in self.entry_ids.iter() {
+            let is_curiosity = self
+                .entries
+                .get(&id.value)
+                .map(|e| e.is_curiosity)
+                .unwrap_or(false);
+            if !is_curiosity || referenced.contains(id.value.as_str()) {
+                continue;
+            }
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: get_error_code(error_codes::SHIPLOG_UNUSED_CURIOSITY),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message: format!(
+                        "Entry `{}` is a Curiosity but no entry uses it as their Curiosity",
+                        id.value
+                    ),
+                    related_information: None,
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    /// NH only draws rumor arrows from a RumorFact's SourceID, and ignores it on ExploreFacts
+    fn validate_fact_sources(&self, errors: &mut ErrorSet) {
+        for fact in self.fact_definitions.iter() {
+            let (severity, code, message) = match (fact.is_rumor, &fact.source_id) {
+                (true, None) => (
+                    DiagnosticSeverity::WARNING,
+                    error_codes::SHIPLOG_RUMOR_MISSING_SOURCE,
+                    format!(
+                        "RumorFact `{}` in entry `{}` has no SourceID",
+                        fact.id, fact.entry
+                    ),
+                ),
+                (false, Some(source)) => (
+                    DiagnosticSeverity::INFORMATION,
+                    error_codes::SHIPLOG_EXPLORE_FACT_SOURCE_IGNORED,
+                    format!(
+                        "SourceID `{source}` on ExploreFact `{}` is ignored, only RumorFacts use it",
+                        fact.id
+                    ),
+                ),
+                _ => continue,
+            };
+            errors.push((
+                fact.source_file.clone(),
+                Diagnostic {
+                    range: fact.range,
+                    severity: Some(severity),
+                    code: get_error_code(code),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message,
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+    }
+
+    pub fn validate_entry_missing_positions(&self, project: &Project, errors: &mut ErrorSet) {
+        for id in self.entry_ids.iter() {
+            let Some(entry) = self.entries.get(&id.value) else {
+                continue;
+            };
+            // Child entries are positioned relative to their parent
+            if entry.position.is_some() || entry.parent.is_some() {
+                continue;
+            }
+            // Same lookup as the quick fix, so the message names the file it would edit
+            let system_file = Self::system_file_for_log(project, &id.source_file.uri).map(|file| {
+                let path = file
+                    .get_relative(&project.root_path)
+                    .unwrap_or_else(|| file.nice_path.clone());
+                normalize_config_path(&path.to_string_lossy())
+            });
+            let message = match &system_file {
+                Some(file) => format!(
+                    "Entry `{}` has no position, add one to `entryPositions` in `{file}`",
+                    id.value
+                ),
+                None => format!(
+                    "Entry `{}` has no position, add one to `entryPositions` in its system config",
+                    id.value
+                ),
+            };
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: get_error_code(error_codes::SHIPLOG_ENTRY_MISSING_POSITION),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message,
+                    related_information: None,
+                    tags: None,
+                    data: Some(serde_json::json!({
+                        "entry": id.value,
+                        "systemFile": system_file,
+                    })),
+                },
+            ));
+        }
+    }
+
+    pub fn validate(&self, project: &Project) -> ErrorSet {
+        let mut errors: ErrorSet = vec![];
+
+        self.validate_xml_errors(&mut errors);
+        let systems = self.systems_by_file(project);
+        for (id_name, set) in [
+            ("Astro Object", &self.astro_object_ids),
+            ("Entry", &self.entry_ids),
+            ("Fact", &self.fact_ids),
+        ] {
+            self.validate_id_set_duplicates(&mut errors, id_name, set, &systems);
+        }
+
+        self.validate_id_taken(&mut errors, "Entry", &self.entry_ids, &VANILLA_ENTRY_IDS);
+        self.validate_id_taken(&mut errors, "Fact", &self.fact_ids, &VANILLA_FACT_IDS);
+
+        self.validate_curiosity_references(&project.system_files, &mut errors);
+        self.validate_unused_curiosities(&mut errors);
+        self.validate_source_ids(&mut errors);
+        self.validate_fact_sources(&mut errors);
+        self.validate_empty_ids(&mut errors);
+        self.validate_parent_references(&mut errors);
+        self.validate_entry_positions(&project.system_files, &mut errors);
+        self.validate_circular_references(&mut errors);
+
+        errors
+    }
+
+    pub fn validate_astro_objects(&self, planet_files: &[Arc<ProjectFile>], errors: &mut ErrorSet) {
+        let planet_ids = planet_files
+            .iter()
+            .filter_map(|f| serde_json::from_str::<Planet>(&f.json()).ok())
+            .map(|p| normalize_astro_object_name(&p.name))
+            .collect::<Vec<_>>();
+        // Mods can add entries to planets another mod defines
+        let external_ids = self
+            .external
+            .iter()
+            .flat_map(|external| external.astro_object_ids.iter())
+            .map(|id| id.value.as_str());
+        let known_ids = planet_ids
+            .iter()
+            .map(String::as_str)
+            .chain(external_ids)
+            .collect::<Vec<_>>();
+        for id in self.astro_object_ids.iter() {
+            if Self::VANILLA_ASTRO_OBJECTS.contains(&id.value.as_str())
+                || known_ids.contains(&id.value.as_str())
+            {
+                continue;
+            }
+            let mut message = format!(
+                "Unknown astro object `{}`, its entries won't show up in game",
+                id.value
+            );
+            if let Some(closest) = known_ids
+                .iter()
+                .chain(Self::VANILLA_ASTRO_OBJECTS.iter())
+                .min_by_key(|known_id| edit_distance(&id.value, known_id))
+            {
+                message.push_str(&format!(", did you mean `{closest}`?"));
+            }
+            errors.push((
+                id.source_file.clone(),
+                Diagnostic {
+                    range: id.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: get_error_code(error_codes::SHIPLOG_UNKNOWN_ASTRO_OBJECT),
+                    code_description: None,
+                    source: Some(error_codes::ERROR_SOURCE.to_string()),
+                    message,
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ));
+        }
+    }
+
+    /// Planets in the same system whose ship logs are for the same astro object
+    pub fn validate_shared_astro_objects(
+        &self,
+        planet_files: &[Arc<ProjectFile>],
+        errors: &mut ErrorSet,
+    ) {
+        let mut groups: HashMap<(String, &str), Vec<(&ProjectFile, Range)>> = HashMap::new();
+        for file in planet_files.iter() {
+            let Ok(planet) = serde_json::from_str::<Planet>(&file.json()) else {
+                continue;
+            };
+            let Some(xml_file) = planet.ShipLog.and_then(|m| m.xml_file) else {
+                continue;
+            };
+            let xml_file = normalize_config_path(&xml_file);
+            let Some(ao_id) = self.relative_to_astro_object.get(&xml_file) else {
+                continue;
+            };
+            let range = json_value_range(&file.json(), "/ShipLog/xmlFile").unwrap_or_default();
+            groups
+                .entry((planet.starSystem, ao_id))
+                .or_default()
+                .push((file, range));
+        }
+        for ((system, ao_id), group) in groups.into_iter().filter(|(_, g)| g.len() > 1) {
+            for (i, (planet, range)) in group.iter().enumerate() {
+                let related = group
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, (other, other_range))| DiagnosticRelatedInformation {
+                        location: Location::new(other.id.uri.clone(), *other_range),
+                        message: format!("`{ao_id}` also used here"),
+                    })
+                    .collect();
+                errors.push((
+                    planet.id.clone(),
+                    Diagnostic {
+                        range: *range,
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: get_error_code(error_codes::SHIPLOG_SHARED_ASTRO_OBJECT),
+                        code_description: None,
+                        source: Some(error_codes::ERROR_SOURCE.to_string()),
+                        message: format!(
+                            "Astro object `{ao_id}` is used by {} planets in `{system}`, their entries will collide",
+                            group.len()
+                        ),
+                        related_information: Some(related),
+                        tags: None,
+                        data: None,
+                    },
+                ));
+            }
+        }
+    }
+
+    pub fn fact_exists(&self, fact_id: &str) -> bool {
+        VANILLA_FACT_IDS.contains(&fact_id)
+            || self.fact_ids.iter().any(|id| id.value == fact_id)
+            || self
+                .external
+                .iter()
+                .any(|external| external.fact_ids.iter().any(|id| id.value == fact_id))
+    }
+
+    fn find_id_at<'a>(set: &'a IdSet, uri: &Url, position: Position) -> Option<&'a ID> {
+        set.iter()
+            .find(|id| &id.source_file.uri == uri && range_contains(&id.range, position))
+    }
+
+    fn describe_entry(entry: &ShipLogEntry) -> String {
+        let mut lines = vec![
+            format!("**{}** (`{}`)", entry.name, entry.id),
+            format!("Astro Object: `{}`", entry.astro_object),
+        ];
+        if let Some(parent) = &entry.parent {
+            lines.push(format!("Parent: `{parent}`"));
+        }
+        lines.join("\n\n")
+    }
+
+    pub fn hover(&self, uri: &Url, position: Position) -> Option<Hover> {
+        let (id, value) = if let Some(id) = Self::find_id_at(&self.entry_ids, uri, position) {
+            (id, Self::describe_entry(self.entries.get(&id.value)?))
+        } else {
+            let id = Self::find_id_at(&self.fact_ids, uri, position)?;
+            let entry = self
+                .fact_to_entry
+                .get(&id.value)
+                .and_then(|entry_id| self.entries.get(entry_id))?;
+            (
+                id,
+                format!(
+                    "Fact `{}` of entry\n\n{}",
+                    id.value,
+                    Self::describe_entry(entry)
+                ),
+            )
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(id.range),
+        })
+    }
+
+    pub fn definition(&self, uri: &Url, position: Position) -> Option<Location> {
+        let target =
+            if let Some(reference) = Self::find_id_at(&self.source_id_references, uri, position) {
+                self.entry_ids.iter().find(|id| id.value == reference.value)
+            } else {
+                let reference = Self::find_id_at(&self.curiosity_references, uri, position)?;
+                self.entry_ids.iter().find(|id| {
+                    id.value == reference.value
+                        && self
+                            .entries
+                            .get(&id.value)
+                            .map(|e| e.is_curiosity)
+                            .unwrap_or(false)
+                })
+            };
+        target.map(|id| Location::new(id.source_file.uri.clone(), id.range))
+    }
+
+    /// Where `entryPositions` use the entry ID, without the quotes around it
+    fn entry_position_locations(
+        system_files: &[Arc<ProjectFile>],
+        entry_id: &str,
+    ) -> Vec<Location> {
+        let path = json_path_to_json_pos_path("/entryPositions/*/id");
+        let mut locations = vec![];
+        for file in system_files.iter() {
+            let Ok(tree) = json_position_parser::parse_json(&file.json()) else {
+                continue;
+            };
+            for found in tree.value_at(&path) {
+                if matches!(&found.entry_type, EntryType::String(id) if id == entry_id) {
+                    locations.push(Location::new(
+                        file.id.uri.clone(),
+                        json_pos_string_content_range(&file.contents, found.range),
+                    ));
+                }
+            }
+        }
+        locations
+    }
+
+    pub fn references(
+        &self,
+        system_files: &[Arc<ProjectFile>],
+        uri: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let target = &Self::find_id_at(&self.entry_ids, uri, position)
+            .or_else(|| Self::find_id_at(&self.source_id_references, uri, position))
+            .or_else(|| Self::find_id_at(&self.curiosity_references, uri, position))?
+            .value;
+        let mut locations: Vec<Location> = self
+            .entry_ids
+            .iter()
+            .filter(|_| include_declaration)
+            .chain(self.source_id_references.iter())
+            .chain(self.curiosity_references.iter())
+            .filter(|id| &id.value == target)
+            .map(|id| Location::new(id.source_file.uri.clone(), id.range))
+            .collect();
+        locations.extend(Self::entry_position_locations(system_files, target));
+        Some(locations)
+    }
+
+    fn value_completion(label: &str, detail: Option<String>) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail,
+            ..Default::default()
+        }
+    }
+
+    fn entry_completions(&self, filter: impl Fn(&ShipLogEntry) -> bool) -> Vec<CompletionItem> {
+        self.entries
+            .values()
+            .filter(|entry| filter(entry))
+            .map(|entry| CompletionItem {
+                documentation: Some(Documentation::String(format!(
+                    "Astro Object: {}",
+                    entry.astro_object
+                ))),
+                ..Self::value_completion(&entry.id, Some(entry.name.clone()))
+            })
+            .collect()
+    }
+
+    fn fact_completions(&self) -> Vec<CompletionItem> {
+        let mut items: Vec<CompletionItem> = self
+            .fact_ids
+            .iter()
+            .map(|id| {
+                let entry = self
+                    .fact_to_entry
+                    .get(&id.value)
+                    .and_then(|entry_id| self.entries.get(entry_id));
+                Self::value_completion(&id.value, entry.map(|e| e.name.clone()))
+            })
+            .collect();
+        items.extend(
+            VANILLA_FACT_IDS
+                .iter()
+                .map(|id| Self::value_completion(id, None)),
+        );
+        items
+    }
+
+    pub fn completion(
+        &self,
+        system_files: &[Arc<ProjectFile>],
+        raw_str: &str,
+        position: Position,
+    ) -> Option<Vec<CompletionItem>> {
+        let tree = Document::parse(raw_str).ok()?;
+        let node = xml_element_at(&tree, lsp_position_to_offset(raw_str, position))?;
+        match node.tag_name().name() {
+            "SourceID" => {
+                let mut items = self.entry_completions(|_| true);
+                items.extend(
+                    VANILLA_ENTRY_IDS
+                        .iter()
+                        .filter(|id| !self.entries.contains_key(**id))
+                        .map(|id| Self::value_completion(id, None)),
+                );
+                Some(items)
+            }
+            "Curiosity" => {
+                let mut items: Vec<CompletionItem> = Self::KNOWN_CURIOSITIES
+                    .iter()
+                    .map(|id| Self::value_completion(id, Some("Base-game curiosity".to_string())))
+                    .collect();
+                items.extend(
+                    Self::find_custom_curiosities(system_files)
+                        .iter()
+                        .map(|id| Self::value_completion(id, Some("Custom curiosity".to_string()))),
+                );
+                Some(items)
+            }
+            "FactID" | "Condition" | "AltPhotoCondition" | "IgnoreMoreToExploreCondition" => {
+                Some(self.fact_completions())
+            }
+            _ => None,
+        }
+    }
+
+    fn find_renameable_at(&self, uri: &Url, position: Position) -> Option<&ID> {
+        let id = Self::find_id_at(&self.entry_ids, uri, position)
+            .or_else(|| Self::find_id_at(&self.source_id_references, uri, position))
+            .or_else(|| Self::find_id_at(&self.curiosity_references, uri, position))?;
+        if self.entry_ids.iter().any(|e| e.value == id.value) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    pub fn prepare_rename(&self, uri: &Url, position: Position) -> Option<Range> {
+        self.find_renameable_at(uri, position)
+            .map(|id| id.value_range)
+    }
+
+    pub fn rename(
+        &self,
+        system_files: &[Arc<ProjectFile>],
+        uri: &Url,
+        position: Position,
+        new_name: &str,
+    ) -> Result<WorkspaceEdit, String> {
+        let old_name = &self
+            .find_renameable_at(uri, position)
+            .ok_or("No renameable entry ID at this position")?
+            .value;
+        if new_name.is_empty() {
+            return Err("Entry ID can't be empty".to_string());
+        }
+        if VANILLA_ENTRY_IDS.contains(&new_name) {
+            return Err(format!("Entry ID `{new_name}` is taken by the base-game"));
+        }
+        if self.entry_ids.iter().any(|id| id.value == new_name) {
+            return Err(format!("Entry ID `{new_name}` is already in use"));
+        }
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for id in self
+            .entry_ids
+            .iter()
+            .chain(self.source_id_references.iter())
+            .chain(self.curiosity_references.iter())
+            .filter(|id| &id.value == old_name)
+        {
+            changes
+                .entry(id.source_file.uri.clone())
+                .or_default()
+                .push(TextEdit::new(id.value_range, new_name.to_string()));
+        }
+        for location in Self::entry_position_locations(system_files, old_name) {
+            changes
+                .entry(location.uri)
+                .or_default()
+                .push(TextEdit::new(location.range, new_name.to_string()));
+        }
+        Ok(WorkspaceEdit::new(changes))
+    }
+
+    fn node_range(tree: &Document, node: &Node) -> Range {
+        xml_node_range(tree, node)
+    }
+
+    fn child_text<'a>(node: &Node<'a, '_>, tag: &str) -> Option<(Node<'a, 'a>, &'a str)> {
+        node.children()
+            .find(|n| n.tag_name().name() == tag)
+            .map(|n| (n, n.text().unwrap_or_default()))
+    }
+
+    fn element_symbol(
+        tree: &Document,
+        node: &Node,
+        kind: SymbolKind,
+        detail: Option<String>,
+        children: Option<Vec<DocumentSymbol>>,
+    ) -> Option<DocumentSymbol> {
+        let (id_node, id) = Self::child_text(node, "ID")?;
+        #[allow(deprecated)]
+        Some(DocumentSymbol {
+            name: if id.trim().is_empty() {
+                "<empty ID>".to_string()
+            } else {
+                id.to_string()
+            },
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: Self::node_range(tree, node),
+            selection_range: Self::node_range(tree, &id_node),
+            children,
+        })
+    }
+
+    fn entry_symbol(tree: &Document, node: &Node) -> Option<DocumentSymbol> {
+        let children = node
+            .children()
+            .filter_map(|child| match child.tag_name().name() {
+                "Entry" => Self::entry_symbol(tree, &child),
+                "RumorFact" | "ExploreFact" => Self::element_symbol(
+                    tree,
+                    &child,
+                    SymbolKind::KEY,
+                    Some(child.tag_name().name().to_string()),
+                    None,
+                ),
+                _ => None,
+            })
+            .collect();
+        let name = Self::child_text(node, "Name").map(|(_, name)| name.to_string());
+        Self::element_symbol(tree, node, SymbolKind::OBJECT, name, Some(children))
+    }
+
+    pub fn document_symbols(raw_str: &str) -> Option<Vec<DocumentSymbol>> {
+        let tree = Document::parse(raw_str).ok()?;
+        let root = tree
+            .descendants()
+            .find(|e| e.tag_name().name() == "AstroObjectEntry")?;
+        let entries = root
+            .children()
+            .filter(|n| n.tag_name().name() == "Entry")
+            .filter_map(|n| Self::entry_symbol(&tree, &n))
+            .collect();
+        Some(vec![Self::element_symbol(
+            &tree,
+            &root,
+            SymbolKind::OBJECT,
+            None,
+            Some(entries),
+        )?])
+    }
+
+    /// The Name of the entry each SourceID and Curiosity in `range` points at, after the element
+    pub fn inlay_hints(&self, uri: &Url, range: Range) -> Vec<InlayHint> {
+        self.source_id_references
+            .iter()
+            .chain(self.curiosity_references.iter())
+            .filter(|id| &id.source_file.uri == uri && range_contains(&range, id.range.end))
+            .filter_map(|id| {
+                let entry = self.find_entry(&id.value)?;
+                if entry.name.is_empty() {
+                    return None;
+                }
+                Some(InlayHint {
+                    position: id.range.end,
+                    label: InlayHintLabel::String(format!("« {}", entry.name)),
+                    kind: None,
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Astro object and entry IDs, SourceID and Curiosity references, and fact IDs in `uri`
+    pub fn semantic_tokens(&self, uri: &Url) -> Vec<SemanticToken> {
+        let mut tokens = [
+            (&self.astro_object_ids, SEMANTIC_ID),
+            (&self.entry_ids, SEMANTIC_ID),
+            (&self.source_id_references, SEMANTIC_REFERENCE),
+            (&self.curiosity_references, SEMANTIC_REFERENCE),
+            (&self.fact_ids, SEMANTIC_FACT),
+        ]
+        .into_iter()
+        .flat_map(|(set, token_type)| set.iter().map(move |id| (id, token_type)))
+        .filter(|(id, _)| &id.source_file.uri == uri && !id.value.is_empty())
+        // Tokens can't span lines
+        .filter(|(id, _)| id.value_range.start.line == id.value_range.end.line)
+        .map(|(id, token_type)| (id.value_range, token_type))
+        .collect::<Vec<_>>();
+        tokens.sort_by_key(|(range, _)| range.start);
+        let mut previous = Position::default();
+        tokens
+            .into_iter()
+            .map(|(range, token_type)| {
+                let delta_line = range.start.line - previous.line;
+                let delta_start = if delta_line == 0 {
+                    range.start.character - previous.character
+                } else {
+                    range.start.character
+                };
+                previous = range.start;
+                SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length: range.end.character - range.start.character,
+                    token_type,
+                    token_modifiers_bitset: 0,
+                }
+            })
+            .collect()
+    }
+
+    pub fn workspace_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let query = query.to_lowercase();
+        let astro_objects = self
+            .astro_object_ids
+            .iter()
+            .map(|id| (id, SymbolKind::OBJECT, None));
+        let entries = self.entry_ids.iter().map(|id| {
+            let container = self.entries.get(&id.value).map(|e| e.astro_object.clone());
+            (id, SymbolKind::OBJECT, container)
+        });
+        let facts = self.fact_ids.iter().map(|id| {
+            (
+                id,
+                SymbolKind::KEY,
+                self.fact_to_entry.get(&id.value).cloned(),
+            )
+        });
+        astro_objects
+            .chain(entries)
+            .chain(facts)
+            .filter(|(id, _, _)| !id.value.is_empty() && id.value.to_lowercase().contains(&query))
+            .map(|(id, kind, container_name)| {
+                #[allow(deprecated)]
+                SymbolInformation {
+                    name: id.value.clone(),
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    location: Location::new(id.source_file.uri.clone(), id.range),
+                    container_name,
+                }
+            })
+            .collect()
+    }
+
+    const VANILLA_ASTRO_OBJECTS: [&'static str; 14] = [
+        "SUN_STATION",
+        "CAVE_TWIN",
+        "TOWER_TWIN",
+        "TIMBER_HEARTH",
+        "TIMBER_MOON",
+        "BRITTLE_HOLLOW",
+        "VOLCANIC_MOON",
+        "GIANTS_DEEP",
+        "ORBITAL_PROBE_CANNON",
+        "DARK_BRAMBLE",
+        "WHITE_HOLE",
+        "COMET",
+        "QUANTUM_MOON",
+        "INVISIBLE_PLANET",
+    ];
+
+    pub fn get_facts_for_entry(&self, entry_id: &str) -> Vec<ShipLogFact> {
+        self.entries
+            .get(entry_id)
+            .map(Self::entry_facts)
+            .unwrap_or_default()
+    }
+
+    fn entry_facts(entry: &ShipLogEntry) -> Vec<ShipLogFact> {
+        let rumors = entry.facts.rumor.iter().map(|f| (f, true));
+        let explores = entry.facts.explore.iter().map(|f| (f, false));
+        rumors
+            .chain(explores)
+            .map(|(fact, is_rumor)| ShipLogFact {
+                is_rumor,
+                ..fact.clone()
+            })
+            .collect()
+    }
+
+    /// Looks in external projects too, like `find_entry`
+    pub fn get_entry_details(&self, entry_id: &str) -> Option<EntryDetails> {
+        let mut entry = self.find_entry(entry_id)?.clone();
+        if let Some(position) = self.position_map.get(entry_id) {
+            entry.position = Some(*position);
+        }
+        let location = self
+            .entry_ids
+            .iter()
+            .chain(self.external.iter().flat_map(|e| e.entry_ids.iter()))
+            .find(|id| id.value == entry_id)
+            .map(|id| Location::new(id.source_file.uri.clone(), id.range));
+        Some(EntryDetails {
+            facts: Self::entry_facts(&entry),
+            entry,
+            location,
+        })
+    }
+
+    fn entries_for_system(&self, system: &str) -> Option<Vec<&ShipLogEntry>> {
+        let paths = self.system_to_relative_path.get(system)?;
+        let mut ao_ids = paths
+            .iter()
+            .filter_map(|path| {
+                self.relative_to_astro_object
+                    .get(&normalize_config_path(path))
+            })
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>();
+
+        ao_ids.extend(Self::VANILLA_ASTRO_OBJECTS.iter());
+
+        let entries = self
+            .entries
+            .iter()
+            .filter_map(|(_id, entry)| {
+                if ao_ids.contains(&entry.astro_object.as_str()) {
+                    Some(entry)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(entries)
+    }
+
+    pub fn get_facts_for_system(&self, system: &str) -> Option<Vec<SystemFact>> {
+        let entries = self.entries_for_system(system)?;
+        Some(
+            entries
+                .iter()
+                .flat_map(|entry| {
+                    self.get_facts_for_entry(&entry.id)
+                        .into_iter()
+                        .map(|fact| SystemFact {
+                            entry: entry.id.clone(),
+                            fact,
+                        })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn get_curiosities(&self, system: Option<&str>) -> Vec<CuriosityInfo> {
+        let mut curiosities = self
+            .system_curiosities
+            .iter()
+            .filter(|(name, _)| system.map(|s| s == name.as_str()).unwrap_or(true))
+            .flat_map(|(name, curiosities)| {
+                curiosities.iter().map(move |curiosity| {
+                    let mut entries = self
+                        .entries
+                        .values()
+                        .filter(|e| e.curiosity.as_ref() == Some(&curiosity.id))
+                        .map(|e| e.id.clone())
+                        .collect::<Vec<_>>();
+                    entries.sort_unstable();
+                    CuriosityInfo {
+                        system: name.clone(),
+                        curiosity: curiosity.clone(),
+                        entries,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        curiosities.sort_by(|a, b| (&a.system, &a.curiosity.id).cmp(&(&b.system, &b.curiosity.id)));
+        curiosities
+    }
+
+    /// Entries, rumor arrows and curiosity colors of a system, ready to be laid out
+    pub fn get_ship_log_graph(&self, system: &str) -> Option<ShipLogGraph> {
+        let entries = self.entries_for_system(system)?;
+        let curiosities = self
+            .system_curiosities
+            .get(system)
+            .map(|curiosities| curiosities.iter().map(Curiosity::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut nodes = entries
+            .iter()
+            .map(|entry| {
+                let curiosity = entry
+                    .curiosity
+                    .clone()
+                    .or_else(|| entry.is_curiosity.then(|| entry.id.clone()));
+                let color = curiosity
+                    .as_ref()
+                    .and_then(|id| curiosities.iter().find(|c| &c.id == id))
+                    .map(|c| c.color.clone())
+                    .unwrap_or(DEFAULT_CURIOSITY_COLOR);
+                GraphNode {
+                    id: entry.id.clone(),
+                    name: entry.name.clone(),
+                    astro_object: entry.astro_object.clone(),
+                    position: entry.position,
+                    is_curiosity: entry.is_curiosity,
+                    curiosity,
+                    color,
+                }
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut edges = entries
+            .iter()
+            .flat_map(|entry| {
+                entry.facts.rumor.iter().filter_map(|fact| {
+                    let source = fact.source_id.as_ref()?;
+                    // Only link entries the client is actually going to draw
+                    entries.iter().any(|e| &e.id == source).then(|| GraphEdge {
+                        source: source.clone(),
+                        target: entry.id.clone(),
+                        fact: fact.id.clone(),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by(|a, b| a.fact.cmp(&b.fact));
+        Some(ShipLogGraph {
+            nodes,
+            edges,
+            curiosities,
+        })
+    }
+
+    pub fn get_entries_for_system(
+        &self,
+        system: &str,
+        include_external: bool,
+    ) -> Option<SystemEntriesResponse> {
+        let local = self.entries_for_system(system);
+        let external = self
+            .external
+            .as_ref()
+            .filter(|_| include_external)
+            .and_then(|external| external.entries_for_system(system));
+        if local.is_none() && external.is_none() {
+            return None;
+        }
+        let mut entries = local
+            .unwrap_or_default()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        for entry in external.unwrap_or_default() {
+            if !entries.iter().any(|e| e.id == entry.id) {
+                entries.push(ShipLogEntry {
+                    // Vanilla entries don't come from any project
+                    external: entry.source_file.is_some(),
+                    ..entry.clone()
+                });
+            }
+        }
+        let mut links = vec![];
+        for entry in entries.iter() {
+            let rumors = entry.sources.iter().map(|s| (s, true));
+            let explores = entry.explore_sources.iter().map(|s| (s, false));
+            for (source, is_rumor) in rumors.chain(explores) {
+                // Only link entries the client is actually going to draw
+                if entries.iter().any(|e| &e.id == source) {
+                    links.push(ShipLogLink {
+                        source: source.clone(),
+                        target: entry.id.clone(),
+                        is_rumor,
+                    });
+                }
+            }
+        }
+        Some(SystemEntriesResponse { entries, links })
+    }
+}
+
+pub struct ShipLogValidator {
+    pub lint_unreachable_entries: bool,
+}
+
+impl Validator for ShipLogValidator {
+    fn name() -> &'static str {
+        "ship_log"
+    }
+
+    fn prepare() -> Self {
+        Self {
+            lint_unreachable_entries: true,
+        }
+    }
+
+    fn should_invalidate(&self, changed_paths: &[Url], project: &Project) -> bool {
+        project
+            .ship_log_files
+            .iter()
+            .chain(project.system_files.iter())
+            .chain(project.planet_files.iter())
+            .any(|file| changed_paths.contains(&file.id.uri))
+    }
+
+    fn validate(&self, project: &Project) -> Vec<(VersionedTextDocumentIdentifier, Diagnostic)> {
+        let mut errors = project.ship_logs.validate(project);
+        project
+            .ship_logs
+            .validate_astro_objects(&project.planet_files, &mut errors);
+        project
+            .ship_logs
+            .validate_shared_astro_objects(&project.planet_files, &mut errors);
+        project
+            .ship_logs
+            .validate_entry_missing_positions(project, &mut errors);
+        if self.lint_unreachable_entries {
+            project.ship_logs.validate_unreachable_entries(&mut errors);
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lsp_types::{Position, Url};
+    use serde_json::json;
+
+    use super::*;
+
+    fn get_test_file() -> Vec<Arc<ProjectFile>> {
+        let contents = json!({
+            "curiosities": [{
+                "id": "EXAMPLE_ENTRY"
+            }]
+        });
+        let new_file = ProjectFile::new(
             Url::parse("file://test_system.json").unwrap(),
             0,
-            serde_json::to_string(&contents).unwrap(),
+            serde_json::to_string(&contents).unwrap(),
+        );
+        vec![new_file.into()]
+    }
+
+    fn get_test_project() -> Project {
+        Project {
+            system_files: get_test_file(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_example() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        assert_eq!(ctx.astro_object_ids.len(), 1);
+        assert_eq!(ctx.astro_object_ids[0].value, "EXAMPLE_PLANET");
+        assert_eq!(
+            ctx.astro_object_ids[0].range.start,
+            Position {
+                line: 2,
+                character: 4
+            }
+        );
+
+        assert_eq!(ctx.entry_ids.len(), 3);
+        assert_eq!(ctx.entry_ids[0].value, "EXAMPLE_ENTRY");
+        assert_eq!(ctx.entry_ids[1].value, "EXAMPLE_CHILD_ENTRY");
+        assert_eq!(
+            ctx.entry_ids[1].range.start,
+            Position {
+                line: 33,
+                character: 12
+            }
+        );
+
+        let entry = &ctx.entries["EXAMPLE_ENTRY_2"];
+        assert_eq!(entry.sources, vec!["EXAMPLE_ENTRY"]);
+        assert!(entry.explore_sources.is_empty());
+    }
+
+    #[test]
+    fn test_get_facts_for_entry() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::from_project(&Project::default());
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let facts = ctx.get_facts_for_entry("EXAMPLE_ENTRY_2");
+        let rumor = facts
+            .iter()
+            .find(|f| f.id == "EXAMPLE_RUMOR_FACT_2")
+            .unwrap();
+        assert!(rumor.is_rumor);
+        assert_eq!(rumor.source_id.as_deref(), Some("EXAMPLE_ENTRY"));
+        assert!(rumor.rumor_name.is_some());
+        assert!(facts.iter().any(|f| !f.is_rumor));
+        assert_eq!(
+            ctx.entries["EXAMPLE_ENTRY"].children,
+            vec!["EXAMPLE_CHILD_ENTRY".to_string()]
+        );
+
+        let facts = ctx.get_facts_for_entry("S_SUNSTATION");
+        let rumor = facts.iter().find(|f| f.id == "S_SUNSTATION_R1").unwrap();
+        assert!(rumor.is_rumor);
+        assert_eq!(rumor.source_id.as_deref(), Some("CT_SUNLESS_CITY"));
+        assert!(facts
+            .iter()
+            .any(|f| f.id == "S_SUNSTATION_X1" && !f.is_rumor));
+
+        assert!(ctx.get_facts_for_entry("MISSING_ENTRY").is_empty());
+    }
+
+    #[test]
+    fn test_get_entries_for_system() {
+        let mut ctx = ShipLogContext::from_project(&Project::default());
+        ctx.system_to_relative_path
+            .insert("SolarSystem".to_string(), vec![]);
+
+        let response = ctx.get_entries_for_system("SolarSystem", false).unwrap();
+        assert!(response.entries.iter().any(|e| e.id == "S_SUNSTATION"));
+        assert!(response
+            .links
+            .iter()
+            .any(|link| link.source == "CT_SUNLESS_CITY"
+                && link.target == "S_SUNSTATION"
+                && link.is_rumor));
+
+        assert!(ctx.get_entries_for_system("MissingSystem", false).is_none());
+
+        let parent = &ctx.entries["CT_HIGH_ENERGY_LAB"];
+        assert!(parent.children.contains(&"CT_WARP_TOWER_MAP".to_string()));
+
+        let facts = ctx.get_facts_for_system("SolarSystem").unwrap();
+        let fact = facts
+            .iter()
+            .find(|f| f.fact.id == "S_SUNSTATION_R1")
+            .unwrap();
+        assert_eq!(fact.entry, "S_SUNSTATION");
+        assert!(fact.fact.is_rumor);
+        assert!(ctx.get_facts_for_system("MissingSystem").is_none());
+    }
+
+    #[test]
+    fn test_external_projects() {
+        let child_log = r#"<AstroObjectEntry>
+    <ID>CHILD_PLANET</ID>
+    <Entry>
+        <ID>CHILD_ENTRY</ID>
+        <Name>Child Entry</Name>
+        <Curiosity>PARENT_CURIOSITY</Curiosity>
+        <RumorFact>
+            <ID>CHILD_RUMOR_FACT</ID>
+            <SourceID>PARENT_CURIOSITY</SourceID>
+        </RumorFact>
+    </Entry>
+</AstroObjectEntry>"#;
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Child.json").unwrap(),
+                0,
+                r#"{"name": "Child", "starSystem": "ParentSystem", "ShipLog": {"xmlFile": "planets/Child.xml"}}"#
+                    .to_string(),
+            ).into()],
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Child.xml").unwrap(),
+                0,
+                child_log.to_string(),
+            ).into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let messages = |project: &Project| {
+            project
+                .ship_logs
+                .validate(project)
+                .into_iter()
+                .map(|(_, d)| d.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(messages(&project).len(), 2);
+        assert!(!project.ship_logs.fact_exists("PARENT_EXPLORE_FACT"));
+
+        let external =
+            vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/external_mod")];
+        assert!(project.set_external_projects(&external));
+        assert!(!project.set_external_projects(&external));
+        // The external ship log's duplicate isn't reported either
+        assert!(messages(&project).is_empty());
+        assert!(project.ship_logs.fact_exists("PARENT_EXPLORE_FACT"));
+
+        let response = project
+            .ship_logs
+            .get_entries_for_system("ParentSystem", true)
+            .unwrap();
+        let parent = response
+            .entries
+            .iter()
+            .find(|e| e.id == "PARENT_CURIOSITY")
+            .unwrap();
+        assert!(parent.external);
+        assert!(response
+            .entries
+            .iter()
+            .any(|e| e.id == "CHILD_ENTRY" && !e.external));
+        assert!(response
+            .links
+            .iter()
+            .any(|link| link.source == "PARENT_CURIOSITY" && link.target == "CHILD_ENTRY"));
+
+        let response = project
+            .ship_logs
+            .get_entries_for_system("ParentSystem", false)
+            .unwrap();
+        assert!(response.entries.iter().all(|e| !e.external));
+
+        project.set_external_projects(&[]);
+        assert_eq!(messages(&project).len(), 2);
+    }
+
+    #[test]
+    fn test_get_ship_log_graph() {
+        let log = r#"<AstroObjectEntry>
+    <ID>TEST_PLANET</ID>
+    <Entry>
+        <ID>RED_CURIOSITY</ID>
+        <Name>Red</Name>
+        <IsCuriosity />
+        <Entry>
+            <ID>RED_CHILD</ID>
+            <Name>Red Child</Name>
+            <Curiosity>RED_CURIOSITY</Curiosity>
+            <RumorFact>
+                <ID>RED_CHILD_R1</ID>
+                <SourceID>PLAIN_CURIOSITY</SourceID>
+            </RumorFact>
+            <ExploreFact>
+                <ID>RED_CHILD_X1</ID>
+                <SourceID>PLAIN_CURIOSITY</SourceID>
+            </ExploreFact>
+        </Entry>
+    </Entry>
+    <Entry>
+        <ID>PLAIN_CURIOSITY</ID>
+        <Name>Plain</Name>
+        <IsCuriosity />
+    </Entry>
+    <Entry>
+        <ID>LONE_ENTRY</ID>
+        <Name>Lone</Name>
+    </Entry>
+</AstroObjectEntry>"#;
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Test.json").unwrap(),
+                0,
+                r#"{"name": "Test", "starSystem": "TestSystem", "ShipLog": {"xmlFile": "planets/Test.xml"}}"#
+                    .to_string(),
+            ).into()],
+            system_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/systems/TestSystem.json").unwrap(),
+                0,
+                r#"{"curiosities": [{"id": "RED_CURIOSITY", "color": {"r": 255, "g": 0, "b": 0}}, {"id": "PLAIN_CURIOSITY"}]}"#
+                    .to_string(),
+            ).into()],
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Test.xml").unwrap(),
+                0,
+                log.to_string(),
+            ).into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let graph = project.ship_logs.get_ship_log_graph("TestSystem").unwrap();
+        let node = |id: &str| graph.nodes.iter().find(|n| n.id == id).unwrap();
+        assert_eq!(
+            node("RED_CHILD").curiosity.as_deref(),
+            Some("RED_CURIOSITY")
+        );
+        assert_eq!(
+            (node("RED_CHILD").color.r, node("RED_CHILD").color.g),
+            (255, 0)
+        );
+        assert_eq!(node("RED_CURIOSITY").color.a, 255);
+        assert_eq!(node("PLAIN_CURIOSITY").color.g, DEFAULT_CURIOSITY_COLOR.g);
+        assert!(node("LONE_ENTRY").curiosity.is_none());
+        assert_eq!(node("LONE_ENTRY").color.b, DEFAULT_CURIOSITY_COLOR.b);
+
+        // ExploreFact SourceIDs don't draw arrows
+        let edges = graph
+            .edges
+            .iter()
+            .filter(|e| e.target == "RED_CHILD")
+            .collect::<Vec<_>>();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].source, "PLAIN_CURIOSITY");
+        assert_eq!(edges[0].fact, "RED_CHILD_R1");
+
+        assert_eq!(graph.curiosities.len(), 2);
+        assert_eq!(graph.curiosities[1].color.g, DEFAULT_CURIOSITY_COLOR.g);
+
+        assert!(project
+            .ship_logs
+            .get_ship_log_graph("MissingSystem")
+            .is_none());
+    }
+
+    #[test]
+    fn test_get_curiosities() {
+        const TEST_STR: &str = include_str!("test_files/curiosity_not_flagged.xml");
+
+        let mut ctx = ShipLogContext::default();
+        ctx.parse_system_positions(&ProjectFile::new(
+            Url::parse("file:///systems/Test.json").unwrap(),
+            0,
+            r#"{"curiosities": [
+                {"id": "EXAMPLE_FLAGGED_ENTRY", "color": {"r": 255, "g": 0, "b": 0}},
+                {"id": "EXAMPLE_ENTRY_2"}
+            ]}"#
+            .to_string(),
+        ));
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ctx.parse(&test_file, &ProjectFile::dummy(), Path::new("."), TEST_STR)
+            .unwrap();
+
+        let curiosities = ctx.get_curiosities(Some("Test"));
+        assert_eq!(curiosities.len(), 2);
+        assert_eq!(curiosities[0].system, "Test");
+        assert_eq!(curiosities[0].curiosity.id, "EXAMPLE_ENTRY_2");
+        assert!(curiosities[0].curiosity.color.is_none());
+        let flagged = &curiosities[1];
+        assert_eq!(flagged.curiosity.color.as_ref().unwrap().a, 255);
+        assert_eq!(
+            flagged.entries,
+            vec!["EXAMPLE_CHILD_ENTRY", "EXAMPLE_FLAGGED_ENTRY"]
+        );
+
+        assert_eq!(ctx.get_curiosities(None).len(), 2);
+        assert!(ctx.get_curiosities(Some("MissingSystem")).is_empty());
+    }
+
+    #[test]
+    fn test_validate_entry_positions() {
+        let system = ProjectFile::new(
+            Url::parse("file:///mod/systems/Test.json").unwrap(),
+            0,
+            r#"{"entryPositions": [
+    {"id": "S_SUNSTATION", "position": {"x": 0, "y": 0}},
+    {"id": "MISSING", "position": {"x": 0, "y": 0}},
+    {"id": "S_SUNSTATION", "position": {"x": 1, "y": 1}}
+]}"#
+            .to_string(),
+        );
+        let mut errors = vec![];
+        ShipLogContext::default().validate_entry_positions(&[system.into()], &mut errors);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].1.message,
+            "Position set for unknown Entry: `MISSING`"
+        );
+        assert_eq!(
+            errors[1].1.message,
+            "Duplicate position for Entry: `S_SUNSTATION`"
+        );
+        let related = errors[1].1.related_information.as_ref().unwrap();
+        assert_eq!(related[0].location.range.start.line, 1);
+        assert_eq!(errors[1].1.range.start.line, 3);
+    }
+
+    #[test]
+    fn test_validate_shared_astro_objects() {
+        let planet = |name: &str, system: &str, xml_file: &str| {
+            ProjectFile::new(
+                Url::parse(&format!("file:///mod/planets/{name}.json")).unwrap(),
+                0,
+                format!(
+                    r#"{{"name": "{name}", "starSystem": "{system}", "ShipLog": {{"xmlFile": "{xml_file}"}}}}"#
+                ),
+            )
+        };
+        let ship_log = |name: &str, ao_id: &str| {
+            ProjectFile::new(
+                Url::parse(&format!("file:///mod/planets/{name}.xml")).unwrap(),
+                0,
+                format!("<AstroObjectEntry><ID>{ao_id}</ID></AstroObjectEntry>"),
+            )
+        };
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![
+                planet("A", "Test", "planets/Shared.xml").into(),
+                planet("B", "Test", "./planets/Shared.xml").into(),
+                planet("C", "Other", "planets/Shared.xml").into(),
+                planet("D", "Test", "planets/Own.xml").into(),
+            ],
+            ship_log_files: vec![
+                ship_log("Shared", "SHARED").into(),
+                ship_log("Own", "OWN").into(),
+            ],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let mut errors = vec![];
+        project
+            .ship_logs
+            .validate_shared_astro_objects(&project.planet_files, &mut errors);
+        errors.sort_by(|a, b| a.0.uri.cmp(&b.0.uri));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].0.uri.path().ends_with("A.json"));
+        assert!(errors[1].0.uri.path().ends_with("B.json"));
+        let related = errors[0].1.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].location.uri, errors[1].0.uri);
+        assert_eq!(related[0].location.range, errors[1].1.range);
+        assert_ne!(errors[1].1.range, Range::default());
+        assert_eq!(
+            errors[0].1.message,
+            "Astro object `SHARED` is used by 2 planets in `Test`, their entries will collide"
+        );
+    }
+
+    #[test]
+    fn test_validate_astro_objects() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ctx.parse(&test_file, &ProjectFile::dummy(), Path::new("."), TEST_STR)
+            .unwrap();
+        let planet = |name: &str| ProjectFile {
+            contents: format!(r#"{{"name": "{name}"}}"#),
+            ..ProjectFile::dummy()
+        };
+
+        let mut errors = vec![];
+        ctx.validate_astro_objects(&[planet("Example_Planet").into()], &mut errors);
+        assert!(errors.is_empty());
+
+        ctx.validate_astro_objects(
+            &[planet("Other").into(), planet("Example's Plant").into()],
+            &mut errors,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_UNKNOWN_ASTRO_OBJECT)
+        );
+        assert_eq!(
+            errors[0].1.message,
+            "Unknown astro object `EXAMPLE_PLANET`, its entries won't show up in game, did you mean `EXAMPLESPLANT`?"
+        );
+
+        // Typos of vanilla objects are suggested too
+        let mut errors = vec![];
+        let mut ctx = ShipLogContext::default();
+        ctx.parse(
+            &test_file,
+            &ProjectFile::dummy(),
+            Path::new("."),
+            "<AstroObjectEntry><ID>TIMBER_HERTH</ID></AstroObjectEntry>",
+        )
+        .unwrap();
+        ctx.validate_astro_objects(&[], &mut errors);
+        assert!(errors[0]
+            .1
+            .message
+            .ends_with("did you mean `TIMBER_HEARTH`?"));
+
+        // Planets from external projects are known as well
+        let mut errors = vec![];
+        let mut external = ShipLogContext::default();
+        external
+            .parse(
+                &test_file,
+                &ProjectFile::dummy(),
+                Path::new("."),
+                "<AstroObjectEntry><ID>TIMBER_HERTH</ID></AstroObjectEntry>",
+            )
+            .unwrap();
+        ctx.external = Some(Arc::new(external));
+        ctx.validate_astro_objects(&[], &mut errors);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validate_entry_missing_positions() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let file = |path: &str, contents: &str| -> Arc<ProjectFile> {
+            ProjectFile::new(
+                Url::parse(&format!("file:///mod/{path}")).unwrap(),
+                0,
+                contents.to_string(),
+            )
+            .into()
+        };
+        // The layout can put systems anywhere, and the planet's path isn't normalized
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            system_files: vec![file(
+                "star_systems/Example.json",
+                include_str!("test_files/system_positions.json"),
+            )],
+            planet_files: vec![file(
+                "planets/Example.json",
+                r#"{"name": "Example", "starSystem": "Example", "ShipLog": {"xmlFile": "./logs/example.xml"}}"#,
+            )],
+            ship_log_files: vec![file("logs/example.xml", TEST_STR)],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let mut errors = vec![];
+        project
+            .ship_logs
+            .validate_entry_missing_positions(&project, &mut errors);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.message,
+            "Entry `EXAMPLE_ENTRY_2` has no position, add one to `entryPositions` in `star_systems/Example.json`"
+        );
+        assert_eq!(
+            errors[0].1.data,
+            Some(serde_json::json!({
+                "entry": "EXAMPLE_ENTRY_2",
+                "systemFile": "star_systems/Example.json",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicates() {
+        const TEST_STR: &str = include_str!("test_files/duplicate_ids.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+        let duplicates = errors
+            .iter()
+            .filter(|e| e.1.code == get_error_code(error_codes::SHIPLOG_DUPLICATE_ID))
+            .collect::<Vec<_>>();
+
+        assert_eq!(duplicates.len(), 6);
+        for message in [
+            "Duplicate Entry ID: `EXAMPLE_ENTRY`",
+            "Duplicate Fact ID: `EXAMPLE_EXPLORE_FACT`",
+            "Duplicate Fact ID: `EXAMPLE_RUMOR_FACT`",
+        ] {
+            let group = duplicates
+                .iter()
+                .filter(|e| e.1.message == message)
+                .collect::<Vec<_>>();
+            assert_eq!(group.len(), 2);
+            // Each one points at the other
+            for (i, error) in group.iter().enumerate() {
+                let related = error.1.related_information.as_ref().unwrap();
+                assert_eq!(related.len(), 1);
+                assert_eq!(related[0].location.uri, test_file.uri);
+                assert_eq!(related[0].location.range, group[1 - i].1.range);
+                assert!(related[0].message.ends_with("also defined here"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_duplicates_across_files() {
+        let mut ctx = ShipLogContext::default();
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        let first = ShipLogFile::new(Url::parse("file:///first.xml").unwrap(), 0);
+        let second = ShipLogFile::new(Url::parse("file:///second.xml").unwrap(), 0);
+        ctx.parse(
+            &first,
+            &pf,
+            cwd,
+            "<AstroObjectEntry><ID>FIRST</ID><Entry><ID>SHARED</ID></Entry></AstroObjectEntry>",
+        )
+        .unwrap();
+        ctx.parse(
+            &second,
+            &pf,
+            cwd,
+            "<AstroObjectEntry><ID>SECOND</ID>\n<Entry><ID>SHARED</ID></Entry></AstroObjectEntry>",
+        )
+        .unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+        let duplicates = errors
+            .iter()
+            .filter(|e| e.1.message == "Duplicate Entry ID: `SHARED`")
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates.len(), 2);
+        let in_file =
+            |file: &ShipLogFile| &duplicates.iter().find(|e| e.0.uri == file.uri).unwrap().1;
+        let (in_first, in_second) = (in_file(&first), in_file(&second));
+        assert_eq!(in_second.range.start.line, 1);
+        let related = in_first.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(
+            related[0].location,
+            Location::new(second.uri.clone(), in_second.range)
+        );
+        let related = in_second.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(
+            related[0].location,
+            Location::new(first.uri.clone(), in_first.range)
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicates_per_system() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test_files/two_systems_mod");
+        let mut project = Project::default();
+        project.load_from(&root);
+
+        let errors = project.ship_logs.validate(&project);
+        let mut duplicates = errors
+            .iter()
+            .filter(|e| e.1.code == get_error_code(error_codes::SHIPLOG_DUPLICATE_ID))
+            .map(|e| {
+                let file = e.0.uri.path().rsplit('/').next().unwrap().to_string();
+                (e.1.message.as_str(), file)
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        // Gamma reuses Alpha's IDs from another system, Beta clashes with Alpha in the same one
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    "Duplicate Entry ID: `CLASHING_ENTRY`",
+                    "Alpha.xml".to_string()
+                ),
+                (
+                    "Duplicate Entry ID: `CLASHING_ENTRY`",
+                    "Beta.xml".to_string()
+                ),
+                (
+                    "Duplicate Fact ID: `CLASHING_FACT`",
+                    "Alpha.xml".to_string()
+                ),
+                ("Duplicate Fact ID: `CLASHING_FACT`", "Beta.xml".to_string()),
+            ]
+        );
+
+        // Moving Gamma into the first system makes its IDs clash too
+        let gamma = project
+            .planet_files
+            .iter_mut()
+            .find(|f| f.id.uri.path().ends_with("Gamma.json"))
+            .unwrap();
+        let gamma = Arc::make_mut(gamma);
+        gamma.contents = gamma.contents.replace("SecondSystem", "FirstSystem");
+        project.rebuild_ship_logs();
+        let errors = project.ship_logs.validate(&project);
+        for message in [
+            "Duplicate Astro Object ID: `REUSED_PLANET`",
+            "Duplicate Entry ID: `REUSED_ENTRY`",
+            "Duplicate Fact ID: `REUSED_FACT`",
+        ] {
+            assert_eq!(errors.iter().filter(|e| e.1.message == message).count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_validate_missing_curiosity() {
+        const TEST_STR: &str = include_str!("test_files/missing_curiosity.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.message,
+            "Unknown Curiosity: `COOL_ROCK`. Please define it in a system config"
+        );
+    }
+
+    #[test]
+    fn test_validate_curiosity_colors() {
+        let system_file = ProjectFile::new(
+            Url::parse("file:///systems/Test.json").unwrap(),
+            0,
+            r##"{"curiosities": [
+    {"id": "GOOD", "color": {"r": 255, "g": 0, "b": 0}, "highlightColor": "#FF8000"},
+    {"id": "BAD_OBJECT", "color": {"r": 300, "g": 0, "b": 0}},
+    {"id": "BAD_HEX", "highlightColor": "#FF80"}
+]}"##
+                .to_string(),
+        );
+        let mut errors = vec![];
+        ShipLogContext::validate_curiosity_colors(&system_file, &mut errors);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::CONFIG_INVALID_COLOR)
+        );
+        assert_eq!(
+            errors[0].1.message,
+            "Color component `r` must be a whole number from 0 to 255, got `300`"
+        );
+        assert_eq!(errors[0].1.range.start, Position::new(2, 34));
+        assert_eq!(
+            errors[1].1.message,
+            "`#FF80` isn't a valid hex color (e.g. `#FF8000`)"
+        );
+        assert_eq!(errors[1].1.range.start, Position::new(3, 40));
+    }
+
+    #[test]
+    fn test_validate_unused_curiosity() {
+        const TEST_STR: &str = include_str!("test_files/unused_curiosity.xml");
+
+        let mut ctx = ShipLogContext::default();
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ctx.parse(&test_file, &ProjectFile::dummy(), Path::new("."), TEST_STR)
+            .unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_UNUSED_CURIOSITY)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            errors[0].1.message,
+            "Entry `EXAMPLE_UNUSED_CURIOSITY` is a Curiosity but no entry uses it as their Curiosity"
+        );
+        assert_eq!(errors[0].1.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        // Single entry projects are left alone
+        let mut ctx = ShipLogContext::default();
+        ctx.parse(
+            &test_file,
+            &ProjectFile::dummy(),
+            Path::new("."),
+            "<AstroObjectEntry><ID>PLANET</ID><Entry><ID>LONE</ID><IsCuriosity/></Entry></AstroObjectEntry>",
+        )
+        .unwrap();
+        let mut errors = vec![];
+        ctx.validate_unused_curiosities(&mut errors);
+        assert!(errors.is_empty());
+
+        // Even with the vanilla entries merged in
+        let project = Project {
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///planets/Lone.xml").unwrap(),
+                0,
+                "<AstroObjectEntry><ID>PLANET</ID><Entry><ID>LONE</ID><IsCuriosity/></Entry></AstroObjectEntry>"
+                    .to_string(),
+            ).into()],
+            ..Default::default()
+        };
+        let ctx = ShipLogContext::from_project(&project);
+        assert!(ctx.entries.len() > 1);
+        let mut errors = vec![];
+        ctx.validate_unused_curiosities(&mut errors);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_validate_missing_source_id() {
+        const TEST_STR: &str = include_str!("test_files/missing_source_id.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Unknown Entry: `GABAGOOL`");
+    }
+
+    fn validate_test_file(contents: &str) -> ErrorSet {
+        let mut ctx = ShipLogContext::default();
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        ctx.parse(&test_file, &pf, Path::new("."), contents)
+            .unwrap();
+        ctx.validate(&get_test_project())
+    }
+
+    #[test]
+    fn test_validate_rumor_missing_source() {
+        let errors = validate_test_file(include_str!("test_files/rumor_missing_source.xml"));
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_RUMOR_MISSING_SOURCE)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            errors[0].1.message,
+            "RumorFact `EXAMPLE_RUMOR_FACT` in entry `EXAMPLE_ENTRY` has no SourceID"
+        );
+        assert_eq!(errors[0].1.range.start, Position::new(8, 8));
+    }
+
+    #[test]
+    fn test_validate_explore_fact_source_ignored() {
+        let errors = validate_test_file(include_str!("test_files/explore_fact_source.xml"));
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_EXPLORE_FACT_SOURCE_IGNORED)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::INFORMATION));
+        assert_eq!(errors[0].1.range.start, Position::new(8, 8));
+    }
+
+    #[test]
+    fn test_validate_curiosity_not_flagged() {
+        const TEST_STR: &str = include_str!("test_files/curiosity_not_flagged.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            errors[0].1.message,
+            "Entry `EXAMPLE_ENTRY_2` is used as a Curiosity but isn't marked with IsCuriosity"
         );
-        vec![new_file]
     }
 
-    fn get_test_project() -> Project {
-        Project {
-            system_files: get_test_file(),
+    #[test]
+    fn test_validate_curiosity_not_hub() {
+        const TEST_STR: &str = include_str!("test_files/curiosity_not_hub.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_CURIOSITY_NOT_HUB)
+        );
+        assert_eq!(
+            errors[0].1.message,
+            "Entry `EXAMPLE_CHILD_ENTRY` isn't a Curiosity, it belongs to the Curiosity `EXAMPLE_CURIOSITY`"
+        );
+        assert_eq!(errors[0].1.range.start.line, 18);
+    }
+
+    #[test]
+    fn test_missing_curiosity_code_action() {
+        const TEST_STR: &str = include_str!("test_files/missing_curiosity.xml");
+
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Test.json").unwrap(),
+                0,
+                r#"{"name": "Test", "starSystem": "TestSystem", "ShipLog": {"xmlFile": "./planets/test_file.xml"}}"#
+                    .to_string(),
+            ).into()],
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/test_file.xml").unwrap(),
+                0,
+                TEST_STR.to_string(),
+            ).into()],
+            system_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/systems/TestSystem.json").unwrap(),
+                0,
+                "{\n    \"name\": \"TestSystem\"\n}".to_string(),
+            ).into()],
             ..Default::default()
-        }
+        };
+        project.rebuild_ship_logs();
+        let errors = project.ship_logs.validate(&project);
+        let diagnostics = errors
+            .into_iter()
+            .map(|(_, d)| d)
+            .filter(|d| d.code == get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY))
+            .collect::<Vec<_>>();
+        assert!(!diagnostics.is_empty());
+
+        let uri = project.ship_log_files[0].id.uri.clone();
+        let actions = ShipLogContext::code_actions(&project, &uri, Range::default(), &diagnostics);
+        assert_eq!(actions.len(), diagnostics.len());
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("Expected a code action");
+        };
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()
+            [&project.system_files[0].id.uri];
+        assert_eq!(edits[0].range.start, Position::new(2, 0));
+        assert!(edits[0]
+            .new_text
+            .starts_with(",\n    \"curiosities\": [{ \"id\": "));
     }
 
     #[test]
-    fn test_parse_example() {
+    fn test_missing_curiosity_creates_system() {
+        const TEST_STR: &str = include_str!("test_files/missing_curiosity.xml");
+
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Test.json").unwrap(),
+                0,
+                r#"{"name": "Test", "ShipLog": {"xmlFile": "planets/test_file.xml"}}"#.to_string(),
+            )
+            .into()],
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/test_file.xml").unwrap(),
+                0,
+                TEST_STR.to_string(),
+            )
+            .into()],
+            // Belongs to another system, so it shouldn't get the curiosity
+            system_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/systems/OtherSystem.json").unwrap(),
+                0,
+                "{\n    \"name\": \"OtherSystem\"\n}".to_string(),
+            )
+            .into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let diagnostics = project
+            .ship_logs
+            .validate(&project)
+            .into_iter()
+            .map(|(_, d)| d)
+            .filter(|d| d.code == get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY))
+            .collect::<Vec<_>>();
+
+        let uri = project.ship_log_files[0].id.uri.clone();
+        let actions = ShipLogContext::code_actions(&project, &uri, Range::default(), &diagnostics);
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("Expected a code action");
+        };
+        assert_eq!(
+            action.title,
+            "Create systems/SolarSystem.json defining curiosity COOL_ROCK"
+        );
+        let Some(DocumentChanges::Operations(operations)) =
+            &action.edit.as_ref().unwrap().document_changes
+        else {
+            panic!("Expected document change operations");
+        };
+        let DocumentChangeOperation::Op(ResourceOp::Create(create)) = &operations[0] else {
+            panic!("Expected the system config to be created first");
+        };
+        assert_eq!(create.uri.path(), "/mod/systems/SolarSystem.json");
+        let DocumentChangeOperation::Edit(edit) = &operations[1] else {
+            panic!("Expected an edit filling in the system config");
+        };
+        let OneOf::Left(text_edit) = &edit.edits[0] else {
+            panic!("Expected a plain text edit");
+        };
+        let system: Value = serde_json::from_str(&text_edit.new_text).unwrap();
+        assert_eq!(system["curiosities"][0]["id"], "COOL_ROCK");
+
+        project.unrooted = true;
+        assert!(
+            ShipLogContext::code_actions(&project, &uri, Range::default(), &diagnostics).is_empty()
+        );
+    }
+
+    fn entry_position_action(system_json: &str) -> TextEdit {
         const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
 
+        let mut project = Project {
+            root_path: PathBuf::from("/mod"),
+            planet_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/Test.json").unwrap(),
+                0,
+                r#"{"name": "Test", "starSystem": "test_system", "ShipLog": {"xmlFile": "planets\\test_file.xml"}}"#
+                    .to_string(),
+            ).into()],
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/planets/test_file.xml").unwrap(),
+                0,
+                TEST_STR.to_string(),
+            ).into()],
+            system_files: vec![ProjectFile::new(
+                Url::parse("file:///mod/systems/test_system.json").unwrap(),
+                0,
+                system_json.to_string(),
+            ).into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let uri = project.ship_log_files[0].id.uri.clone();
+        // Inside <ID>EXAMPLE_ENTRY_2</ID>
+        let position = Position::new(52, 14);
+        let actions =
+            ShipLogContext::code_actions(&project, &uri, Range::new(position, position), &[]);
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("Expected a code action");
+        };
+        assert_eq!(
+            action.title,
+            "Add position for EXAMPLE_ENTRY_2 to test_system.json"
+        );
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()
+            [&project.system_files[0].id.uri];
+        edits[0].clone()
+    }
+
+    #[test]
+    fn test_entry_position_code_action() {
+        const ITEM: &str = r#"{ "id": "EXAMPLE_ENTRY_2", "position": { "x": 0, "y": 0 } }"#;
+
+        let edit = entry_position_action(include_str!("test_files/system_positions.json"));
+        assert_eq!(edit.range.start, Position::new(5, 9));
+        assert_eq!(edit.new_text, format!(", {ITEM}"));
+
+        let edit = entry_position_action("{\n    \"entryPositions\": []\n}");
+        assert_eq!(edit.range.start, Position::new(1, 23));
+        assert_eq!(edit.new_text, ITEM);
+
+        let edit = entry_position_action("{\n    \"name\": \"TestSystem\"\n}");
+        assert_eq!(edit.range.start, Position::new(2, 0));
+        assert_eq!(
+            edit.new_text,
+            format!(",\n    \"entryPositions\": [{ITEM}]\n")
+        );
+    }
+
+    #[test]
+    fn test_validate_xml_errors() {
         let mut ctx = ShipLogContext::default();
 
         let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
         let pf = ProjectFile::dummy();
         let cwd = Path::new(".");
-        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+        let res = ctx.parse(
+            &test_file,
+            &pf,
+            cwd,
+            "<AstroObjectEntry>\n    <ID>FOO</Name>\n</AstroObjectEntry>",
+        );
+        assert!(res.is_err());
 
-        assert_eq!(ctx.astro_object_ids.len(), 1);
-        assert_eq!(ctx.astro_object_ids[0].value, "EXAMPLE_PLANET");
+        let errors = ctx.validate(&get_test_project());
+        assert_eq!(errors.len(), 1);
         assert_eq!(
-            ctx.astro_object_ids[0].range.start,
-            Position {
-                line: 2,
-                character: 4
-            }
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_XML_PARSE_ERROR)
         );
+        assert_eq!(errors[0].1.range.start.line, 1);
 
-        assert_eq!(ctx.entry_ids.len(), 3);
-        assert_eq!(ctx.entry_ids[0].value, "EXAMPLE_ENTRY");
-        assert_eq!(ctx.entry_ids[1].value, "EXAMPLE_CHILD_ENTRY");
+        ctx.remove_file(&test_file.uri, cwd);
+        assert!(ctx.validate(&get_test_project()).is_empty());
+    }
+
+    #[test]
+    fn test_validator_reports_mismatched_tags() {
+        const TEST_STR: &str = include_str!("test_files/mismatched_tags.xml");
+
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::new(
+                Url::parse("file:///mismatched_tags.xml").unwrap(),
+                0,
+                TEST_STR.to_string(),
+            )
+            .into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+
+        let errors = ShipLogValidator::prepare().validate(&project);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0.uri, project.ship_log_files[0].id.uri);
         assert_eq!(
-            ctx.entry_ids[1].range.start,
-            Position {
-                line: 33,
-                character: 12
-            }
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_XML_PARSE_ERROR)
         );
+        assert_eq!(errors[0].1.range.start.line, 4);
     }
 
     #[test]
-    fn test_validate_duplicates() {
-        const TEST_STR: &str = include_str!("test_files/duplicate_ids.xml");
+    fn test_validate_empty_id() {
+        const TEST_STR: &str = include_str!("test_files/empty_id.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|e| e.1.code == get_error_code(error_codes::SHIPLOG_EMPTY_ID)));
+        assert_eq!(errors[0].1.range.start, Position::new(5, 8));
+        assert_eq!(errors[1].1.range.start, Position::new(15, 8));
+    }
+
+    #[test]
+    fn test_validate_missing_parent() {
+        const TEST_STR: &str = include_str!("test_files/missing_parent.xml");
 
         let mut ctx = ShipLogContext::default();
 
@@ -527,66 +3490,395 @@ mod tests {
 
         let errors = ctx.validate(&get_test_project());
 
-        assert_eq!(errors.len(), 6);
+        let errors = errors
+            .iter()
+            .filter(|e| e.1.code == get_error_code(error_codes::SHIPLOG_MISSING_PARENT))
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.message, "Unknown parent Entry: ``");
+        assert_eq!(errors[0].1.range.start, Position::new(9, 12));
+    }
+
+    #[test]
+    fn test_validate_unreachable_entries() {
+        const TEST_STR: &str = include_str!("test_files/missing_parent.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let mut errors = vec![];
+        ctx.validate_unreachable_entries(&mut errors);
+
+        let messages = errors
+            .iter()
+            .map(|e| e.1.message.as_str())
+            .collect::<Vec<_>>();
         assert_eq!(
-            errors
-                .iter()
-                .filter(|e| e.1.message == "Duplicate ID: `EXAMPLE_ENTRY`")
-                .count(),
-            2
+            messages,
+            vec![
+                "Entry `EXAMPLE_CHILD_ENTRY` is never referenced by a SourceID or Curiosity",
+                "Entry `EXAMPLE_CHILD_ENTRY_2` is never referenced by a SourceID or Curiosity"
+            ]
         );
+        // Editors fade these out rather than underlining them
+        assert!(errors
+            .iter()
+            .all(|e| e.1.tags == Some(vec![DiagnosticTag::UNNECESSARY])));
+    }
+
+    #[test]
+    fn test_validate_circular_references() {
+        const TEST_STR: &str = include_str!("test_files/circular_sources.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let errors = ctx.validate(&get_test_project());
+
+        let mut messages = errors
+            .iter()
+            .map(|e| (e.1.range.start.line, e.1.message.as_str()))
+            .collect::<Vec<_>>();
+        messages.sort();
         assert_eq!(
-            errors
-                .iter()
-                .filter(|e| e.1.message == "Duplicate ID: `EXAMPLE_EXPLORE_FACT`")
-                .count(),
-            2
+            messages,
+            vec![
+                (
+                    8,
+                    "Circular reference: ENTRY_A -> ENTRY_B -> ENTRY_C -> ENTRY_A"
+                ),
+                (
+                    16,
+                    "Circular reference: ENTRY_B -> ENTRY_C -> ENTRY_A -> ENTRY_B"
+                ),
+                (
+                    24,
+                    "Circular reference: ENTRY_C -> ENTRY_A -> ENTRY_B -> ENTRY_C"
+                ),
+                (36, "Circular reference: ENTRY_SELF -> ENTRY_SELF"),
+            ]
         );
+    }
+
+    #[test]
+    fn test_hover() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let hover = ctx.hover(&test_file.uri, Position::new(33, 16)).unwrap();
+        let HoverContents::Markup(contents) = hover.contents else {
+            panic!("Expected markup hover contents");
+        };
+        assert!(contents.value.contains("**Example Child Entry**"));
+        assert!(contents.value.contains("Parent: `EXAMPLE_ENTRY`"));
+
+        let hover = ctx.hover(&test_file.uri, Position::new(15, 16)).unwrap();
+        let HoverContents::Markup(contents) = hover.contents else {
+            panic!("Expected markup hover contents");
+        };
+        assert!(contents.value.contains("Fact `EXAMPLE_RUMOR_FACT`"));
+        assert!(contents.value.contains("**Example Planet**"));
+
+        assert!(ctx.hover(&test_file.uri, Position::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_definition() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let location = ctx
+            .definition(&test_file.uri, Position::new(61, 25))
+            .unwrap();
+        assert_eq!(location.uri, test_file.uri);
+        assert_eq!(location.range.start, Position::new(5, 8));
+
+        let location = ctx
+            .definition(&test_file.uri, Position::new(35, 25))
+            .unwrap();
+        assert_eq!(location.range.start, Position::new(5, 8));
+
+        assert!(ctx
+            .definition(&test_file.uri, Position::new(5, 10))
+            .is_none());
+    }
+
+    #[test]
+    fn test_references() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let locations = ctx
+            .references(&[], &test_file.uri, Position::new(61, 25), true)
+            .unwrap();
+        // The declaration, three curiosities and one source
+        assert_eq!(locations.len(), 5);
+        assert!(locations
+            .iter()
+            .any(|l| l.range.start == Position::new(5, 8)));
+
+        let locations = ctx
+            .references(&[], &test_file.uri, Position::new(5, 10), false)
+            .unwrap();
+        assert_eq!(locations.len(), 4);
+
+        assert!(ctx
+            .references(&[], &test_file.uri, Position::new(0, 0), true)
+            .is_none());
+    }
+
+    #[test]
+    fn test_workspace_symbols() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
+
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        assert_eq!(ctx.workspace_symbols("").len(), 11);
+
+        let symbols = ctx.workspace_symbols("child_rumor");
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "EXAMPLE_CHILD_RUMOR_FACT");
+        assert_eq!(symbols[0].kind, SymbolKind::KEY);
         assert_eq!(
-            errors
-                .iter()
-                .filter(|e| e.1.message == "Duplicate ID: `EXAMPLE_RUMOR_FACT`")
-                .count(),
-            2
+            symbols[0].container_name.as_deref(),
+            Some("EXAMPLE_CHILD_ENTRY")
         );
+
+        let symbols = ctx.workspace_symbols("Child_Entry");
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].container_name.as_deref(), Some("EXAMPLE_PLANET"));
     }
 
     #[test]
-    fn test_validate_missing_curiosity() {
-        const TEST_STR: &str = include_str!("test_files/missing_curiosity.xml");
+    fn test_inlay_hints() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
 
         let mut ctx = ShipLogContext::default();
 
         let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
-
         let pf = ProjectFile::dummy();
         let cwd = Path::new(".");
         ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
 
-        let errors = ctx.validate(&get_test_project());
+        let everything = Range::new(Position::new(0, 0), Position::new(u32::MAX, 0));
+        let hints = ctx.inlay_hints(&test_file.uri, everything);
+        assert_eq!(hints.len(), 4);
 
-        assert_eq!(errors.len(), 1);
+        let hints = ctx.inlay_hints(
+            &test_file.uri,
+            Range::new(Position::new(60, 0), Position::new(63, 0)),
+        );
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].position, Position::new(61, 46));
+        assert!(
+            matches!(&hints[0].label, InlayHintLabel::String(name) if name == "« Example Planet")
+        );
+
+        // References that don't resolve get no hint
+        let mut ctx = ShipLogContext::default();
+        ctx.parse(
+            &test_file,
+            &pf,
+            cwd,
+            "<AstroObjectEntry><ID>PLANET</ID><Entry><ID>ENTRY</ID><Curiosity>MISSING</Curiosity></Entry></AstroObjectEntry>",
+        )
+        .unwrap();
+        assert!(ctx.inlay_hints(&test_file.uri, everything).is_empty());
+
+        // Vanilla entries are named too
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::new(
+                test_file.uri.clone(),
+                0,
+                "<AstroObjectEntry><ID>PLANET</ID><Entry><ID>ENTRY</ID><RumorFact><ID>FACT</ID>\
+                <SourceID>S_SUNSTATION</SourceID></RumorFact></Entry></AstroObjectEntry>"
+                    .to_string(),
+            )
+            .into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let hints = project.ship_logs.inlay_hints(&test_file.uri, everything);
+        assert_eq!(hints.len(), 1);
+        assert!(matches!(&hints[0].label, InlayHintLabel::String(name) if name == "« Sun Station"));
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let mut ctx = ShipLogContext::default();
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        ctx.parse(
+            &test_file,
+            &ProjectFile::dummy(),
+            Path::new("."),
+            "<AstroObjectEntry><ID>PLANET</ID>\n<Entry><ID>ENTRY</ID><Curiosity>ENTRY</Curiosity><ExploreFact><ID>FACT</ID></ExploreFact></Entry></AstroObjectEntry>",
+        )
+        .unwrap();
+
+        let tokens = ctx
+            .semantic_tokens(&test_file.uri)
+            .into_iter()
+            .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type))
+            .collect::<Vec<_>>();
         assert_eq!(
-            errors[0].1.message,
-            "Unknown Curiosity: `COOL_ROCK`. Please define it in a system config"
+            tokens,
+            vec![
+                (0, 22, 6, SEMANTIC_ID),
+                (1, 11, 5, SEMANTIC_ID),
+                (0, 21, 5, SEMANTIC_REFERENCE),
+                (0, 34, 4, SEMANTIC_FACT),
+            ]
         );
+
+        let other_file = Url::parse("file://other_file.xml").unwrap();
+        assert!(ctx.semantic_tokens(&other_file).is_empty());
     }
 
     #[test]
-    fn test_validate_missing_source_id() {
-        const TEST_STR: &str = include_str!("test_files/missing_source_id.xml");
+    fn test_document_symbols() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let symbols = ShipLogContext::document_symbols(TEST_STR).unwrap();
+
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "EXAMPLE_PLANET");
+        let entries = symbols[0].children.as_ref().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "EXAMPLE_ENTRY");
+        assert_eq!(entries[0].detail.as_deref(), Some("Example Planet"));
+        assert_eq!(entries[0].range.start, Position::new(4, 4));
+        assert_eq!(entries[0].selection_range.start, Position::new(5, 8));
+
+        let children = entries[0].children.as_ref().unwrap();
+        let names = children.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "EXAMPLE_RUMOR_FACT",
+                "EXAMPLE_EXPLORE_FACT",
+                "EXAMPLE_CHILD_ENTRY"
+            ]
+        );
+        assert_eq!(children[2].children.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_completion() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
 
         let mut ctx = ShipLogContext::default();
 
         let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
+        let pf = ProjectFile::dummy();
+        let cwd = Path::new(".");
+        ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
+
+        let system_files = get_test_file();
+
+        let items = ctx
+            .completion(&system_files, TEST_STR, Position::new(61, 25))
+            .unwrap();
+        let child = items
+            .iter()
+            .find(|i| i.label == "EXAMPLE_CHILD_ENTRY")
+            .unwrap();
+        assert_eq!(child.detail.as_deref(), Some("Example Child Entry"));
+        assert_eq!(
+            child.documentation,
+            Some(Documentation::String(
+                "Astro Object: EXAMPLE_PLANET".to_string()
+            ))
+        );
+        assert!(items.iter().any(|i| i.label == "TH_VILLAGE"));
+
+        let items = ctx
+            .completion(&system_files, TEST_STR, Position::new(35, 25))
+            .unwrap();
+        let custom = items.iter().find(|i| i.label == "EXAMPLE_ENTRY").unwrap();
+        assert_eq!(custom.detail.as_deref(), Some("Custom curiosity"));
+        let vanilla = items.iter().find(|i| i.label == "QuantumMoon").unwrap();
+        assert_eq!(vanilla.detail.as_deref(), Some("Base-game curiosity"));
+
+        let items = ctx
+            .completion(&system_files, TEST_STR, Position::new(26, 30))
+            .unwrap();
+        assert!(items.iter().any(|i| i.label == "EXAMPLE_CHILD_RUMOR_FACT"));
+
+        assert!(ctx
+            .completion(&system_files, TEST_STR, Position::new(6, 16))
+            .is_none());
+    }
+
+    #[test]
+    fn test_rename() {
+        const TEST_STR: &str = include_str!("test_files/test_ship_log.xml");
+
+        let mut ctx = ShipLogContext::default();
 
+        let test_file = ShipLogFile::new(Url::parse("file://test_file.xml").unwrap(), 0);
         let pf = ProjectFile::dummy();
         let cwd = Path::new(".");
         ctx.parse(&test_file, &pf, cwd, TEST_STR).unwrap();
 
-        let errors = ctx.validate(&get_test_project());
+        assert_eq!(
+            ctx.prepare_rename(&test_file.uri, Position::new(5, 10)),
+            Some(Range::new(Position::new(5, 12), Position::new(5, 25)))
+        );
 
-        assert_eq!(errors.len(), 1);
-        assert_eq!(errors[0].1.message, "Unknown Entry: `GABAGOOL`");
+        let edit = ctx
+            .rename(&[], &test_file.uri, Position::new(61, 25), "RENAMED_ENTRY")
+            .unwrap();
+        let edits = &edit.changes.unwrap()[&test_file.uri];
+        // The declaration, three curiosities and one source
+        assert_eq!(edits.len(), 5);
+        assert!(edits.iter().all(|e| e.new_text == "RENAMED_ENTRY"));
+
+        assert!(ctx
+            .rename(
+                &[],
+                &test_file.uri,
+                Position::new(5, 10),
+                "EXAMPLE_CHILD_ENTRY"
+            )
+            .is_err());
+        assert!(ctx
+            .rename(&[], &test_file.uri, Position::new(5, 10), "TH_VILLAGE")
+            .is_err());
+        assert!(ctx
+            .rename(&[], &test_file.uri, Position::new(0, 0), "SOMETHING")
+            .is_err());
     }
 }
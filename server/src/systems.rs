@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Deserialize)]
 pub struct MVector2 {
@@ -12,8 +13,109 @@ pub struct EntryPos {
     pub position: MVector2,
 }
 
+fn default_alpha() -> u8 {
+    255
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    #[serde(default = "default_alpha")]
+    pub a: u8,
+}
+
+impl MColor {
+    /// Why `value` can't be used as a color, accepts hex codes and `{r, g, b, a}` objects
+    pub fn invalid_reason(value: &Value) -> Option<String> {
+        match value {
+            Value::String(hex) => {
+                let digits = hex.strip_prefix('#').unwrap_or(hex);
+                let valid = matches!(digits.len(), 6 | 8)
+                    && digits.chars().all(|c| c.is_ascii_hexdigit());
+                (!valid).then(|| format!("`{hex}` isn't a valid hex color (e.g. `#FF8000`)"))
+            }
+            Value::Object(map) => ["r", "g", "b", "a"].iter().find_map(|component| {
+                match map.get(*component) {
+                    None if *component == "a" => None,
+                    None => Some(format!("Color is missing component `{component}`")),
+                    Some(value) if value.as_u64().map(|v| v <= 255).unwrap_or(false) => None,
+                    Some(value) => Some(format!(
+                        "Color component `{component}` must be a whole number from 0 to 255, got `{value}`"
+                    )),
+                }
+            }),
+            _ => Some("Color must be a hex code or an object with `r`, `g`, `b` and `a`".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CuriosityModule {
+    pub id: String,
+    pub color: Option<MColor>,
+    pub highlight_color: Option<MColor>,
+}
+
+/// Used for curiosities that don't set a color, and entries that don't belong to a curiosity
+pub const DEFAULT_CURIOSITY_COLOR: MColor = MColor {
+    r: 255,
+    g: 255,
+    b: 255,
+    a: 255,
+};
+
+/// A curiosity with its color filled in
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Curiosity {
+    pub id: String,
+    pub color: MColor,
+}
+
+impl From<&CuriosityModule> for Curiosity {
+    fn from(module: &CuriosityModule) -> Self {
+        Self {
+            id: module.id.clone(),
+            color: module.color.clone().unwrap_or(DEFAULT_CURIOSITY_COLOR),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StarSystem {
     pub entry_positions: Option<Vec<EntryPos>>,
+    pub curiosities: Option<Vec<CuriosityModule>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_color_reason() {
+        for valid in [
+            r##""#FF8000""##,
+            r#""ff8000cc""#,
+            r#"{"r": 255, "g": 0, "b": 12}"#,
+            r#"{"r": 0, "g": 0, "b": 0, "a": 128}"#,
+        ] {
+            let value = serde_json::from_str::<Value>(valid).unwrap();
+            assert_eq!(MColor::invalid_reason(&value), None, "{valid}");
+        }
+        for invalid in [
+            r##""#FF80""##,
+            r##""#GG8000""##,
+            r#"{"r": 256, "g": 0, "b": 0}"#,
+            r#"{"r": 0.5, "g": 0, "b": 0}"#,
+            r#"{"r": 0, "g": 0}"#,
+            r#"{"r": 0, "g": 0, "b": 0, "a": -1}"#,
+            "12",
+        ] {
+            let value = serde_json::from_str::<Value>(invalid).unwrap();
+            assert!(MColor::invalid_reason(&value).is_some(), "{invalid}");
+        }
+    }
 }
@@ -1,7 +1,8 @@
 use json_position_parser::{tree::PathType, types::Range as JSONRange};
-use lsp_types::{Position as LSPPosition, Range as LSPRange};
-use roxmltree::TextPos;
+use lsp_types::{Position as LSPPosition, PositionEncodingKind, Range as LSPRange};
+use roxmltree::{Document, Node, TextPos};
 use serde_json::Value;
+use std::{borrow::Cow, collections::HashSet, sync::OnceLock};
 
 pub mod error_codes {
     use lsp_types::NumberOrString;
@@ -11,80 +12,477 @@ pub mod error_codes {
     pub const SHIPLOG_DUPLICATE_ID: &str = "nh.shiplog.duplicate_ids";
     pub const SHIPLOG_VANILLA_ID: &str = "nh.shiplog.vanilla_id";
     pub const SHIPLOG_MISSING_CURIOSITY: &str = "nh.shiplog.missing_curiosity";
+    pub const SHIPLOG_EMPTY_ID: &str = "nh.shiplog.empty_id";
+    pub const SHIPLOG_MISSING_PARENT: &str = "nh.shiplog.missing_parent";
+    pub const SHIPLOG_UNREACHABLE_ENTRY: &str = "nh.shiplog.unreachable_entry";
+    pub const SHIPLOG_CIRCULAR_REFERENCE: &str = "nh.shiplog.circular_reference";
+    pub const SHIPLOG_CURIOSITY_NOT_FLAGGED: &str = "nh.shiplog.curiosity_not_flagged";
+    pub const SHIPLOG_CURIOSITY_NOT_HUB: &str = "nh.shiplog.curiosity_not_hub";
     pub const SHIPLOG_MISSING_SOURCE_ID: &str = "nh.shiplog.invalid_source_id";
+    pub const SHIPLOG_XML_PARSE_ERROR: &str = "nh.shiplog.xml_parse_error";
+    pub const SHIPLOG_UNKNOWN_ASTRO_OBJECT: &str = "nh.shiplog.unknown_astro_object";
+    pub const SHIPLOG_SHARED_ASTRO_OBJECT: &str = "nh.shiplog.shared_astro_object";
+    pub const SHIPLOG_ENTRY_MISSING_POSITION: &str = "nh.shiplog.entry_missing_position";
+    pub const SHIPLOG_UNREVEALED_FACT: &str = "nh.shiplog.unrevealed_fact";
+    pub const SHIPLOG_UNUSED_CURIOSITY: &str = "nh.shiplog.unused_curiosity";
+    pub const SHIPLOG_RUMOR_MISSING_SOURCE: &str = "nh.shiplog.rumor_missing_source";
+    pub const SHIPLOG_EXPLORE_FACT_SOURCE_IGNORED: &str = "nh.shiplog.explore_fact_source_ignored";
+
+    pub const DIALOGUE_INVALID_FACT: &str = "nh.dialogue.invalid_fact";
+    pub const DIALOGUE_MISSING_TARGET: &str = "nh.dialogue.missing_target";
+    pub const DIALOGUE_DUPLICATE_NODE: &str = "nh.dialogue.duplicate_node";
+    pub const DIALOGUE_CONDITION_NEVER_SET: &str = "nh.dialogue.condition_never_set";
+    pub const DIALOGUE_CONDITION_UNUSED: &str = "nh.dialogue.condition_unused";
+
+    pub const TEXT_DUPLICATE_ID: &str = "nh.text.duplicate_id";
+    pub const TEXT_MISSING_PARENT: &str = "nh.text.missing_parent";
+    pub const TEXT_INVALID_FACT: &str = "nh.text.invalid_fact";
+
+    pub const SYSTEM_UNKNOWN_ENTRY_POSITION: &str = "nh.system.unknown_entry_position";
+    pub const SYSTEM_DUPLICATE_ENTRY_POSITION: &str = "nh.system.duplicate_entry_position";
+
+    pub const PLANET_INVALID_CONFIG: &str = "nh.planet.invalid_config";
+    pub const PLANET_MISSING_SHIP_LOG: &str = "nh.planet.missing_ship_log";
+    pub const PLANET_UNKNOWN_SYSTEM: &str = "nh.planet.unknown_system";
+    pub const PLANET_UNKNOWN_FACT: &str = "nh.planet.unknown_fact";
+
+    pub const MANIFEST_INVALID_CONFIG: &str = "nh.manifest.invalid_config";
+    pub const MANIFEST_MISSING_FIELD: &str = "nh.manifest.missing_field";
+    pub const MANIFEST_INVALID_UNIQUE_NAME: &str = "nh.manifest.invalid_unique_name";
+    pub const MANIFEST_INVALID_VERSION: &str = "nh.manifest.invalid_version";
+    pub const MANIFEST_MISSING_DEPENDENCY: &str = "nh.manifest.missing_dependency";
+
+    pub const JSON_SYNTAX_ERROR: &str = "nh.json.syntax_error";
 
     pub const CONFIG_FILE_PATH_NOT_FOUND: &str = "nh.config.file_path_invalid";
+    pub const CONFIG_INVALID_COLOR: &str = "nh.config.invalid_color";
+    pub const CONFIG_INVALID_ENUM_VALUE: &str = "nh.config.invalid_enum_value";
 
     pub fn get_error_code(code: &str) -> Option<NumberOrString> {
         Some(NumberOrString::String(code.to_string()))
     }
 }
 
-pub fn xml_range_to_diag_range(start_pos: TextPos, end_pos: TextPos) -> LSPRange {
+/// How columns in LSP positions are counted, negotiated with the client on initialize
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+}
+
+static POSITION_ENCODING: OnceLock<PositionEncoding> = OnceLock::new();
+
+impl PositionEncoding {
+    /// UTF-16 is the fallback every client has to support
+    pub fn negotiate(client_encodings: Option<&[PositionEncodingKind]>) -> Self {
+        if client_encodings
+            .unwrap_or_default()
+            .contains(&PositionEncodingKind::UTF8)
+        {
+            Self::Utf8
+        } else {
+            Self::Utf16
+        }
+    }
+
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+        }
+    }
+
+    fn char_len(self, c: char) -> usize {
+        match self {
+            Self::Utf8 => c.len_utf8(),
+            Self::Utf16 => c.len_utf16(),
+        }
+    }
+
+    fn column(self, line: &str) -> u32 {
+        line.chars().map(|c| self.char_len(c)).sum::<usize>() as u32
+    }
+
+    pub fn position_to_offset(self, text: &str, pos: LSPPosition) -> usize {
+        let mut line_start = 0;
+        for _ in 0..pos.line {
+            match text[line_start..].find('\n') {
+                Some(idx) => line_start += idx + 1,
+                None => return text.len(),
+            }
+        }
+        let mut offset = line_start;
+        let mut col = 0;
+        for c in text[line_start..].chars() {
+            if col >= pos.character as usize || c == '\n' {
+                break;
+            }
+            col += self.char_len(c);
+            offset += c.len_utf8();
+        }
+        offset
+    }
+
+    pub fn offset_to_position(self, text: &str, offset: usize) -> LSPPosition {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        LSPPosition::new(
+            before.matches('\n').count() as u32,
+            self.column(&before[line_start..]),
+        )
+    }
+}
+
+/// Set once the client tells us what it supports, UTF-16 until then
+pub fn set_position_encoding(encoding: PositionEncoding) {
+    if POSITION_ENCODING.set(encoding).is_err() {
+        eprintln!("Position encoding was already negotiated");
+    }
+}
+
+pub fn position_encoding() -> PositionEncoding {
+    POSITION_ENCODING.get().copied().unwrap_or_default()
+}
+
+/// roxmltree columns count chars, convert them to the negotiated encoding
+pub fn xml_pos_to_lsp_position(text: &str, pos: TextPos) -> LSPPosition {
+    let line = text
+        .split('\n')
+        .nth(pos.row.saturating_sub(1) as usize)
+        .unwrap_or_default();
+    let character = line
+        .char_indices()
+        .nth(pos.col.saturating_sub(1) as usize)
+        .map(|(idx, _)| &line[..idx])
+        .unwrap_or(line);
+    LSPPosition::new(
+        pos.row.saturating_sub(1),
+        position_encoding().column(character),
+    )
+}
+
+pub fn xml_range_to_diag_range(text: &str, start_pos: TextPos, end_pos: TextPos) -> LSPRange {
     LSPRange::new(
-        LSPPosition::new(start_pos.row - 1, start_pos.col - 1),
-        LSPPosition::new(end_pos.row - 1, end_pos.col - 1),
+        xml_pos_to_lsp_position(text, start_pos),
+        xml_pos_to_lsp_position(text, end_pos),
     )
 }
 
-pub fn json_pos_range_to_diag_range(range: JSONRange) -> LSPRange {
+pub fn xml_node_range(tree: &Document, node: &Node) -> LSPRange {
+    let text = tree.input_text();
+    LSPRange::new(
+        offset_to_lsp_position(text, node.range().start),
+        offset_to_lsp_position(text, node.range().end),
+    )
+}
+
+pub fn lsp_position_to_offset(text: &str, pos: LSPPosition) -> usize {
+    position_encoding().position_to_offset(text, pos)
+}
+
+pub fn offset_to_lsp_position(text: &str, offset: usize) -> LSPPosition {
+    position_encoding().offset_to_position(text, offset)
+}
+
+pub fn xml_element_at<'a, 'input>(
+    tree: &'a Document<'input>,
+    offset: usize,
+) -> Option<Node<'a, 'input>> {
+    tree.descendants()
+        .rfind(|n| n.is_element() && n.range().start < offset && offset <= n.range().end)
+}
+
+pub fn range_contains(range: &LSPRange, pos: LSPPosition) -> bool {
+    range.start <= pos && pos <= range.end
+}
+
+/// json_position_parser positions count chars, this turns one into a byte offset in `text`
+pub fn json_idx_to_offset(text: &str, idx: usize) -> usize {
+    text.char_indices()
+        .nth(idx)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len())
+}
+
+pub fn json_pos_range_to_diag_range(text: &str, range: JSONRange) -> LSPRange {
+    // Trees are usually parsed from the lenient text, which has the same byte offsets but
+    // blanks out comments byte by byte
+    let parsed = if text.is_ascii() {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(lenient_json(text))
+    };
     LSPRange::new(
-        LSPPosition::new(range.start.line as u32, range.start.char as u32),
-        LSPPosition::new(range.end.line as u32, range.end.char as u32),
+        offset_to_lsp_position(text, json_idx_to_offset(&parsed, range.start.idx)),
+        offset_to_lsp_position(text, json_idx_to_offset(&parsed, range.end.idx)),
     )
 }
 
-pub fn find_paths_with_x_prop(
+/// Range of the first value at a JSON path like `/ShipLog/xmlFile`, the root for an empty path
+pub fn json_value_range(text: &str, path: &str) -> Option<LSPRange> {
+    let tree = json_position_parser::parse_json(text).ok()?;
+    tree.value_at(&json_path_to_json_pos_path(path))
+        .first()
+        .map(|found| json_pos_range_to_diag_range(text, found.range))
+}
+
+/// Range of a JSON string value without its surrounding quotes
+pub fn json_pos_string_content_range(text: &str, range: JSONRange) -> LSPRange {
+    let mut range = json_pos_range_to_diag_range(text, range);
+    range.start.character += 1;
+    range.end.character = range.end.character.saturating_sub(1);
+    range
+}
+
+/// serde_json counts columns in bytes, the range covers the char the error is at
+pub fn serde_error_range(text: &str, why: &serde_json::Error) -> LSPRange {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(why.line().saturating_sub(1))
+        .map(|line| line.len())
+        .sum::<usize>();
+    let mut offset = (line_start + why.column().saturating_sub(1)).min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let end = text[offset..]
+        .chars()
+        .next()
+        .filter(|c| *c != '\n')
+        .map(|c| offset + c.len_utf8())
+        .unwrap_or(offset);
+    LSPRange::new(
+        offset_to_lsp_position(text, offset),
+        offset_to_lsp_position(text, end),
+    )
+}
+
+/// Blanks out comments and trailing commas, which New Horizons allows, so configs can go
+/// through serde. Offsets are kept the same so positions still line up with the original text
+pub fn lenient_json(text: &str) -> String {
+    let mut stripped = strip_json_comments(text).into_bytes();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut pending_comma = None;
+    for i in 0..stripped.len() {
+        let c = stripped[i];
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            b'"' => {
+                in_string = true;
+                pending_comma = None;
+            }
+            b',' => pending_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    stripped[comma] = b' ';
+                }
+            }
+            _ if c.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+    }
+    // Only ASCII bytes were swapped for other ASCII bytes
+    String::from_utf8(stripped).unwrap_or_default()
+}
+
+/// Blanks out `//` and `/* */` comments, offsets are kept the same
+fn strip_json_comments(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    let blank = |c: char| match c {
+        '\n' | '\r' => c.to_string(),
+        _ => " ".repeat(c.len_utf8()),
+    };
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            if c == '\\' {
+                stripped.extend(chars.next());
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                stripped.push_str("  ");
+                chars.next();
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    stripped.push_str(&blank(c));
+                }
+            }
+            ('/', Some('*')) => {
+                stripped.push_str("  ");
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        stripped.push_str("  ");
+                        break;
+                    }
+                    stripped.push_str(&blank(c));
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// The ID New Horizons gives a planet's astro object, based on its name
+pub fn normalize_astro_object_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '\'')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let cost = if a_char == *b_char { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn resolve_schema_ref<'a>(schema: &'a Value, reference: &str) -> Option<&'a Value> {
+    schema
+        .pointer(reference.trim_start_matches('#'))
+        .or_else(|| {
+            let name = reference.split('/').last()?;
+            ["definitions", "$defs"]
+                .iter()
+                .find_map(|key| schema.get(key)?.get(name))
+        })
+}
+
+/// Paths found while walking a schema, see `index_schema`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SchemaIndex {
+    /// Strings with the x-prop flag set
+    pub x_prop_paths: Vec<String>,
+    /// Strings that have to be one of an `enum`'s values, along with those values
+    pub enum_paths: Vec<(String, Vec<String>)>,
+}
+
+pub fn index_schema(x_prop: &str, path: &str, schema: &Value, node: &Value) -> SchemaIndex {
+    let mut index = SchemaIndex::default();
+    index_schema_inner(x_prop, path, schema, node, &mut vec![], &mut index);
+    // Combinators can list the same property more than once
+    let mut seen = HashSet::new();
+    index.x_prop_paths.retain(|p| seen.insert(p.clone()));
+    let mut seen = HashSet::new();
+    index.enum_paths.retain(|(p, _)| seen.insert(p.clone()));
+    index
+}
+
+/// `visiting` holds the refs expanded above this node, so recursive definitions are only
+/// followed once
+fn index_schema_inner<'a>(
     x_prop: &str,
     path: &str,
-    schema: &Value,
-    node: &Value,
-) -> Vec<String> {
-    let mut paths: Vec<String> = vec![];
+    schema: &'a Value,
+    node: &'a Value,
+    visiting: &mut Vec<&'a str>,
+    index: &mut SchemaIndex,
+) {
     let mut node = node;
-    if let Some(schema_ref) = node.get("$ref") {
-        let target = schema_ref.as_str().map(|s| s.split('/').last());
-        if let Some(Some(target)) = target {
-            if let Some(Some(new_node)) = schema.get("definitions").map(|d| d.get(target)) {
-                node = new_node;
+    let depth = visiting.len();
+    while let Some(schema_ref) = node.get("$ref").and_then(|r| r.as_str()) {
+        if visiting.contains(&schema_ref) {
+            visiting.truncate(depth);
+            return;
+        }
+        let Some(new_node) = resolve_schema_ref(schema, schema_ref) else {
+            break;
+        };
+        visiting.push(schema_ref);
+        node = new_node;
+    }
+    for combinator in ["allOf", "anyOf", "oneOf"] {
+        if let Some(Some(subschemas)) = node.get(combinator).map(|c| c.as_array()) {
+            for subschema in subschemas {
+                index_schema_inner(x_prop, path, schema, subschema, visiting, index);
             }
         }
     }
-    if let Some(Some(node_type)) = node.get("type").map(|t| t.as_str()) {
+    // Only string enums are checked, numeric ones are left to the game
+    if let Some(values) = node.get("enum").and_then(|e| e.as_array()) {
+        let values = values
+            .iter()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect::<Vec<_>>();
+        if !values.is_empty() {
+            index.enum_paths.push((path.to_string(), values));
+        }
+    }
+    // `type` can also be a list, e.g. `["string", "null"]`
+    let node_types = match node.get("type") {
+        Some(Value::String(node_type)) => vec![node_type.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+        _ => vec![],
+    };
+    for node_type in node_types {
         match node_type {
             "string" => {
                 if let Some(Some(flag)) = node.get(x_prop).map(|x| x.as_bool()) {
                     if flag {
-                        paths.push(path.to_string())
+                        index.x_prop_paths.push(path.to_string())
                     }
                 }
             }
             "object" => {
                 if let Some(Some(props)) = node.get("properties").map(|p| p.as_object()) {
                     for (name, prop) in props {
-                        paths.extend(find_paths_with_x_prop(
+                        index_schema_inner(
                             x_prop,
                             &format!("{path}/{name}"),
                             schema,
                             prop,
-                        ))
+                            visiting,
+                            index,
+                        );
                     }
                 }
             }
             "array" => {
                 if let Some(items) = node.get("items") {
-                    paths.extend(find_paths_with_x_prop(
+                    index_schema_inner(
                         x_prop,
                         &format!("{path}/*"),
                         schema,
                         items,
-                    ));
+                        visiting,
+                        index,
+                    );
                 }
             }
             _ => {}
         }
     }
-    paths
+    visiting.truncate(depth);
 }
 
 pub fn json_path_to_json_pos_path(path: &str) -> Vec<PathType<'_>> {
@@ -101,3 +499,250 @@ pub fn json_path_to_json_pos_path(path: &str) -> Vec<PathType<'_>> {
         .collect();
     output_path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_astro_object_name() {
+        assert_eq!(normalize_astro_object_name("Wetrock"), "WETROCK");
+        assert_eq!(normalize_astro_object_name("Hearth's Moon"), "HEARTHSMOON");
+        assert_eq!(normalize_astro_object_name(" Ember  Twin "), "EMBERTWIN");
+    }
+
+    #[test]
+    fn test_find_paths_through_ref_chains() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "ShipLog": { "$ref": "#/definitions/ShipLogRef" },
+                "children": { "type": "array", "items": { "$ref": "#/$defs/Node" } }
+            },
+            "definitions": {
+                "ShipLogRef": { "$ref": "#/$defs/ShipLogModule" }
+            },
+            "$defs": {
+                "ShipLogModule": {
+                    "type": "object",
+                    "properties": {
+                        "xmlFile": { "type": "string", "x-file-path": true }
+                    }
+                },
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "texture": { "type": "string", "x-file-path": true },
+                        "child": { "$ref": "#/$defs/Node" },
+                        "loop": { "$ref": "#/$defs/Loop" }
+                    }
+                },
+                "Loop": { "$ref": "#/$defs/Loop" }
+            }
+        });
+        let mut paths = index_schema("x-file-path", "", &schema, &schema).x_prop_paths;
+        paths.sort();
+        assert_eq!(paths, vec!["/ShipLog/xmlFile", "/children/*/texture"]);
+    }
+
+    #[test]
+    fn test_find_paths_in_combinators() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "sprite": { "type": ["string", "null"], "x-file-path": true },
+                "props": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Props" },
+                        { "type": "object", "properties": {
+                            "audio": { "type": "string", "x-file-path": true }
+                        } }
+                    ]
+                },
+                "model": {
+                    "oneOf": [
+                        { "type": "string", "x-file-path": true },
+                        { "anyOf": [{ "type": "string", "x-file-path": true }] }
+                    ]
+                }
+            },
+            "definitions": {
+                "Props": {
+                    "type": "object",
+                    "properties": {
+                        "xmlFile": { "type": "string", "x-file-path": true }
+                    }
+                }
+            }
+        });
+        let mut paths = index_schema("x-file-path", "", &schema, &schema).x_prop_paths;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["/model", "/props/audio", "/props/xmlFile", "/sprite"]
+        );
+    }
+
+    #[test]
+    fn test_index_schema_enums() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "Base": { "$ref": "#/definitions/BaseModule" },
+                "axes": { "type": "array", "items": { "$ref": "#/definitions/Axis" } },
+                "quality": { "type": "integer", "enum": [0, 1, 2] }
+            },
+            "definitions": {
+                "BaseModule": {
+                    "type": "object",
+                    "properties": {
+                        "fallOff": { "$ref": "#/definitions/GravityFallOff" },
+                        "map": { "type": "string", "x-file-path": true }
+                    }
+                },
+                "GravityFallOff": {
+                    "type": "string",
+                    "enum": ["linear", "inverseSquared"]
+                },
+                "Axis": { "oneOf": [{ "$ref": "#/definitions/AxisName" }, { "type": "null" }] },
+                "AxisName": { "type": "string", "enum": ["alignX", "alignY", "alignZ"] }
+            }
+        });
+        let index = index_schema("x-file-path", "", &schema, &schema);
+        assert_eq!(index.x_prop_paths, vec!["/Base/map"]);
+        let mut enum_paths = index.enum_paths;
+        enum_paths.sort();
+        assert_eq!(
+            enum_paths,
+            vec![
+                (
+                    "/Base/fallOff".to_string(),
+                    vec!["linear".to_string(), "inverseSquared".to_string()]
+                ),
+                (
+                    "/axes/*".to_string(),
+                    vec![
+                        "alignX".to_string(),
+                        "alignY".to_string(),
+                        "alignZ".to_string()
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xml_positions_utf16() {
+        let text = "<Entry>\n    <Name>Caf\u{e9} \u{1F680}</Name><ID>FOO</ID>\n</Entry>";
+        let tree = Document::parse(text).unwrap();
+        let id = tree
+            .descendants()
+            .find(|n| n.tag_name().name() == "ID")
+            .unwrap();
+        // The rocket takes up two UTF-16 code units
+        let expected = LSPRange::new(LSPPosition::new(1, 24), LSPPosition::new(1, 36));
+        assert_eq!(xml_node_range(&tree, &id), expected);
+        assert_eq!(
+            xml_range_to_diag_range(
+                text,
+                tree.text_pos_at(id.range().start),
+                tree.text_pos_at(id.range().end)
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_position_encodings() {
+        let text = "a\n\u{e9}\u{1F680}b";
+        assert_eq!(
+            PositionEncoding::Utf16.offset_to_position(text, 8),
+            LSPPosition::new(1, 3)
+        );
+        assert_eq!(
+            PositionEncoding::Utf8.offset_to_position(text, 8),
+            LSPPosition::new(1, 6)
+        );
+        assert_eq!(
+            PositionEncoding::Utf16.position_to_offset(text, LSPPosition::new(1, 3)),
+            8
+        );
+        assert_eq!(
+            PositionEncoding::Utf8.position_to_offset(text, LSPPosition::new(1, 6)),
+            8
+        );
+    }
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        assert_eq!(PositionEncoding::negotiate(None), PositionEncoding::Utf16);
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&[PositionEncodingKind::UTF16])),
+            PositionEncoding::Utf16
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&[
+                PositionEncodingKind::UTF32,
+                PositionEncodingKind::UTF8
+            ])),
+            PositionEncoding::Utf8
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("WETROCK", "WETROCK"), 0);
+        assert_eq!(edit_distance("WETROK", "WETROCK"), 1);
+        assert_eq!(edit_distance("", "ABC"), 3);
+        assert_eq!(edit_distance("KITTEN", "SITTING"), 3);
+    }
+
+    #[test]
+    fn test_lenient_json() {
+        let text = "{\n  \"a\": [1, 2, ],\n  \"b\": \"x,]\", // Trailing\n}";
+        let lenient = lenient_json(text);
+        assert_eq!(lenient.len(), text.len());
+        let value = serde_json::from_str::<Value>(&lenient).unwrap();
+        assert_eq!(value["a"], serde_json::json!([1, 2]));
+        assert_eq!(value["b"], "x,]");
+        // Syntax errors are still reported where they are in the original
+        let why =
+            serde_json::from_str::<Value>(&lenient_json("{\n  // Hi\n  \"a\": }")).unwrap_err();
+        assert_eq!((why.line(), why.column()), (3, 8));
+    }
+
+    #[test]
+    fn test_json_ranges_non_ascii() {
+        let range = |start: u32, end: u32| {
+            LSPRange::new(LSPPosition::new(0, start), LSPPosition::new(0, end))
+        };
+        assert_eq!(
+            json_value_range(r#"{"name": "Ñ🚀", "x": 1}"#, "/x"),
+            Some(range(21, 22))
+        );
+
+        let text = r#"{ /* Ñ */ "x": 1 }"#;
+        let tree = json_position_parser::parse_json(&lenient_json(text)).unwrap();
+        let found = tree.value_at(&json_path_to_json_pos_path("/x"))[0].clone();
+        assert_eq!(
+            json_pos_range_to_diag_range(text, found.range),
+            range(15, 16)
+        );
+
+        let text = r#"{"name": "Ñ🚀", "x": }"#;
+        let why = serde_json::from_str::<Value>(text).unwrap_err();
+        assert_eq!(serde_error_range(text, &why), range(21, 22));
+    }
+
+    #[test]
+    fn test_strip_json_comments() {
+        let text = "{\n  // A comment\n  \"url\": \"http://a/*b*/\", /* inline */ \"a\": 1\n}";
+        let stripped = strip_json_comments(text);
+        assert_eq!(stripped.len(), text.len());
+        assert_eq!(stripped.lines().count(), text.lines().count());
+        let value = serde_json::from_str::<Value>(&stripped).unwrap();
+        assert_eq!(value["url"], "http://a/*b*/");
+        assert_eq!(value["a"], 1);
+        assert_eq!(stripped.find("\"a\""), text.find("\"a\""));
+    }
+}
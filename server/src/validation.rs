@@ -1,16 +1,175 @@
-use std::time::Instant;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use lsp_server::{Connection, Message, Notification};
+use crossbeam_channel::Sender;
+use lsp_server::{Message, Notification};
 use lsp_types::{
     notification::{Notification as INotification, PublishDiagnostics},
-    Diagnostic, PublishDiagnosticsParams, Url, VersionedTextDocumentIdentifier,
+    Diagnostic, DiagnosticSeverity, NumberOrString, PublishDiagnosticsParams, Url,
+    VersionedTextDocumentIdentifier,
 };
+use serde::Deserialize;
+use serde_json::Value;
 
-use crate::{file_paths::FilePathValidator, project::Project, ship_log::ShipLogValidator};
+use crate::{
+    dialogue::DialogueValidator,
+    file_paths::{FilePathValidator, SchemaFetch, SchemaSource},
+    json_syntax::JsonSyntaxValidator,
+    manifest::ManifestValidator,
+    nomai_text::NomaiTextValidator,
+    planets::PlanetValidator,
+    project::Project,
+    requests::{ProjectStats, ProjectStatsNotification},
+    reveals::RevealValidator,
+    ship_log::ShipLogValidator,
+};
 
 pub type ErrorSet = Vec<(VersionedTextDocumentIdentifier, Diagnostic)>;
 
-pub trait Validator {
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ValidationOptions {
+    /// Report entries that nothing references, some mods have standalone entries on purpose
+    pub lint_unreachable_entries: bool,
+    pub body_schema_url: Option<String>,
+    pub system_schema_url: Option<String>,
+    pub addon_manifest_schema_url: Option<String>,
+    pub rules: ValidationConfig,
+    /// How long to wait after an edit before validating, in milliseconds
+    pub debounce_ms: u64,
+    /// Globs relative to the project root to leave out of discovery, e.g. `build/**`
+    pub exclude_globs: Vec<String>,
+    /// Also exclude whatever the project's `.gitignore` lists
+    pub use_gitignore: bool,
+    /// Other mods' folders to resolve entries, facts and curiosities from
+    pub external_projects: Vec<PathBuf>,
+    /// Whether edits are validated as they're typed or once they're saved
+    pub validate_on: ValidateOn,
+    /// Names of the only validators to run, all of them if unset
+    pub enabled_validators: Option<Vec<String>>,
+    pub disabled_validators: Vec<String>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            lint_unreachable_entries: true,
+            body_schema_url: None,
+            system_schema_url: None,
+            addon_manifest_schema_url: None,
+            rules: ValidationConfig::default(),
+            debounce_ms: 300,
+            exclude_globs: vec![],
+            use_gitignore: true,
+            external_projects: vec![],
+            validate_on: ValidateOn::Change,
+            enabled_validators: None,
+            disabled_validators: vec![],
+        }
+    }
+}
+
+impl ValidationOptions {
+    pub fn is_validator_enabled(&self, name: &str) -> bool {
+        let enabled = self
+            .enabled_validators
+            .as_ref()
+            .map(|names| names.iter().any(|n| n == name))
+            .unwrap_or(true);
+        enabled && !self.disabled_validators.iter().any(|n| n == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidateOn {
+    Change,
+    Save,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    Off,
+    Hint,
+    #[serde(alias = "information")]
+    Info,
+    Warning,
+    Error,
+}
+
+/// Per error code overrides, e.g. `{ "nh.shiplog.vanilla_id": "off" }`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "HashMap<String, Value>")]
+pub struct ValidationConfig(pub HashMap<String, RuleSeverity>);
+
+impl From<HashMap<String, Value>> for ValidationConfig {
+    // A typo in one rule shouldn't throw out the rest of the options
+    fn from(rules: HashMap<String, Value>) -> Self {
+        Self(
+            rules
+                .into_iter()
+                .filter_map(|(code, severity)| {
+                    match serde_json::from_value::<RuleSeverity>(severity) {
+                        Ok(severity) => Some((code, severity)),
+                        Err(why) => {
+                            eprintln!("Ignoring severity for {code}: {why}");
+                            None
+                        }
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+impl ValidationConfig {
+    fn severity_for(&self, diagnostic: &Diagnostic) -> Option<RuleSeverity> {
+        match &diagnostic.code {
+            Some(NumberOrString::String(code)) => self.0.get(code).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, errors: ErrorSet) -> ErrorSet {
+        errors
+            .into_iter()
+            .filter_map(|(file, mut diagnostic)| {
+                match self.severity_for(&diagnostic) {
+                    Some(RuleSeverity::Off) => return None,
+                    Some(RuleSeverity::Hint) => {
+                        diagnostic.severity = Some(DiagnosticSeverity::HINT)
+                    }
+                    Some(RuleSeverity::Info) => {
+                        diagnostic.severity = Some(DiagnosticSeverity::INFORMATION)
+                    }
+                    Some(RuleSeverity::Warning) => {
+                        diagnostic.severity = Some(DiagnosticSeverity::WARNING)
+                    }
+                    Some(RuleSeverity::Error) => {
+                        diagnostic.severity = Some(DiagnosticSeverity::ERROR)
+                    }
+                    None => {}
+                }
+                Some((file, diagnostic))
+            })
+            .collect()
+    }
+}
+
+pub trait Validator: Send + Sync {
+    /// Used to turn the validator on or off in `ValidationOptions`, don't change these
+    fn name() -> &'static str
+    where
+        Self: Sized;
     fn prepare() -> Self
     where
         Self: Sized;
@@ -21,70 +180,214 @@ pub trait Validator {
 #[derive(Default)]
 pub struct MainValidator {
     pub validators: Vec<Box<dyn Validator>>,
+    pub config: ValidationConfig,
+    /// What each validator found the last time it ran, by its index in `validators`.
+    /// Validators that don't need to re-run after a change keep reporting these
+    last_errors: Vec<(usize, ErrorSet)>,
+    /// Files that diagnostics were last published for, so they can be cleared once fixed
+    published: Vec<VersionedTextDocumentIdentifier>,
+    /// Files in the last validated snapshot, to tell deleted files apart from unrelated ones
+    known_files: HashSet<Url>,
+    /// How many files have diagnostics, shared with whatever answers `getProjectStats`
+    pub files_with_diagnostics: Arc<AtomicUsize>,
 }
 
 impl MainValidator {
-    pub fn new() -> Self {
-        Self {
-            validators: vec![
-                Box::new(ShipLogValidator::prepare()),
-                Box::new(FilePathValidator::prepare()),
-            ],
+    pub fn new(options: &ValidationOptions, schema_fetch: SchemaFetch) -> Self {
+        let mut validator = Self {
+            config: options.rules.clone(),
+            ..Default::default()
+        };
+        // JSON syntax errors go first, later validators skip files that don't parse
+        validator.add(options, JsonSyntaxValidator::prepare);
+        validator.add(options, || ShipLogValidator {
+            lint_unreachable_entries: options.lint_unreachable_entries,
+        });
+        // Only built when enabled, it downloads the schemas
+        validator.add(options, || {
+            FilePathValidator::new(options, SchemaSource::default(), schema_fetch)
+        });
+        validator.add(options, DialogueValidator::prepare);
+        validator.add(options, PlanetValidator::prepare);
+        validator.add(options, NomaiTextValidator::prepare);
+        validator.add(options, RevealValidator::prepare);
+        validator.add(options, ManifestValidator::prepare);
+        validator
+    }
+
+    /// Swaps in validators built from new options, keeping track of what was published so
+    /// files nothing reports anymore get cleared
+    fn reconfigure(&mut self, options: &ValidationOptions, schema_fetch: SchemaFetch) {
+        *self = Self {
+            published: std::mem::take(&mut self.published),
+            known_files: std::mem::take(&mut self.known_files),
+            files_with_diagnostics: self.files_with_diagnostics.clone(),
+            ..Self::new(options, schema_fetch)
+        };
+    }
+
+    fn add<V: Validator + 'static>(
+        &mut self,
+        options: &ValidationOptions,
+        make: impl FnOnce() -> V,
+    ) {
+        if options.is_validator_enabled(V::name()) {
+            self.validators.push(Box::new(make()));
+        } else {
+            eprintln!("Validator {} is disabled", V::name());
         }
     }
 
-    fn internal_emit(connection: &Connection, current_buffer: &ErrorSet) {
+    fn publish(
+        sender: &Sender<Message>,
+        uri: Url,
+        version: Option<i32>,
+        diagnostics: Vec<Diagnostic>,
+    ) {
         let params = PublishDiagnosticsParams {
-            uri: current_buffer.last().unwrap().0.uri.clone(),
-            diagnostics: current_buffer.iter().map(|e| e.1.clone()).collect(),
-            version: Some(current_buffer.last().unwrap().0.version),
-        };
-        let res = connection.sender.send(Message::Notification(Notification {
-            method: PublishDiagnostics::METHOD.to_string(),
-            params: serde_json::to_value(params).unwrap(),
-        }));
+            uri,
+            version,
+            diagnostics,
+        };
+        let res = sender.send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )));
         if let Err(why) = res {
             eprintln!("Error emitting diagnostics: {why:?}");
         }
     }
 
-    fn emit_diagnostics(&self, connection: &Connection, mut errors: ErrorSet) {
-        let mut current_buffer: ErrorSet = vec![];
-        let mut last_uri: Option<Url> = None;
-        errors.sort_unstable_by_key(|e| e.0.uri.clone());
-        for error in errors.into_iter() {
-            if last_uri.map(|u| u == error.0.uri).unwrap_or(true) {
-                current_buffer.push(error.clone());
-            } else {
-                Self::internal_emit(connection, &current_buffer);
-                current_buffer.clear();
-                current_buffer.push(error.clone());
-            }
-            last_uri = Some(error.0.uri.clone());
-        }
-        if !current_buffer.is_empty() {
-            Self::internal_emit(connection, &current_buffer);
+    /// Diagnostics for each file, along with the newest version they were reported for
+    pub fn group_diagnostics(errors: ErrorSet) -> HashMap<Url, (i32, Vec<Diagnostic>)> {
+        let mut grouped: HashMap<Url, (i32, Vec<Diagnostic>)> = HashMap::new();
+        for (id, diagnostic) in errors.into_iter() {
+            let (version, diagnostics) = grouped.entry(id.uri).or_insert((id.version, vec![]));
+            *version = (*version).max(id.version);
+            diagnostics.push(diagnostic);
         }
+        grouped
     }
 
-    pub fn force_validate(&self, connection: &Connection, project: &mut Project) {
-        let now = Instant::now();
+    fn emit_diagnostics(&self, sender: &Sender<Message>, errors: ErrorSet) {
+        let mut grouped = Self::group_diagnostics(errors)
+            .into_iter()
+            .collect::<Vec<_>>();
+        grouped.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        for (uri, (version, diagnostics)) in grouped.into_iter() {
+            Self::publish(sender, uri, Some(version), diagnostics);
+        }
+    }
 
+    /// Runs every validator without emitting anything
+    pub fn validate_all(&self, project: &Project) -> ErrorSet {
         let mut errors: ErrorSet = vec![];
         for validator in &self.validators {
             errors.extend(validator.validate(project).into_iter());
         }
+        self.finish(project, errors)
+    }
+
+    /// Applies rule overrides and drops anything reported for excluded files
+    fn finish(&self, project: &Project, errors: ErrorSet) -> ErrorSet {
+        let errors = errors
+            .into_iter()
+            .filter(|(id, _)| !project.is_excluded(&id.uri))
+            .collect();
+        self.config.apply(errors)
+    }
 
-        let len = errors.len();
+    /// Files with errors, with the version they currently have in the project
+    fn files_with_errors(
+        project: &Project,
+        errors: &ErrorSet,
+    ) -> Vec<VersionedTextDocumentIdentifier> {
+        let mut files: Vec<VersionedTextDocumentIdentifier> = vec![];
+        for (id, _) in errors.iter() {
+            if !files.iter().any(|f| f.uri == id.uri) {
+                let current = project.iter_all().find(|f| f.id.uri == id.uri);
+                files.push(current.map(|f| f.id.clone()).unwrap_or_else(|| id.clone()));
+            }
+        }
+        files
+    }
 
-        project.files_with_diagnostics = errors
+    /// Publishes an empty list for any of `candidates` that has no diagnostics anymore
+    fn clear_stale_diagnostics<'a>(
+        &self,
+        sender: &Sender<Message>,
+        project: &Project,
+        candidates: impl Iterator<Item = &'a Url>,
+    ) {
+        let mut cleared: Vec<&Url> = vec![];
+        for uri in candidates {
+            if cleared.contains(&uri) || self.published.iter().any(|f| &f.uri == uri) {
+                continue;
+            }
+            // Files that left the project (e.g. deleted from disk) don't have a version anymore
+            let version = project
+                .iter_all()
+                .find(|f| &f.id.uri == uri)
+                .map(|f| f.id.version);
+            Self::publish(sender, uri.clone(), version, vec![]);
+            cleared.push(uri);
+        }
+    }
+
+    /// Every cached error, with rule overrides applied
+    fn merged_errors(&self, project: &Project) -> ErrorSet {
+        let errors = self
+            .last_errors
             .iter()
-            .map(|e| e.0.clone())
-            .collect::<Vec<VersionedTextDocumentIdentifier>>();
+            .flat_map(|(_, errors)| errors.iter().cloned())
+            .collect();
+        self.finish(project, errors)
+    }
 
-        project.files_with_diagnostics.dedup();
+    /// Publishes the merged errors, clearing files in `candidates` or previously published
+    /// that don't have any anymore
+    fn publish_merged(&mut self, sender: &Sender<Message>, project: &Project, candidates: &[Url]) {
+        let errors = self.merged_errors(project);
+        let previous = std::mem::replace(
+            &mut self.published,
+            Self::files_with_errors(project, &errors),
+        );
+        self.clear_stale_diagnostics(
+            sender,
+            project,
+            previous.iter().map(|f| &f.uri).chain(candidates.iter()),
+        );
+        self.emit_diagnostics(sender, errors);
+        self.files_with_diagnostics
+            .store(self.published.len(), Ordering::Relaxed);
+        Self::send_stats(sender, project, self.published.len());
+    }
+
+    fn send_stats(sender: &Sender<Message>, project: &Project, files_with_diagnostics: usize) {
+        let res = sender.send(Message::Notification(Notification::new(
+            ProjectStatsNotification::METHOD.to_string(),
+            ProjectStats::new(project, files_with_diagnostics),
+        )));
+        if let Err(why) = res {
+            eprintln!("Error sending project stats: {why:?}");
+        }
+    }
 
-        self.emit_diagnostics(connection, errors);
+    pub fn force_validate(&mut self, sender: &Sender<Message>, project: &Project) {
+        let now = Instant::now();
+
+        self.last_errors = self
+            .validators
+            .iter()
+            .enumerate()
+            .map(|(i, validator)| (i, validator.validate(project)))
+            .collect();
+        self.known_files = project.iter_all().map(|f| f.id.uri.clone()).collect();
+
+        let len = self.merged_errors(project).len();
+
+        // Files that no longer have any errors (e.g. after a rule was turned off) need clearing
+        self.publish_merged(sender, project, &[]);
 
         eprintln!(
             "Finished validation, found {} errors in {:?}",
@@ -94,55 +397,758 @@ impl MainValidator {
     }
 
     pub fn on_change(
-        &self,
-        connection: &Connection,
+        &mut self,
+        sender: &Sender<Message>,
         changed_paths: Vec<Url>,
-        project: &mut Project,
+        project: &Project,
     ) {
-        let mut errors: ErrorSet = vec![];
-        for validator in self
-            .validators
+        // Deleted files are already gone from the project, so validators can't tell they used them.
+        // Anything that never was in the project (e.g. a file in `target/`) doesn't matter
+        let current_files = project
+            .iter_all()
+            .map(|f| f.id.uri.clone())
+            .collect::<HashSet<_>>();
+        let removed = changed_paths
             .iter()
-            .filter(|v| v.should_invalidate(&changed_paths, project))
-        {
-            errors.extend(validator.validate(project).into_iter());
+            .any(|uri| self.known_files.contains(uri) && !current_files.contains(uri));
+        for (i, validator) in self.validators.iter().enumerate() {
+            let reported_changed = self
+                .last_errors
+                .iter()
+                .find(|(index, _)| *index == i)
+                .is_some_and(|(_, errors)| {
+                    errors.iter().any(|(id, _)| changed_paths.contains(&id.uri))
+                });
+            if !removed
+                && !reported_changed
+                && !validator.should_invalidate(&changed_paths, project)
+            {
+                continue;
+            }
+            let errors = validator.validate(project);
+            match self.last_errors.iter_mut().find(|(index, _)| *index == i) {
+                Some((_, last)) => *last = errors,
+                None => self.last_errors.push((i, errors)),
+            }
+        }
+
+        for (_, errors) in self.last_errors.iter_mut() {
+            errors.retain(|(id, _)| current_files.contains(&id.uri));
+        }
+        self.known_files = current_files;
+
+        // Anything that had diagnostics before or was just changed may have been fixed
+        self.publish_merged(sender, project, &changed_paths);
+    }
+}
+
+pub enum ValidationJob {
+    Full(Project),
+    Changed(Project, Vec<Url>),
+    /// Rebuilds the validators, the next run validates everything
+    Configure(ValidationOptions),
+    /// Validates the last snapshot again, e.g. once the latest schemas are in
+    Revalidate,
+}
+
+/// Validates snapshots of the project on another thread so requests can be answered meanwhile
+pub struct ValidationWorker {
+    jobs: mpsc::Sender<ValidationJob>,
+    pub debounce: Duration,
+    pending_changes: Vec<Url>,
+    pub validate_on: ValidateOn,
+    /// Files edited since they were last saved, only used when validating on save
+    unsaved_changes: Vec<Url>,
+    pub files_with_diagnostics: Arc<AtomicUsize>,
+}
+
+impl ValidationWorker {
+    pub fn spawn(
+        debounce: Duration,
+        sender: Sender<Message>,
+        make_validator: impl FnOnce(Box<dyn FnOnce() + Send>) -> MainValidator + Send + 'static,
+    ) -> Self {
+        let (jobs, receiver) = mpsc::channel();
+        let worker_jobs = jobs.clone();
+        let files_with_diagnostics = Arc::new(AtomicUsize::new(0));
+        let shared = files_with_diagnostics.clone();
+        thread::spawn(move || {
+            let validator = MainValidator {
+                files_with_diagnostics: shared,
+                ..make_validator(Self::revalidate_callback(&worker_jobs))
+            };
+            Self::run(validator, &sender, &receiver, &worker_jobs)
+        });
+        Self {
+            jobs,
+            debounce,
+            pending_changes: vec![],
+            validate_on: ValidateOn::Change,
+            unsaved_changes: vec![],
+            files_with_diagnostics,
         }
+    }
+
+    fn revalidate_callback(jobs: &mpsc::Sender<ValidationJob>) -> Box<dyn FnOnce() + Send> {
+        let jobs = jobs.clone();
+        Box::new(move || {
+            let _ = jobs.send(ValidationJob::Revalidate);
+        })
+    }
 
-        eprintln!("Validate: {:?}", errors);
-
-        let mut uris_with_diagnostics =
-            errors.iter().map(|e| e.0.uri.clone()).collect::<Vec<Url>>();
-
-        uris_with_diagnostics.sort();
-        uris_with_diagnostics.dedup();
-
-        self.emit_diagnostics(connection, errors);
-
-        for file in project.iter_all() {
-            if !uris_with_diagnostics.contains(&file.id.uri) {
-                let params = PublishDiagnosticsParams {
-                    uri: file.id.uri.clone(),
-                    version: project
-                        .files_with_diagnostics
-                        .iter()
-                        .find(|f| f.uri == file.id.uri)
-                        .map(|f| f.version),
-                    diagnostics: vec![],
-                };
-                let res = connection
-                    .sender
-                    .send(Message::Notification(Notification::new(
-                        PublishDiagnostics::METHOD.to_string(),
-                        params,
-                    )));
-                if let Err(why) = res {
-                    eprintln!("Error emitting diagnostics: {why:?}");
+    fn run(
+        mut validator: MainValidator,
+        sender: &Sender<Message>,
+        jobs: &mpsc::Receiver<ValidationJob>,
+        own_jobs: &mpsc::Sender<ValidationJob>,
+    ) {
+        let mut last_project = None;
+        while let Ok(job) = jobs.recv() {
+            // Anything queued up while the last run was going is superseded by the newest snapshot
+            let mut latest = None;
+            let mut full = false;
+            let mut changed_paths: Vec<Url> = vec![];
+            for job in std::iter::once(job).chain(jobs.try_iter()) {
+                match job {
+                    ValidationJob::Full(project) => {
+                        full = true;
+                        latest = Some(project);
+                    }
+                    ValidationJob::Changed(project, paths) => {
+                        for path in paths {
+                            if !changed_paths.contains(&path) {
+                                changed_paths.push(path);
+                            }
+                        }
+                        latest = Some(project);
+                    }
+                    ValidationJob::Configure(options) => {
+                        let fetch = SchemaFetch::Background(Self::revalidate_callback(own_jobs));
+                        validator.reconfigure(&options, fetch);
+                        full = true;
+                    }
+                    ValidationJob::Revalidate => full = true,
                 }
             }
+            let Some(project) = latest.or(last_project.take()) else {
+                continue;
+            };
+            if full {
+                validator.force_validate(sender, &project);
+            } else {
+                validator.on_change(sender, changed_paths, &project);
+            }
+            last_project = Some(project);
+        }
+    }
+
+    fn send(&self, job: ValidationJob) {
+        if let Err(why) = self.jobs.send(job) {
+            eprintln!("Validation worker stopped: {why:?}");
+        }
+    }
+
+    pub fn configure(&mut self, options: ValidationOptions) {
+        self.debounce = Duration::from_millis(options.debounce_ms);
+        self.validate_on = options.validate_on;
+        self.send(ValidationJob::Configure(options));
+    }
+
+    pub fn force_validate(&mut self, project: &Project) {
+        self.pending_changes.clear();
+        self.unsaved_changes.clear();
+        self.send(ValidationJob::Full(project.snapshot()));
+    }
+
+    pub fn on_change(&mut self, mut changed_paths: Vec<Url>, project: &Project) {
+        for url in self.pending_changes.drain(..) {
+            if !changed_paths.contains(&url) {
+                changed_paths.push(url);
+            }
+        }
+        self.send(ValidationJob::Changed(project.snapshot(), changed_paths));
+    }
+
+    pub fn has_pending_changes(&self) -> bool {
+        !self.pending_changes.is_empty()
+    }
+
+    /// Validates after the debounce interval instead of right away, see `flush_pending_changes`
+    pub fn queue_change(&mut self, url: Url, project: &Project) {
+        if self.validate_on == ValidateOn::Save {
+            if !self.unsaved_changes.contains(&url) {
+                self.unsaved_changes.push(url);
+            }
+        } else if self.debounce.is_zero() {
+            self.on_change(vec![url], project);
+        } else if !self.pending_changes.contains(&url) {
+            self.pending_changes.push(url);
+        }
+    }
+
+    /// Saving can also mean files on disk changed, so this always validates
+    pub fn on_save(&mut self, url: Url, project: &Project) {
+        let mut changed_paths = std::mem::take(&mut self.unsaved_changes);
+        if !changed_paths.contains(&url) {
+            changed_paths.push(url);
+        }
+        self.on_change(changed_paths, project);
+    }
+
+    pub fn flush_pending_changes(&mut self, project: &Project) {
+        if self.has_pending_changes() {
+            self.on_change(vec![], project);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::Connection;
+
+    use crate::{
+        project::ProjectFile,
+        ship_log::ShipLogValidator,
+        utils::error_codes::{self, get_error_code},
+    };
+
+    use super::*;
+
+    fn error(code: &str) -> (VersionedTextDocumentIdentifier, Diagnostic) {
+        (
+            VersionedTextDocumentIdentifier::new(Url::parse("file://test.xml").unwrap(), 0),
+            Diagnostic {
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: get_error_code(code),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_validation_config() {
+        let options: ValidationOptions = serde_json::from_str(
+            r#"{ "rules": { "nh.shiplog.vanilla_id": "off", "nh.shiplog.missing_curiosity": "warning" } }"#,
+        )
+        .unwrap();
+        assert!(options.lint_unreachable_entries);
+
+        let errors = options.rules.apply(vec![
+            error(error_codes::SHIPLOG_VANILLA_ID),
+            error(error_codes::SHIPLOG_MISSING_CURIOSITY),
+            error(error_codes::SHIPLOG_DUPLICATE_ID),
+        ]);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].1.code,
+            get_error_code(error_codes::SHIPLOG_MISSING_CURIOSITY)
+        );
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(errors[1].1.severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_validation_config_skips_invalid_rules() {
+        let options: ValidationOptions = serde_json::from_str(
+            r#"{ "debounceMs": 50, "rules": { "nh.shiplog.unused_curiosity": "info", "nh.shiplog.empty_id": "hint", "nh.shiplog.duplicate_ids": "loud" } }"#,
+        )
+        .unwrap();
+        assert_eq!(options.debounce_ms, 50);
+        assert_eq!(options.rules.0.len(), 2);
+
+        let errors = options.rules.apply(vec![
+            error(error_codes::SHIPLOG_UNUSED_CURIOSITY),
+            error(error_codes::SHIPLOG_EMPTY_ID),
+            error(error_codes::SHIPLOG_DUPLICATE_ID),
+        ]);
+        assert_eq!(errors[0].1.severity, Some(DiagnosticSeverity::INFORMATION));
+        assert_eq!(errors[1].1.severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(errors[2].1.severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_disabled_validators() {
+        let options: ValidationOptions = serde_json::from_str(
+            r#"{ "disabledValidators": ["file_paths", "ship_log"], "useGitignore": false }"#,
+        )
+        .unwrap();
+        assert!(!options.is_validator_enabled("file_paths"));
+        assert!(options.is_validator_enabled("dialogue"));
+        assert_eq!(
+            MainValidator::new(&options, SchemaFetch::Blocking)
+                .validators
+                .len(),
+            6
+        );
+
+        let options: ValidationOptions = serde_json::from_str(
+            r#"{ "enabledValidators": ["json_syntax", "dialogue"], "disabledValidators": ["dialogue"] }"#,
+        )
+        .unwrap();
+        assert!(options.is_validator_enabled("json_syntax"));
+        assert!(!options.is_validator_enabled("dialogue"));
+        assert!(!options.is_validator_enabled("manifest"));
+        assert_eq!(
+            MainValidator::new(&options, SchemaFetch::Blocking)
+                .validators
+                .len(),
+            1
+        );
+    }
+
+    /// Reports every ship log that contains `ERROR`
+    struct ContainsErrorValidator();
+
+    impl Validator for ContainsErrorValidator {
+        fn name() -> &'static str {
+            "contains_error"
+        }
+
+        fn prepare() -> Self {
+            Self()
         }
 
-        project
-            .files_with_diagnostics
-            .retain(|f| !changed_paths.contains(&f.uri) || uris_with_diagnostics.contains(&f.uri));
+        fn should_invalidate(&self, _: &[Url], _: &Project) -> bool {
+            true
+        }
+
+        fn validate(&self, project: &Project) -> ErrorSet {
+            project
+                .ship_log_files
+                .iter()
+                .filter(|f| f.contents.contains("ERROR"))
+                .map(|f| (f.id.clone(), Diagnostic::default()))
+                .collect()
+        }
+    }
+
+    /// Like `ContainsErrorValidator`, but only looks at and re-runs for one file
+    struct OwnedFileValidator(Url);
+
+    impl Validator for OwnedFileValidator {
+        fn name() -> &'static str {
+            "owned_file"
+        }
+
+        fn prepare() -> Self {
+            Self(Url::parse("file:///owned.xml").unwrap())
+        }
+
+        fn should_invalidate(&self, changed_paths: &[Url], _: &Project) -> bool {
+            changed_paths.contains(&self.0)
+        }
+
+        fn validate(&self, project: &Project) -> ErrorSet {
+            project
+                .ship_log_files
+                .iter()
+                .filter(|f| f.id.uri == self.0 && f.contents.contains("ERROR"))
+                .map(|f| (f.id.clone(), Diagnostic::default()))
+                .collect()
+        }
+    }
+
+    fn published(client: &Connection) -> Vec<PublishDiagnosticsParams> {
+        client
+            .receiver
+            .try_iter()
+            .filter_map(|msg| match msg {
+                Message::Notification(not) if not.method == PublishDiagnostics::METHOD => {
+                    serde_json::from_value(not.params).ok()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clear_fixed_file() {
+        let (connection, client) = Connection::memory();
+        let mut validator = MainValidator {
+            validators: vec![Box::new(ContainsErrorValidator::prepare())],
+            ..Default::default()
+        };
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into(), ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        let (broken, other) = (
+            Url::parse("file:///broken.xml").unwrap(),
+            Url::parse("file:///other.xml").unwrap(),
+        );
+        Arc::make_mut(&mut project.ship_log_files[0]).id =
+            VersionedTextDocumentIdentifier::new(broken.clone(), 1);
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "ERROR".to_string();
+        Arc::make_mut(&mut project.ship_log_files[1]).id =
+            VersionedTextDocumentIdentifier::new(other.clone(), 0);
+
+        validator.force_validate(&connection.sender, &project);
+        let params = published(&client);
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].uri, broken);
+        assert_eq!(params[0].diagnostics.len(), 1);
+
+        // Fixed in a later version, and the change comes in for another file
+        Arc::make_mut(&mut project.ship_log_files[0]).id.version = 2;
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "FIXED".to_string();
+        validator.on_change(&connection.sender, vec![other.clone()], &project);
+        let params = published(&client);
+        let cleared = params.iter().find(|p| p.uri == broken).unwrap();
+        assert!(cleared.diagnostics.is_empty());
+        assert_eq!(cleared.version, Some(2));
+        assert!(validator.published.is_empty());
+    }
+
+    #[test]
+    fn test_send_project_stats() {
+        let (connection, client) = Connection::memory();
+        let mut validator = MainValidator {
+            validators: vec![Box::new(ContainsErrorValidator::prepare())],
+            ..Default::default()
+        };
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "ERROR".to_string();
+
+        let stats = |client: &Connection| -> Vec<ProjectStats> {
+            client
+                .receiver
+                .try_iter()
+                .filter_map(|msg| match msg {
+                    Message::Notification(not)
+                        if not.method == ProjectStatsNotification::METHOD =>
+                    {
+                        serde_json::from_value(not.params).ok()
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        validator.force_validate(&connection.sender, &project);
+        let sent = stats(&client);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].ship_log_files, 1);
+        assert_eq!(sent[0].files_with_diagnostics, 1);
+        assert_eq!(validator.files_with_diagnostics.load(Ordering::Relaxed), 1);
+
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "FIXED".to_string();
+        let uri = project.ship_log_files[0].id.uri.clone();
+        validator.on_change(&connection.sender, vec![uri], &project);
+        let sent = stats(&client);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].files_with_diagnostics, 0);
+        assert_eq!(validator.files_with_diagnostics.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_ignore_unrelated_changes() {
+        let (connection, client) = Connection::memory();
+        let owned = Url::parse("file:///owned.xml").unwrap();
+        let mut validator = MainValidator {
+            validators: vec![Box::new(OwnedFileValidator(owned.clone()))],
+            ..Default::default()
+        };
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::new(owned, 0, "ERROR".to_string()).into()],
+            ..Default::default()
+        };
+        validator.force_validate(&connection.sender, &project);
+        assert_eq!(published(&client).len(), 1);
+
+        // Neither of these were ever in the project, so the validator doesn't run again
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "FIXED".to_string();
+        let unrelated = vec![
+            Url::parse("file:///package.json").unwrap(),
+            Url::parse("file:///target/debug/out.xml").unwrap(),
+        ];
+        validator.on_change(&connection.sender, unrelated, &project);
+        assert_eq!(validator.published.len(), 1);
+    }
+
+    #[test]
+    fn test_rerun_after_file_deleted() {
+        let (connection, client) = Connection::memory();
+        let mut validator = MainValidator {
+            validators: vec![Box::new(ShipLogValidator {
+                lint_unreachable_entries: false,
+            })],
+            ..Default::default()
+        };
+        let ship_log = |name: &str| {
+            ProjectFile::new(
+                Url::parse(&format!("file:///mod/planets/{name}.xml")).unwrap(),
+                0,
+                "<AstroObjectEntry><ID>PLANET</ID><Entry><ID>ENTRY</ID></Entry></AstroObjectEntry>"
+                    .to_string(),
+            )
+        };
+        let mut project = Project {
+            ship_log_files: vec![ship_log("A").into(), ship_log("B").into()],
+            ..Default::default()
+        };
+        project.rebuild_ship_logs();
+        let (a, b) = (
+            project.ship_log_files[0].id.uri.clone(),
+            project.ship_log_files[1].id.uri.clone(),
+        );
+        let duplicates = |params: &PublishDiagnosticsParams| {
+            params
+                .diagnostics
+                .iter()
+                .filter(|d| d.message.starts_with("Duplicate"))
+                .count()
+        };
+
+        validator.force_validate(&connection.sender, &project);
+        let params = published(&client);
+        assert!(params.iter().all(|p| duplicates(p) == 2));
+
+        // The watcher takes the file out of the project before validation runs
+        project.remove_file(&b);
+        validator.on_change(&connection.sender, vec![b.clone()], &project);
+        let params = published(&client);
+        let for_file = |uri: &Url| params.iter().find(|p| &p.uri == uri).unwrap();
+        assert!(for_file(&b).diagnostics.is_empty());
+        assert_eq!(duplicates(for_file(&a)), 0);
+        assert!(validator
+            .last_errors
+            .iter()
+            .all(|(_, errors)| errors.iter().all(|(id, _)| id.uri != b)));
+    }
+
+    #[test]
+    fn test_keep_diagnostics_of_validators_not_rerun() {
+        let (connection, client) = Connection::memory();
+        let (a, b) = (
+            Url::parse("file:///a.xml").unwrap(),
+            Url::parse("file:///b.xml").unwrap(),
+        );
+        let mut validator = MainValidator {
+            validators: vec![
+                Box::new(OwnedFileValidator(a.clone())),
+                Box::new(OwnedFileValidator(b.clone())),
+            ],
+            ..Default::default()
+        };
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into(), ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        for (file, uri) in project.ship_log_files.iter_mut().zip([&a, &b]) {
+            *file = ProjectFile::new(uri.clone(), 0, "ERROR".to_string()).into();
+        }
+
+        validator.force_validate(&connection.sender, &project);
+        assert_eq!(published(&client).len(), 2);
+
+        // Only B re-runs, A's diagnostics have to stay put
+        Arc::make_mut(&mut project.ship_log_files[1]).contents = "FIXED".to_string();
+        validator.on_change(&connection.sender, vec![b.clone()], &project);
+        let params = published(&client);
+        assert!(params
+            .iter()
+            .find(|p| p.uri == b)
+            .unwrap()
+            .diagnostics
+            .is_empty());
+        assert!(params
+            .iter()
+            .filter(|p| p.uri == a)
+            .all(|p| p.diagnostics.len() == 1));
+        assert_eq!(validator.published.len(), 1);
+        assert_eq!(validator.published[0].uri, a);
+    }
+
+    #[test]
+    fn test_group_diagnostics() {
+        let diagnostic = |uri: &str, version: i32, message: &str| {
+            (
+                VersionedTextDocumentIdentifier::new(Url::parse(uri).unwrap(), version),
+                Diagnostic {
+                    message: message.to_string(),
+                    ..Default::default()
+                },
+            )
+        };
+        let grouped = MainValidator::group_diagnostics(vec![
+            diagnostic("file:///b.xml", 1, "b1"),
+            diagnostic("file:///a.xml", 0, "a1"),
+            diagnostic("file:///b.xml", 3, "b2"),
+            diagnostic("file:///c.xml", 2, "c1"),
+            diagnostic("file:///a.xml", 0, "a2"),
+        ]);
+        assert_eq!(grouped.len(), 3);
+        for (uri, (version, diagnostics)) in grouped.iter() {
+            let name = uri.path().trim_start_matches('/').trim_end_matches(".xml");
+            assert!(diagnostics.iter().all(|d| d.message.starts_with(name)));
+            let expected_version = match name {
+                "b" => 3,
+                "c" => 2,
+                _ => 0,
+            };
+            assert_eq!(*version, expected_version);
+        }
+        assert_eq!(grouped[&Url::parse("file:///a.xml").unwrap()].1.len(), 2);
+        assert_eq!(grouped[&Url::parse("file:///b.xml").unwrap()].1.len(), 2);
+    }
+
+    #[test]
+    fn test_force_validate_clears_fixed_file() {
+        let (connection, client) = Connection::memory();
+        let mut validator = MainValidator {
+            validators: vec![Box::new(ContainsErrorValidator::prepare())],
+            ..Default::default()
+        };
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "ERROR".to_string();
+
+        validator.force_validate(&connection.sender, &project);
+        assert_eq!(published(&client)[0].diagnostics.len(), 1);
+
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "FIXED".to_string();
+        validator.force_validate(&connection.sender, &project);
+        let params = published(&client);
+        assert_eq!(params.len(), 1);
+        assert!(params[0].diagnostics.is_empty());
+        assert!(validator.published.is_empty());
+    }
+
+    #[test]
+    fn test_queue_change() {
+        let (connection, client) = Connection::memory();
+        let mut worker = ValidationWorker::spawn(
+            Duration::from_millis(300),
+            connection.sender.clone(),
+            |_| MainValidator::default(),
+        );
+        let project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        let url = project.ship_log_files[0].id.uri.clone();
+
+        worker.queue_change(url.clone(), &project);
+        worker.queue_change(url, &project);
+        assert!(worker.has_pending_changes());
+        assert!(client.receiver.try_recv().is_err());
+
+        worker.flush_pending_changes(&project);
+        assert!(!worker.has_pending_changes());
+        // The worker clears the file's diagnostics since nothing was found
+        assert!(client.receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_revalidate() {
+        let (connection, client) = Connection::memory();
+        let (send_revalidate, revalidate) = mpsc::channel();
+        let mut worker =
+            ValidationWorker::spawn(Duration::ZERO, connection.sender.clone(), move |r| {
+                send_revalidate.send(r).unwrap();
+                MainValidator {
+                    validators: vec![Box::new(ContainsErrorValidator::prepare())],
+                    ..Default::default()
+                }
+            });
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "ERROR".to_string();
+        worker.force_validate(&project);
+        let diagnostics = |client: &Connection| loop {
+            match client.receiver.recv_timeout(Duration::from_secs(5)) {
+                Ok(Message::Notification(not)) if not.method == PublishDiagnostics::METHOD => {
+                    break serde_json::from_value::<PublishDiagnosticsParams>(not.params).unwrap();
+                }
+                Ok(_) => continue,
+                Err(why) => panic!("Expected diagnostics: {why:?}"),
+            }
+        };
+        assert_eq!(diagnostics(&client).diagnostics.len(), 1);
+
+        // Nothing changed in the project, the last snapshot is validated again
+        revalidate.recv_timeout(Duration::from_secs(5)).unwrap()();
+        assert_eq!(diagnostics(&client).diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_configure() {
+        let (connection, client) = Connection::memory();
+        let mut worker = ValidationWorker::spawn(Duration::ZERO, connection.sender.clone(), |_| {
+            MainValidator::new(&ValidationOptions::default(), SchemaFetch::Blocking)
+        });
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents =
+            "<AstroObjectEntry>\n    <ID>FOO</Name>\n</AstroObjectEntry>".to_string();
+        project.rebuild_ship_logs();
+        let diagnostics = |client: &Connection| loop {
+            match client.receiver.recv_timeout(Duration::from_secs(5)) {
+                Ok(Message::Notification(not)) if not.method == PublishDiagnostics::METHOD => {
+                    break serde_json::from_value::<PublishDiagnosticsParams>(not.params).unwrap();
+                }
+                Ok(_) => continue,
+                Err(why) => panic!("Expected diagnostics: {why:?}"),
+            }
+        };
+        worker.force_validate(&project);
+        assert_eq!(diagnostics(&client).diagnostics.len(), 1);
+
+        let options: ValidationOptions = serde_json::from_str(
+            r#"{ "disabledValidators": ["ship_log"], "debounceMs": 50, "validateOn": "save" }"#,
+        )
+        .unwrap();
+        worker.configure(options);
+        assert_eq!(worker.debounce, Duration::from_millis(50));
+        assert_eq!(worker.validate_on, ValidateOn::Save);
+        // Configuring alone validates everything again with the new validators
+        assert!(diagnostics(&client).diagnostics.is_empty());
+
+        worker.configure(ValidationOptions::default());
+        assert_eq!(diagnostics(&client).diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_on_save() {
+        let (connection, client) = Connection::memory();
+        let mut worker = ValidationWorker::spawn(Duration::ZERO, connection.sender.clone(), |_| {
+            MainValidator {
+                validators: vec![Box::new(ContainsErrorValidator::prepare())],
+                ..Default::default()
+            }
+        });
+        worker.validate_on = ValidateOn::Save;
+        let mut project = Project {
+            ship_log_files: vec![ProjectFile::dummy().into()],
+            ..Default::default()
+        };
+        Arc::make_mut(&mut project.ship_log_files[0]).contents = "ERROR".to_string();
+        let url = project.ship_log_files[0].id.uri.clone();
+
+        worker.queue_change(url.clone(), &project);
+        assert!(!worker.has_pending_changes());
+        assert!(client
+            .receiver
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+
+        worker.on_save(url.clone(), &project);
+        let Ok(Message::Notification(not)) = client.receiver.recv_timeout(Duration::from_secs(5))
+        else {
+            panic!("Expected diagnostics after saving");
+        };
+        let params: PublishDiagnosticsParams = serde_json::from_value(not.params).unwrap();
+        assert_eq!(params.uri, url);
+        assert_eq!(params.diagnostics.len(), 1);
     }
 }
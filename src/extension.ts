@@ -16,7 +16,7 @@ import {
     LanguageClientOptions,
     ServerOptions
 } from "vscode-languageclient/node";
-import { ShipLogEntry } from "./types";
+import { ShipLogEntry, ShipLogLink, SystemEntries } from "./types";
 
 let client: LanguageClient;
 
@@ -57,7 +57,7 @@ async function activateServer(context: ExtensionContext) {
 
     const clientOptions: LanguageClientOptions = {
         outputChannel: window.createOutputChannel("New Horizons Language Server"),
-        documentSelector: [{ language: "xml" }, { language: "json" }],
+        documentSelector: [{ language: "xml" }, { language: "json" }, { language: "jsonc" }],
         synchronize: {
             fileEvents: workspace.createFileSystemWatcher("**")
         }
@@ -92,14 +92,13 @@ export async function activate(context: ExtensionContext) {
                 return;
             }
 
-            const entries: ShipLogEntry[] | null = await client.sendRequest(
-                "getEntriesForSystem",
-                chosenSystem
-            );
+            const systemEntries: SystemEntries = await client.sendRequest("getEntriesForSystem", {
+                system: chosenSystem
+            });
 
-            console.debug(entries);
+            console.debug(systemEntries);
 
-            if (!entries) {
+            if (!systemEntries.found) {
                 window.showErrorMessage(`No entries found for ${chosenSystem}`);
                 return;
             }
@@ -112,10 +111,14 @@ export async function activate(context: ExtensionContext) {
             );
 
             const makeEntryLi = (e: ShipLogEntry) => `<li>${e.name}</li>`;
+            const makeLinkLi = (l: ShipLogLink) =>
+                `<li>${l.source} → ${l.target}${l.isRumor ? " (rumor)" : ""}</li>`;
             const entriesHtml = `
                     <h1>Preview for ${chosenSystem}</h1>
                     <h2>Entries</h2>
-                    <ul>${entries.map(makeEntryLi).join("")}</ul>
+                    <ul>${systemEntries.entries.map(makeEntryLi).join("")}</ul>
+                    <h2>Links</h2>
+                    <ul>${systemEntries.links.map(makeLinkLi).join("")}</ul>
                 `;
 
             panel.webview.html = shipLogHtml.replace("<!-- ~~ -->", entriesHtml);
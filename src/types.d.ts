@@ -1,10 +1,70 @@
+import type { Location } from "vscode-languageclient";
+
 export type ShipLogEntry = {
     id: string;
     astroObject: string;
     position?: [number, number];
     name: string;
     parent?: string;
+    children: string[];
     isCuriosity: boolean;
     sources: string[];
+    exploreSources: string[];
     curiosity?: string;
+    external: boolean;
+};
+
+export type ShipLogLink = {
+    source: string;
+    target: string;
+    isRumor: boolean;
+};
+
+export type SystemEntries = {
+    found: boolean;
+    entries: ShipLogEntry[];
+    links: ShipLogLink[];
+};
+
+export type ShipLogFact = {
+    id: string;
+    isRumor: boolean;
+    text: string;
+    rumorName?: string;
+    sourceId?: string;
+};
+
+export type EntryDetails = ShipLogEntry & {
+    facts: ShipLogFact[];
+    location: Location | null;
+};
+
+export type SystemFact = ShipLogFact & {
+    entry: string;
+};
+
+export type Color = {
+    r: number;
+    g: number;
+    b: number;
+    a: number;
+};
+
+export type Curiosity = {
+    id: string;
+    system: string;
+    color?: Color;
+    highlightColor?: Color;
+    entries: string[];
+};
+
+export type ProjectStats = {
+    planetFiles: number;
+    systemFiles: number;
+    shipLogFiles: number;
+    dialogueFiles: number;
+    textFiles: number;
+    entries: number;
+    facts: number;
+    filesWithDiagnostics: number;
 };